@@ -0,0 +1,505 @@
+use crate::deck::{Card, Deck, Rank, Suit};
+use crate::hand::{DealerHand, Hand, Score};
+use crate::rules::{DealerStoppingRule, DoubleRule, TableRules};
+use crate::strategy::Move;
+use std::collections::{BTreeMap, HashMap};
+
+const BLACKJACK: Score = Score(21);
+const RANKS: [Rank; 10] = [
+    Rank::Two,
+    Rank::Three,
+    Rank::Four,
+    Rank::Five,
+    Rank::Six,
+    Rank::Seven,
+    Rank::Eight,
+    Rank::Nine,
+    Rank::Ten,
+    Rank::Ace,
+];
+
+type Composition = [u32; 10];
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct DealerDistribution {
+    pub totals: BTreeMap<u8, f64>,
+    pub blackjack: f64,
+    pub bust: f64,
+}
+
+impl DealerDistribution {
+    pub fn total(&self, score: u8) -> f64 {
+        self.totals.get(&score).copied().unwrap_or(0.0)
+    }
+
+    pub fn without_blackjack(&self) -> DealerDistribution {
+        let remaining = 1.0 - self.blackjack;
+        if remaining <= 0.0 {
+            return DealerDistribution::default();
+        }
+        DealerDistribution {
+            totals: self
+                .totals
+                .iter()
+                .map(|(score, probability)| (*score, probability / remaining))
+                .collect(),
+            blackjack: 0.0,
+            bust: self.bust / remaining,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ExpectedValues {
+    pub stand: f64,
+    pub hit: f64,
+    pub double: Option<f64>,
+    pub split: Option<f64>,
+}
+
+impl ExpectedValues {
+    pub fn best(&self) -> (Move, f64) {
+        let choices = [
+            (Move::Stand, Some(self.stand)),
+            (Move::Hit, Some(self.hit)),
+            (Move::Double, self.double),
+            (Move::Split, self.split),
+        ];
+        choices
+            .iter()
+            .filter_map(|(choice, ev)| ev.map(|ev| (*choice, ev)))
+            .fold((Move::Stand, f64::NEG_INFINITY), |best, choice| {
+                if choice.1 > best.1 {
+                    choice
+                } else {
+                    best
+                }
+            })
+    }
+}
+
+pub fn dealer_distribution(
+    upcard: &Card,
+    deck: &Deck,
+    stopping_rule: &impl DealerStoppingRule,
+) -> DealerDistribution {
+    let mut composition = composition_of(deck);
+    let mut distribution = DealerDistribution::default();
+    let mut deal_hole_card = |hole_card: Card, probability: f64, composition: &mut Composition| {
+        let dealer_hand = DealerHand::new().add(hole_card).add(*upcard);
+        dealer_draws(
+            &dealer_hand,
+            composition,
+            probability,
+            stopping_rule,
+            &mut distribution,
+        );
+    };
+    draw_each(&mut composition, 1.0, &mut deal_hole_card);
+    distribution
+}
+
+// The player decides after the dealer has checked for blackjack, so every
+// value here is conditioned on the dealer not holding a natural. Splits are
+// played out once, without resplitting.
+pub fn expected_values(
+    hand: &Hand,
+    upcard: &Card,
+    deck: &Deck,
+    rules: &TableRules,
+) -> ExpectedValues {
+    let mut calculator = Calculator::new(upcard, rules);
+    let composition = composition_of(deck);
+    let cards = hand.cards();
+    let first_decision = cards.len() == 2;
+    let pair = first_decision && cards[0].rank == cards[1].rank;
+
+    ExpectedValues {
+        stand: calculator.stand(hand, &composition, first_decision),
+        hit: calculator.hit(hand, &composition),
+        double: if first_decision && calculator.can_double(hand, false) {
+            Some(calculator.double(hand, &composition))
+        } else {
+            None
+        },
+        split: if pair && rules.split_limit > 1 {
+            Some(calculator.split(cards[0], &composition))
+        } else {
+            None
+        },
+    }
+}
+
+struct Calculator<'a> {
+    upcard: Card,
+    rules: &'a TableRules,
+    dealer: HashMap<Composition, DealerDistribution>,
+    best_play: HashMap<(Composition, Composition), f64>,
+}
+
+impl<'a> Calculator<'a> {
+    fn new(upcard: &Card, rules: &'a TableRules) -> Self {
+        Calculator {
+            upcard: *upcard,
+            rules,
+            dealer: HashMap::new(),
+            best_play: HashMap::new(),
+        }
+    }
+
+    fn dealer(&mut self, composition: &Composition) -> DealerDistribution {
+        if let Some(distribution) = self.dealer.get(composition) {
+            return distribution.clone();
+        }
+        let deck = deck_of(composition);
+        let distribution =
+            dealer_distribution(&self.upcard, &deck, &self.rules.dealer).without_blackjack();
+        self.dealer.insert(*composition, distribution.clone());
+        distribution
+    }
+
+    fn stand(&mut self, hand: &Hand, composition: &Composition, natural_pays: bool) -> f64 {
+        let score = hand.score();
+        if score > BLACKJACK {
+            return -1.0;
+        }
+        if natural_pays && hand.cards().len() == 2 && score == BLACKJACK {
+            let (numerator, denominator) = self.rules.blackjack_payout.ratio();
+            return numerator as f64 / denominator as f64;
+        }
+
+        let distribution = self.dealer(composition);
+        distribution
+            .totals
+            .iter()
+            .fold(
+                distribution.bust,
+                |ev, (dealer_score, probability)| match Score(*dealer_score) {
+                    dealer_score if dealer_score < score => ev + probability,
+                    dealer_score if dealer_score > score => ev - probability,
+                    _ => ev,
+                },
+            )
+    }
+
+    fn hit(&mut self, hand: &Hand, composition: &Composition) -> f64 {
+        let mut composition = *composition;
+        let mut ev = 0.0;
+        draw_each(
+            &mut composition,
+            1.0,
+            &mut |card, probability, composition| {
+                ev += probability * self.best_play(&hand.add(card), composition);
+            },
+        );
+        ev
+    }
+
+    fn best_play(&mut self, hand: &Hand, composition: &Composition) -> f64 {
+        if hand.score() >= BLACKJACK {
+            return self.stand(hand, composition, false);
+        }
+        let key = (*composition, composition_of_hand(hand));
+        if let Some(ev) = self.best_play.get(&key) {
+            return *ev;
+        }
+        let ev = self
+            .stand(hand, composition, false)
+            .max(self.hit(hand, composition));
+        self.best_play.insert(key, ev);
+        ev
+    }
+
+    fn can_double(&self, hand: &Hand, split: bool) -> bool {
+        let total_allowed = match self.rules.double_rule {
+            DoubleRule::AnyTwoCards => true,
+            DoubleRule::NineToEleven => Score(9) <= hand.score() && hand.score() <= Score(11),
+        };
+        total_allowed && (self.rules.double_after_split || !split)
+    }
+
+    fn double(&mut self, hand: &Hand, composition: &Composition) -> f64 {
+        let mut composition = *composition;
+        let mut ev = 0.0;
+        draw_each(
+            &mut composition,
+            1.0,
+            &mut |card, probability, composition| {
+                ev += probability * 2.0 * self.stand(&hand.add(card), composition, false);
+            },
+        );
+        ev
+    }
+
+    fn split(&mut self, card: Card, composition: &Composition) -> f64 {
+        let mut composition = *composition;
+        let split_aces = card.rank == Rank::Ace && !self.rules.hit_split_aces;
+        let mut ev = 0.0;
+        draw_each(
+            &mut composition,
+            1.0,
+            &mut |dealt_card, probability, composition| {
+                let hand = Hand::new().add(card).add(dealt_card);
+                let played = if split_aces {
+                    self.stand(&hand, composition, false)
+                } else {
+                    let mut best = self.best_play(&hand, composition);
+                    if hand.score() < BLACKJACK && self.can_double(&hand, true) {
+                        best = best.max(self.double(&hand, composition));
+                    }
+                    best
+                };
+                ev += probability * played;
+            },
+        );
+        2.0 * ev
+    }
+}
+
+fn dealer_draws(
+    dealer_hand: &DealerHand,
+    composition: &mut Composition,
+    probability: f64,
+    stopping_rule: &impl DealerStoppingRule,
+    distribution: &mut DealerDistribution,
+) {
+    let score = dealer_hand.score();
+    if dealer_hand.cards().len() == 2 && score == BLACKJACK {
+        distribution.blackjack += probability;
+    } else if score > BLACKJACK {
+        distribution.bust += probability;
+    } else if !stopping_rule.hits(dealer_hand.total()) || remaining(composition) == 0 {
+        *distribution.totals.entry(score.0).or_insert(0.0) += probability;
+    } else {
+        draw_each(
+            composition,
+            probability,
+            &mut |card, probability, composition| {
+                dealer_draws(
+                    &dealer_hand.add(card),
+                    composition,
+                    probability,
+                    stopping_rule,
+                    distribution,
+                )
+            },
+        );
+    }
+}
+
+fn draw_each<F>(composition: &mut Composition, probability: f64, draw: &mut F)
+where
+    F: FnMut(Card, f64, &mut Composition),
+{
+    let cards = remaining(composition);
+    if cards == 0 {
+        return;
+    }
+    for index in 0..RANKS.len() {
+        let count = composition[index];
+        if count == 0 {
+            continue;
+        }
+        composition[index] -= 1;
+        draw(
+            card_of(RANKS[index]),
+            probability * count as f64 / cards as f64,
+            composition,
+        );
+        composition[index] += 1;
+    }
+}
+
+fn remaining(composition: &Composition) -> u32 {
+    composition.iter().sum()
+}
+
+fn index_of(rank: Rank) -> usize {
+    match rank {
+        Rank::Ace => 9,
+        _ => rank.to_value().0 as usize - 2,
+    }
+}
+
+fn card_of(rank: Rank) -> Card {
+    Card {
+        rank,
+        suit: Suit::Heart,
+    }
+}
+
+fn composition_of(deck: &Deck) -> Composition {
+    deck.cards.iter().fold([0; 10], |mut composition, card| {
+        composition[index_of(card.rank)] += 1;
+        composition
+    })
+}
+
+fn composition_of_hand(hand: &Hand) -> Composition {
+    composition_of(&Deck::new_with_cards(hand.cards()))
+}
+
+fn deck_of(composition: &Composition) -> Deck {
+    Deck::new_with_cards(
+        RANKS
+            .iter()
+            .zip(composition.iter())
+            .flat_map(|(rank, count)| (0..*count).map(move |_| card_of(*rank)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::DealerRule;
+    use im::Vector;
+
+    fn card(rank: Rank) -> Card {
+        card_of(rank)
+    }
+
+    fn hand(ranks: Vec<Rank>) -> Hand {
+        ranks
+            .into_iter()
+            .fold(Hand::new(), |hand, rank| hand.add(card(rank)))
+    }
+
+    fn shoe_without(decks: usize, dealt: Vec<Rank>) -> Deck {
+        let mut cards: Vector<Card> = (0..decks)
+            .flat_map(|_| Deck::standard_deck().cards)
+            .collect();
+        for rank in dealt {
+            let index = cards
+                .iter()
+                .position(|card| card.rank == rank)
+                .expect("card is in the shoe");
+            cards.remove(index);
+        }
+        Deck::new_with_cards(cards)
+    }
+
+    fn only(rank: Rank, count: usize) -> Deck {
+        Deck::new_with_cards((0..count).map(|_| card(rank)).collect())
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() < tolerance,
+            "expected {} to be within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn the_dealer_distribution_adds_up_to_one() {
+        let deck = shoe_without(1, vec![Rank::Ten, Rank::Seven, Rank::Six]);
+
+        let distribution =
+            dealer_distribution(&card(Rank::Six), &deck, &DealerRule::StandsOnSoft17);
+
+        let total: f64 =
+            distribution.totals.values().sum::<f64>() + distribution.bust + distribution.blackjack;
+        assert_close(total, 1.0, 1e-9);
+        assert_eq!(distribution.blackjack, 0.0);
+    }
+
+    #[test]
+    fn a_six_busts_about_forty_two_percent_of_the_time_in_a_big_shoe() {
+        let deck = shoe_without(8, vec![Rank::Six]);
+
+        let distribution =
+            dealer_distribution(&card(Rank::Six), &deck, &DealerRule::StandsOnSoft17);
+
+        assert_close(distribution.bust, 0.42, 0.01);
+    }
+
+    #[test]
+    fn the_dealer_follows_the_soft_seventeen_rule() {
+        let deck = only(Rank::Six, 4);
+
+        let s17 = dealer_distribution(&card(Rank::Ace), &deck, &DealerRule::StandsOnSoft17);
+        let h17 = dealer_distribution(&card(Rank::Ace), &deck, &DealerRule::HitsSoft17);
+
+        assert_eq!(s17.total(17), 1.0);
+        assert_eq!(h17.total(17), 0.0);
+        assert_eq!(h17.total(19), 1.0);
+    }
+
+    #[test]
+    fn an_ace_up_shows_the_chance_of_a_dealer_blackjack() {
+        let mut cards = only(Rank::Ten, 1).cards;
+        cards.push_back(card(Rank::Nine));
+
+        let distribution = dealer_distribution(
+            &card(Rank::Ace),
+            &Deck::new_with_cards(cards),
+            &DealerRule::StandsOnSoft17,
+        );
+
+        assert_eq!(distribution.blackjack, 0.5);
+        assert_eq!(distribution.total(20), 0.5);
+        assert_eq!(distribution.without_blackjack().total(20), 1.0);
+    }
+
+    #[test]
+    fn standing_on_twenty_against_a_dealer_who_must_make_seventeen_wins() {
+        let values = expected_values(
+            &hand(vec![Rank::Ten, Rank::Ten]),
+            &card(Rank::Ten),
+            &only(Rank::Seven, 4),
+            &TableRules::default(),
+        );
+
+        assert_eq!(values.stand, 1.0);
+        assert_eq!(values.hit, -1.0);
+        assert_eq!(values.double, Some(-2.0));
+        assert_eq!(values.split, Some(0.0));
+    }
+
+    #[test]
+    fn hard_sixteen_against_a_ten_is_a_big_underdog() {
+        let deck = shoe_without(6, vec![Rank::Ten, Rank::Six, Rank::Ten]);
+
+        let values = expected_values(
+            &hand(vec![Rank::Ten, Rank::Six]),
+            &card(Rank::Ten),
+            &deck,
+            &TableRules::default(),
+        );
+
+        assert_close(values.stand, -0.54, 0.02);
+        assert!(values.hit > values.stand);
+        assert_eq!(values.split, None);
+    }
+
+    #[test]
+    fn eleven_against_a_six_should_double() {
+        let deck = shoe_without(6, vec![Rank::Six, Rank::Five, Rank::Six]);
+
+        let values = expected_values(
+            &hand(vec![Rank::Six, Rank::Five]),
+            &card(Rank::Six),
+            &deck,
+            &TableRules::default(),
+        );
+
+        assert_eq!(values.best().0, Move::Double);
+    }
+
+    #[test]
+    fn a_natural_pays_the_table_blackjack_rate() {
+        let deck = shoe_without(1, vec![Rank::Ace, Rank::King, Rank::Nine]);
+
+        let values = expected_values(
+            &hand(vec![Rank::Ace, Rank::King]),
+            &card(Rank::Nine),
+            &deck,
+            &TableRules::default(),
+        );
+
+        assert_eq!(values.stand, 1.5);
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::ops::Add;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BankrollError {
+    #[error("Not enough chips to cover the wager")]
+    InsufficientFunds,
+}
+
+pub const STARTING_CHIPS: Chips = Chips(1000);
+
+#[derive(Clone, Copy, PartialEq, Debug, PartialOrd, Eq, Ord, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Chips(pub u32);
+
+impl Chips {
+    pub fn scale(self, numerator: u32, denominator: u32) -> Chips {
+        Chips(self.0 * numerator / denominator)
+    }
+}
+
+impl Add for Chips {
+    type Output = Chips;
+
+    fn add(self, other: Chips) -> Chips {
+        Chips(self.0 + other.0)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Bankroll {
+    balance: Chips,
+}
+
+impl Bankroll {
+    pub fn new(balance: Chips) -> Self {
+        Bankroll { balance }
+    }
+
+    pub fn balance(&self) -> Chips {
+        self.balance
+    }
+
+    pub fn withdraw(&self, amount: Chips) -> Result<Bankroll, BankrollError> {
+        if amount > self.balance {
+            return Err(BankrollError::InsufficientFunds);
+        }
+        Ok(Bankroll::new(Chips(self.balance.0 - amount.0)))
+    }
+
+    pub fn deposit(&self, amount: Chips) -> Bankroll {
+        Bankroll::new(self.balance + amount)
+    }
+}
+
+impl Default for Bankroll {
+    fn default() -> Self {
+        Bankroll::new(STARTING_CHIPS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_new_bankroll_starts_with_the_starting_chips() {
+        assert_eq!(Bankroll::default().balance(), STARTING_CHIPS);
+    }
+
+    #[test]
+    fn withdrawing_removes_chips_from_the_balance() -> Result<(), BankrollError> {
+        let bankroll = Bankroll::new(Chips(100)).withdraw(Chips(40))?;
+
+        assert_eq!(bankroll.balance(), Chips(60));
+        Ok(())
+    }
+
+    #[test]
+    fn cannot_withdraw_more_than_the_balance() {
+        let result = Bankroll::new(Chips(10)).withdraw(Chips(11));
+
+        assert!(result.is_err(), "Bankroll cannot go negative");
+    }
+
+    #[test]
+    fn depositing_adds_chips_to_the_balance() {
+        let bankroll = Bankroll::new(Chips(100)).deposit(Chips(25));
+
+        assert_eq!(bankroll.balance(), Chips(125));
+    }
+
+    #[test]
+    fn scaling_chips_rounds_down() {
+        assert_eq!(Chips(10).scale(3, 2), Chips(15));
+        assert_eq!(Chips(5).scale(3, 2), Chips(7));
+    }
+}
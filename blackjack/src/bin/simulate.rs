@@ -0,0 +1,100 @@
+use blackjack::policy::{policy_named, POLICY_NAMES};
+use blackjack::rules::{
+    BlackjackPayout, CharlieRule, DealerRule, HoleCardRule, SurrenderRule, TableRules,
+};
+use blackjack::simulation::simulate;
+use std::env;
+use std::error::Error;
+
+const DEFAULT_ROUNDS: u64 = 1_000_000;
+
+fn value_of<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .and_then(|value| value.parse().ok())
+}
+
+fn has_flag(args: &[String], flag: &str) -> bool {
+    args.iter().any(|arg| arg == flag)
+}
+
+fn rules_from_args(args: &[String]) -> TableRules {
+    let defaults = if has_flag(args, "--spanish21") {
+        TableRules::spanish_21()
+    } else if has_flag(args, "--switch") {
+        TableRules::blackjack_switch()
+    } else if has_flag(args, "--pontoon") {
+        TableRules::pontoon()
+    } else if has_flag(args, "--double-exposure") {
+        TableRules::double_exposure()
+    } else {
+        TableRules::default()
+    };
+    TableRules {
+        dealer: if has_flag(args, "--h17") {
+            DealerRule::HitsSoft17
+        } else {
+            defaults.dealer
+        },
+        decks: value_of(args, "--decks").unwrap_or(defaults.decks),
+        penetration: value_of(args, "--penetration").unwrap_or(defaults.penetration),
+        blackjack_payout: if has_flag(args, "--six-five") {
+            BlackjackPayout::SixToFive
+        } else {
+            defaults.blackjack_payout
+        },
+        double_after_split: defaults.double_after_split && !has_flag(args, "--no-das"),
+        surrender: if has_flag(args, "--no-surrender") {
+            SurrenderRule::NoSurrender
+        } else {
+            defaults.surrender
+        },
+        hole_card: if has_flag(args, "--obo") {
+            HoleCardRule::OriginalBetsOnly
+        } else if has_flag(args, "--enhc") {
+            HoleCardRule::NoHoleCard
+        } else {
+            defaults.hole_card
+        },
+        charlie: match value_of::<usize>(args, "--charlie") {
+            Some(5) => CharlieRule::FiveCard,
+            Some(6) => CharlieRule::SixCard,
+            Some(7) => CharlieRule::SevenCard,
+            _ => defaults.charlie,
+        },
+        ..defaults
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    let rounds = value_of(&args, "--hands").unwrap_or(DEFAULT_ROUNDS);
+    let seed = value_of(&args, "--seed").unwrap_or_else(rand::random);
+    let rules = rules_from_args(&args);
+    if let Err(error) = rules.validate() {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+    let name = value_of::<String>(&args, "--policy").unwrap_or_else(|| "basic".to_string());
+    let mut policy = match policy_named(&name, seed) {
+        Some(policy) => policy,
+        None => {
+            eprintln!(
+                "Unknown policy {}. Choose one of: {}",
+                name,
+                POLICY_NAMES.join(", ")
+            );
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Simulating {} hands of the {} policy with seed {}",
+        rounds, name, seed
+    );
+    println!("{:?}", rules);
+    let statistics = simulate(&mut policy, rules, seed, rounds)?;
+    print!("{}", statistics);
+    Ok(())
+}
@@ -0,0 +1,367 @@
+use crate::deck::{Card, Rank};
+use crate::game::Action;
+use im::Vector;
+use strum_macros::{Display, EnumIter};
+
+#[derive(Clone, Copy, PartialEq, Debug, EnumIter, Display)]
+pub enum CountingSystem {
+    #[strum(serialize = "Hi-Lo")]
+    HiLo,
+    #[strum(serialize = "KO")]
+    KnockOut,
+    #[strum(serialize = "Omega II")]
+    OmegaII,
+    #[strum(serialize = "Zen")]
+    Zen,
+}
+
+impl CountingSystem {
+    pub fn tag(self, rank: Rank) -> i32 {
+        match (self, rank.to_value().0) {
+            (CountingSystem::HiLo, 2..=6) => 1,
+            (CountingSystem::HiLo, 7..=9) => 0,
+            (CountingSystem::HiLo, _) => -1,
+            (CountingSystem::KnockOut, 2..=7) => 1,
+            (CountingSystem::KnockOut, 8..=9) => 0,
+            (CountingSystem::KnockOut, _) => -1,
+            (CountingSystem::OmegaII, 2) | (CountingSystem::OmegaII, 3) => 1,
+            (CountingSystem::OmegaII, 4..=6) => 2,
+            (CountingSystem::OmegaII, 7) => 1,
+            (CountingSystem::OmegaII, 8) | (CountingSystem::OmegaII, 11) => 0,
+            (CountingSystem::OmegaII, 9) => -1,
+            (CountingSystem::OmegaII, _) => -2,
+            (CountingSystem::Zen, 2) | (CountingSystem::Zen, 3) => 1,
+            (CountingSystem::Zen, 4..=6) => 2,
+            (CountingSystem::Zen, 7) => 1,
+            (CountingSystem::Zen, 8..=9) => 0,
+            (CountingSystem::Zen, 10) => -2,
+            (CountingSystem::Zen, _) => -1,
+        }
+    }
+
+    pub fn is_balanced(self) -> bool {
+        self != CountingSystem::KnockOut
+    }
+
+    // KO starts below zero so that its unbalanced count reaches the same
+    // pivot no matter how many decks are in the shoe.
+    pub fn initial_running_count(self, decks: usize) -> i32 {
+        if self.is_balanced() {
+            0
+        } else {
+            4 - 4 * decks as i32
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct CardCounter {
+    decks: usize,
+    seen: Vector<Card>,
+    on_table: Vector<Card>,
+}
+
+impl CardCounter {
+    pub fn new(decks: usize) -> Self {
+        CardCounter {
+            decks,
+            seen: Vector::new(),
+            on_table: Vector::new(),
+        }
+    }
+
+    // A reshuffle leaves the cards on the table out of the new shoe, so they
+    // stay counted. Dealing clears the table, which is why a reshuffle ahead
+    // of a new hand starts the count from nothing.
+    pub fn observe(&self, actions: &Vector<Action>) -> Self {
+        let dealing = actions
+            .iter()
+            .any(|action| matches!(action, Action::NewHand(_, _)));
+        let counter = if dealing {
+            CardCounter {
+                on_table: Vector::new(),
+                ..self.clone()
+            }
+        } else {
+            self.clone()
+        };
+        actions
+            .iter()
+            .fold(counter, |counter, action| match action {
+                Action::Reshuffle => CardCounter {
+                    seen: counter.on_table.clone(),
+                    ..counter
+                },
+                Action::NewHand(hands, dealer_hand) => hands
+                    .iter()
+                    .fold(counter, |counter, hand| counter.see(hand.cards()))
+                    .see(dealer_hand.visible_cards()),
+                Action::NewPlayerCard(_, card)
+                | Action::DoubleDown(_, _, card)
+                | Action::ShowDealerHoleCard(card)
+                | Action::ShowDealerUpcard(card) => counter.see(Vector::unit(*card)),
+                Action::NewDealerCards(cards) => counter.see(cards.clone()),
+                _ => counter,
+            })
+    }
+
+    pub fn cards_seen(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn running_count(&self, system: CountingSystem) -> i32 {
+        self.seen
+            .iter()
+            .fold(system.initial_running_count(self.decks), |count, card| {
+                count + system.tag(card.rank)
+            })
+    }
+
+    pub fn true_count(&self, system: CountingSystem, decks_remaining: f64) -> f64 {
+        let running_count = self.running_count(system) as f64;
+        if decks_remaining > 0.0 {
+            running_count / decks_remaining
+        } else {
+            running_count
+        }
+    }
+
+    fn see(&self, cards: Vector<Card>) -> Self {
+        CardCounter {
+            seen: self.seen.clone() + cards.clone(),
+            on_table: self.on_table.clone() + cards,
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bankroll::Chips;
+    use crate::deck::{Deck, Suit};
+    use crate::game::{bet, deal, decline_insurance, hit, stand, GameState, HandId};
+    use crate::hand::{DealerHand, Hand, Score};
+    use crate::rules::TableRules;
+    use im::vector;
+    use strum::IntoEnumIterator;
+
+    fn card(rank: Rank) -> Card {
+        Card {
+            rank,
+            suit: Suit::Heart,
+        }
+    }
+
+    #[test]
+    fn balanced_systems_count_a_full_deck_back_to_zero() {
+        for system in CountingSystem::iter().filter(|system| system.is_balanced()) {
+            let total: i32 = Deck::standard_deck()
+                .cards
+                .iter()
+                .map(|card| system.tag(card.rank))
+                .sum();
+            assert_eq!(total, 0, "{} is not balanced", system);
+        }
+    }
+
+    #[test]
+    fn ko_ends_a_full_shoe_at_plus_four() {
+        let counter = CardCounter::new(6);
+        let shoe: Vector<Card> = (0..6).flat_map(|_| Deck::standard_deck().cards).collect();
+
+        let counter = counter.see(shoe);
+
+        assert_eq!(counter.running_count(CountingSystem::KnockOut), 4);
+    }
+
+    #[test]
+    fn the_hole_card_is_not_counted_until_it_is_shown() {
+        let dealer_hand = DealerHand::new().add(card(Rank::Two)).add(card(Rank::King));
+        let hand = Hand::new().add(card(Rank::Five)).add(card(Rank::Six));
+
+        let counter = CardCounter::new(1).observe(&vector![Action::NewHand(
+            vector![hand],
+            dealer_hand.clone()
+        )]);
+        assert_eq!(counter.cards_seen(), 3);
+        assert_eq!(counter.running_count(CountingSystem::HiLo), 1);
+
+        let counter = counter.observe(&vector![Action::ShowDealerHoleCard(
+            *dealer_hand.hole_card().unwrap()
+        )]);
+        assert_eq!(counter.cards_seen(), 4);
+        assert_eq!(counter.running_count(CountingSystem::HiLo), 2);
+    }
+
+    #[test]
+    fn an_exposed_hole_card_is_counted_on_the_deal() {
+        let dealer_hand = DealerHand::exposed()
+            .add(card(Rank::Two))
+            .add(card(Rank::King));
+        let hand = Hand::new().add(card(Rank::Five)).add(card(Rank::Six));
+
+        let counter =
+            CardCounter::new(1).observe(&vector![Action::NewHand(vector![hand], dealer_hand)]);
+
+        assert_eq!(counter.cards_seen(), 4);
+        assert_eq!(counter.running_count(CountingSystem::HiLo), 2);
+    }
+
+    #[test]
+    fn a_concealed_dealer_hand_is_counted_as_it_is_turned_over() {
+        let dealer_hand = DealerHand::concealed()
+            .add(card(Rank::Two))
+            .add(card(Rank::King));
+
+        let counter = CardCounter::new(1).observe(&vector![Action::NewHand(
+            Vector::new(),
+            dealer_hand.clone()
+        )]);
+        assert_eq!(counter.cards_seen(), 0);
+
+        let counter = counter.observe(&vector![
+            Action::ShowDealerHoleCard(*dealer_hand.hole_card().unwrap()),
+            Action::ShowDealerUpcard(*dealer_hand.upcard().unwrap())
+        ]);
+        assert_eq!(counter.cards_seen(), 2);
+        assert_eq!(counter.running_count(CountingSystem::HiLo), 0);
+    }
+
+    #[test]
+    fn every_revealed_card_is_counted() {
+        let counter = CardCounter::new(1).observe(&vector![
+            Action::NewPlayerCard(HandId::new(0, 0), card(Rank::Ace)),
+            Action::DoubleDown(HandId::new(0, 0), Chips(20), card(Rank::Four)),
+            Action::NewDealerCards(vector![card(Rank::Five), card(Rank::Ten)]),
+            Action::WagerLost(HandId::new(0, 0), Chips(20))
+        ]);
+
+        assert_eq!(counter.cards_seen(), 4);
+        assert_eq!(counter.running_count(CountingSystem::HiLo), 0);
+        assert_eq!(counter.running_count(CountingSystem::OmegaII), 2);
+        assert_eq!(counter.running_count(CountingSystem::Zen), 1);
+    }
+
+    #[test]
+    fn a_reshuffle_before_the_deal_starts_the_count_over() {
+        let counter = CardCounter::new(2)
+            .observe(&vector![Action::NewPlayerCard(
+                HandId::new(0, 0),
+                card(Rank::Two)
+            )])
+            .observe(&vector![
+                Action::Reshuffle,
+                Action::NewHand(
+                    vector![Hand::new().add(card(Rank::Seven))],
+                    DealerHand::new()
+                )
+            ]);
+
+        assert_eq!(counter.cards_seen(), 1);
+        assert_eq!(counter.running_count(CountingSystem::HiLo), 0);
+        assert_eq!(counter.running_count(CountingSystem::KnockOut), -3);
+    }
+
+    #[test]
+    fn a_reshuffle_in_mid_round_keeps_the_cards_on_the_table() {
+        let counter = CardCounter::new(1)
+            .observe(&vector![Action::NewHand(
+                vector![Hand::new().add(card(Rank::Two)).add(card(Rank::Three))],
+                DealerHand::new().add(card(Rank::Ten)).add(card(Rank::King))
+            )])
+            .observe(&vector![
+                Action::Reshuffle,
+                Action::NewPlayerCard(HandId::new(0, 0), card(Rank::Four))
+            ]);
+
+        assert_eq!(counter.cards_seen(), 4);
+        assert_eq!(counter.running_count(CountingSystem::HiLo), 2);
+    }
+
+    #[test]
+    fn the_true_count_divides_by_the_decks_remaining() {
+        let counter = CardCounter::new(6).see(vector![
+            card(Rank::Two),
+            card(Rank::Three),
+            card(Rank::Four),
+            card(Rank::Five),
+            card(Rank::Six),
+            card(Rank::Two)
+        ]);
+
+        assert_eq!(counter.true_count(CountingSystem::HiLo, 2.0), 3.0);
+        assert_eq!(counter.true_count(CountingSystem::HiLo, 0.0), 6.0);
+    }
+
+    #[test]
+    fn a_played_round_counts_every_dealt_card_once() -> Result<(), Box<dyn std::error::Error>> {
+        let mut state = GameState::new(TableRules::default(), 11)?;
+        let mut counter = CardCounter::new(1);
+
+        for _ in 0..3 {
+            let (ready, _) = bet(&state, 0, Chips(10))?;
+            let (mut next, mut actions) = deal(&ready)?;
+            loop {
+                counter = counter.observe(&actions);
+                let (following, following_actions) = match next {
+                    GameState::OfferingInsurance(_) => decline_insurance(&next)?,
+                    GameState::WaitingForPlayer(_) => stand(&next)?,
+                    _ => break,
+                };
+                next = following;
+                actions = following_actions;
+            }
+            state = next;
+        }
+
+        let cards_dealt = 52 - (state.context().decks_remaining() * 52.0).round() as usize;
+        assert_eq!(counter.cards_seen(), cards_dealt);
+        Ok(())
+    }
+
+    #[test]
+    fn a_shoe_run_dry_in_mid_round_keeps_the_count_in_step(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let rules = TableRules {
+            penetration: 1.0,
+            ..TableRules::default()
+        };
+        let mut state = GameState::new(rules, 5)?;
+        let mut counter = CardCounter::new(1);
+        let mut reshuffled_in_play = false;
+
+        while !reshuffled_in_play {
+            let (ready, _) = bet(&state, 0, Chips(10))?;
+            let (mut next, mut actions) = deal(&ready)?;
+            loop {
+                reshuffled_in_play |= actions.contains(&Action::Reshuffle)
+                    && !actions
+                        .iter()
+                        .any(|action| matches!(action, Action::NewHand(_, _)));
+                counter = counter.observe(&actions);
+                let context = next.context();
+                let in_shoe = (context.decks_remaining() * 52.0).round() as usize;
+                let hidden = match next {
+                    GameState::WaitingForPlayer(_) | GameState::OfferingInsurance(_) => 1,
+                    _ => 0,
+                };
+                assert_eq!(counter.cards_seen() + in_shoe + hidden, 52);
+                let (following, following_actions) = match next {
+                    GameState::OfferingInsurance(_) => decline_insurance(&next)?,
+                    GameState::WaitingForPlayer(ref context)
+                        if context.player_hand().score() < Score(17) =>
+                    {
+                        hit(&next)?
+                    }
+                    GameState::WaitingForPlayer(_) => stand(&next)?,
+                    _ => break,
+                };
+                next = following;
+                actions = following_actions;
+            }
+            state = next;
+        }
+        Ok(())
+    }
+}
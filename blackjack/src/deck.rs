@@ -1,6 +1,9 @@
 use im::{vector, Vector};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, Rng, SeedableRng};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter};
 use thiserror::Error;
@@ -9,12 +12,20 @@ use thiserror::Error;
 pub enum DeckError {
     #[error("Tried to deal from an empty deck")]
     Empty,
+    #[error("A shoe needs at least one deck")]
+    NoDecks,
+    #[error("Penetration must be more than 0 and at most 1, not {0}")]
+    PenetrationError(f64),
 }
 
+pub const DEFAULT_DECKS: usize = 1;
+pub const DEFAULT_PENETRATION: f64 = 0.75;
+
 #[derive(PartialEq, Debug)]
 pub struct Value(pub u8);
 
 #[derive(Clone, Copy, PartialEq, Debug, EnumIter, Hash, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub enum Suit {
     Heart,
     Diamond,
@@ -23,6 +34,7 @@ pub enum Suit {
 }
 
 #[derive(PartialEq, Clone, Debug, Copy, EnumIter, Display, Hash, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub enum Rank {
     Two,
     Three,
@@ -57,12 +69,39 @@ impl Rank {
 }
 
 #[derive(Clone, Copy, PartialEq, Debug, Hash, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct Card {
     pub suit: Suit,
     pub rank: Rank,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum DeckComposition {
+    #[default]
+    Standard,
+    Spanish,
+}
+
+impl DeckComposition {
+    pub fn deck(self) -> Deck {
+        match self {
+            DeckComposition::Standard => Deck::standard_deck(),
+            DeckComposition::Spanish => Deck::spanish_deck(),
+        }
+    }
+
+    pub fn cards_per_deck(self) -> usize {
+        let ranks = match self {
+            DeckComposition::Standard => Rank::iter().count(),
+            DeckComposition::Spanish => Rank::iter().count() - 1,
+        };
+        ranks * Suit::iter().count()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct Deck {
     pub cards: Vector<Card>,
 }
@@ -82,10 +121,27 @@ impl Deck {
         Deck::new_with_cards(cards)
     }
 
+    pub fn spanish_deck() -> Self {
+        Deck::standard_deck().without(Rank::Ten)
+    }
+
+    pub fn without(&self, rank: Rank) -> Self {
+        Deck::new_with_cards(
+            self.cards
+                .iter()
+                .filter(|card| card.rank != rank)
+                .cloned()
+                .collect(),
+        )
+    }
+
     pub fn shuffle(&self) -> Self {
-        let mut rng = thread_rng();
+        self.shuffle_with(&mut thread_rng())
+    }
+
+    pub fn shuffle_with<R: Rng + ?Sized>(&self, rng: &mut R) -> Self {
         let mut cards_as_vec = self.cards_to_vec();
-        cards_as_vec.shuffle(&mut rng);
+        cards_as_vec.shuffle(rng);
         Self::new_with_cards(Vector::from(cards_as_vec))
     }
 
@@ -100,6 +156,126 @@ impl Deck {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Shoe {
+    pub cards: Vector<Card>,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    composition: DeckComposition,
+    decks: usize,
+    penetration: f64,
+    next_seed: u64,
+}
+
+impl Shoe {
+    pub fn new(decks: usize, penetration: f64, seed: u64) -> Result<Self, DeckError> {
+        Shoe::new_with_composition(DeckComposition::Standard, decks, penetration, seed)
+    }
+
+    pub fn new_with_composition(
+        composition: DeckComposition,
+        decks: usize,
+        penetration: f64,
+        seed: u64,
+    ) -> Result<Self, DeckError> {
+        if decks == 0 {
+            return Err(DeckError::NoDecks);
+        }
+        if !(penetration > 0.0 && penetration <= 1.0) {
+            return Err(DeckError::PenetrationError(penetration));
+        }
+        Ok(Shoe::shuffled(composition, decks, penetration, seed))
+    }
+
+    fn shuffled(composition: DeckComposition, decks: usize, penetration: f64, seed: u64) -> Self {
+        let cards = (0..decks).fold(vector!(), |cards, _| cards + composition.deck().cards);
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        Shoe {
+            cards: Deck::new_with_cards(cards).shuffle_with(&mut rng).cards,
+            composition,
+            decks,
+            penetration,
+            next_seed: rng.gen(),
+        }
+    }
+
+    pub fn new_with_cards(cards: Vector<Card>) -> Self {
+        Shoe {
+            cards,
+            composition: DeckComposition::Standard,
+            decks: 1,
+            penetration: 1.0,
+            next_seed: 0,
+        }
+    }
+
+    pub fn decks(&self) -> usize {
+        self.decks
+    }
+
+    pub fn decks_remaining(&self) -> f64 {
+        self.cards.len() as f64 / self.composition.cards_per_deck() as f64
+    }
+
+    pub fn deal(&self) -> Result<(Shoe, Card), DeckError> {
+        let mut shoe = self.clone();
+        let card = shoe.cards.pop_front().ok_or(DeckError::Empty)?;
+        Ok((shoe, card))
+    }
+
+    pub fn cut_card_reached(&self) -> bool {
+        let full_shoe = (self.decks * self.composition.cards_per_deck()) as f64;
+        let behind_cut_card = (full_shoe * (1.0 - self.penetration)).round() as usize;
+        self.cards.len() <= behind_cut_card
+    }
+
+    pub fn reshuffle(&self) -> Self {
+        Shoe::shuffled(
+            self.composition,
+            self.decks,
+            self.penetration,
+            self.next_seed,
+        )
+    }
+
+    // A round can outlast the cards behind the cut card. When it does the
+    // discards are shuffled back in, leaving out the cards still on the table,
+    // and the returned flag is set so the reshuffle can be announced.
+    pub fn deal_or_reshuffle(
+        &self,
+        in_play: impl FnOnce() -> Vec<Card>,
+    ) -> Result<(Shoe, Card, bool), DeckError> {
+        if self.cards.is_empty() {
+            let (shoe, card) = self.reshuffle_without(&in_play()).deal()?;
+            Ok((shoe, card, true))
+        } else {
+            let (shoe, card) = self.deal()?;
+            Ok((shoe, card, false))
+        }
+    }
+
+    fn reshuffle_without(&self, in_play: &[Card]) -> Self {
+        in_play.iter().fold(self.reshuffle(), |mut shoe, card| {
+            if let Some(index) = shoe.cards.index_of(card) {
+                shoe.cards.remove(index);
+            }
+            shoe
+        })
+    }
+}
+
+impl Default for Shoe {
+    fn default() -> Self {
+        Shoe::shuffled(
+            DeckComposition::Standard,
+            DEFAULT_DECKS,
+            DEFAULT_PENETRATION,
+            rand::random(),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -205,6 +381,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn a_spanish_deck_has_no_tens_but_keeps_the_faces() {
+        let deck = Deck::spanish_deck();
+
+        assert_eq!(deck.cards.len(), 48);
+        assert!(deck.cards.iter().all(|card| card.rank != Rank::Ten));
+        assert_eq!(
+            deck.cards
+                .iter()
+                .filter(|card| card.rank.to_value() == Value(10))
+                .count(),
+            12
+        );
+    }
+
+    #[test]
+    fn a_spanish_shoe_measures_decks_of_forty_eight_cards() -> Result<(), DeckError> {
+        let mut shoe = Shoe::new_with_composition(DeckComposition::Spanish, 2, 0.5, 1)?;
+        assert_eq!(shoe.cards.len(), 96);
+
+        for _ in 0..48 {
+            shoe = shoe.deal()?.0;
+        }
+        assert_eq!(shoe.decks_remaining(), 1.0);
+        assert!(shoe.cut_card_reached());
+        assert_eq!(shoe.reshuffle().cards.len(), 96);
+        Ok(())
+    }
+
     #[test]
     fn shuffle_reorders_the_deck_without_changing_entries() {
         let new_deck = Deck::standard_deck();
@@ -216,4 +421,140 @@ mod tests {
         let shuffled_deck_set = shuffled_deck.cards.into_iter().collect::<HashSet<Card>>();
         assert_eq!(new_deck_set, shuffled_deck_set);
     }
+
+    #[test]
+    fn a_shoe_holds_every_card_from_each_deck() -> Result<(), DeckError> {
+        let shoe = Shoe::new(6, DEFAULT_PENETRATION, 1)?;
+
+        assert_eq!(shoe.cards.len(), 6 * 52);
+        for card in Deck::standard_deck().cards {
+            let copies = shoe.cards.iter().filter(|dealt| **dealt == card).count();
+            assert_eq!(copies, 6);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn a_new_shoe_is_shuffled() -> Result<(), DeckError> {
+        let shoe = Shoe::new(1, DEFAULT_PENETRATION, 1)?;
+
+        assert_ne!(shoe.cards, Deck::standard_deck().cards);
+        Ok(())
+    }
+
+    #[test]
+    fn dealing_from_a_shoe_takes_the_top_card() -> Result<(), DeckError> {
+        let shoe = Shoe::new(2, DEFAULT_PENETRATION, 1)?;
+        let top_card = shoe.cards[0];
+
+        let (new_shoe, card) = shoe.deal()?;
+
+        assert_eq!(card, top_card);
+        assert_eq!(new_shoe.cards.len(), 103);
+        Ok(())
+    }
+
+    #[test]
+    fn the_cut_card_comes_out_after_the_penetration_is_dealt() -> Result<(), DeckError> {
+        let mut shoe = Shoe::new(1, 0.5, 1)?;
+
+        for _ in 0..25 {
+            shoe = shoe.deal()?.0;
+        }
+        assert!(!shoe.cut_card_reached());
+
+        shoe = shoe.deal()?.0;
+        assert!(shoe.cut_card_reached());
+        Ok(())
+    }
+
+    #[test]
+    fn decks_remaining_counts_partial_decks() -> Result<(), DeckError> {
+        let shoe = Shoe::new(2, DEFAULT_PENETRATION, 1)?;
+        assert_eq!(shoe.decks_remaining(), 2.0);
+
+        let mut shoe = shoe;
+        for _ in 0..26 {
+            shoe = shoe.deal()?.0;
+        }
+        assert_eq!(shoe.decks_remaining(), 1.5);
+        Ok(())
+    }
+
+    #[test]
+    fn reshuffling_refills_the_shoe() -> Result<(), DeckError> {
+        let (shoe, _) = Shoe::new(4, DEFAULT_PENETRATION, 1)?.deal()?;
+
+        let reshuffled = shoe.reshuffle();
+
+        assert_eq!(reshuffled.cards.len(), 4 * 52);
+        assert_eq!(reshuffled.decks(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn an_empty_shoe_reshuffles_around_the_cards_in_play() -> Result<(), DeckError> {
+        let in_play = vec![
+            Card {
+                rank: Rank::Ace,
+                suit: Suit::Spade,
+            },
+            Card {
+                rank: Rank::Ten,
+                suit: Suit::Heart,
+            },
+        ];
+        let empty = Shoe {
+            cards: vector!(),
+            ..Shoe::new(1, DEFAULT_PENETRATION, 1)?
+        };
+
+        let (shoe, card, reshuffled) = empty.deal_or_reshuffle(|| in_play.clone())?;
+
+        assert!(reshuffled);
+        assert_eq!(shoe.cards.len(), 52 - in_play.len() - 1);
+        assert!(!in_play.contains(&card));
+        assert!(in_play.iter().all(|card| !shoe.cards.contains(card)));
+        Ok(())
+    }
+
+    #[test]
+    fn shuffling_with_the_same_seed_gives_the_same_order() {
+        let deck = Deck::standard_deck();
+
+        let first = deck.shuffle_with(&mut StdRng::seed_from_u64(42));
+        let second = deck.shuffle_with(&mut StdRng::seed_from_u64(42));
+
+        assert_eq!(first, second);
+        assert_ne!(first, deck);
+    }
+
+    #[test]
+    fn shoes_with_the_same_seed_match() -> Result<(), DeckError> {
+        assert_eq!(Shoe::new(6, 0.75, 7)?, Shoe::new(6, 0.75, 7)?);
+        assert_ne!(Shoe::new(6, 0.75, 7)?.cards, Shoe::new(6, 0.75, 8)?.cards);
+        Ok(())
+    }
+
+    #[test]
+    fn reshuffles_are_reproducible_from_the_seed() -> Result<(), DeckError> {
+        let first = Shoe::new(2, 0.75, 3)?.reshuffle();
+        let second = Shoe::new(2, 0.75, 3)?.reshuffle();
+
+        assert_eq!(first, second);
+        assert_ne!(first.cards, Shoe::new(2, 0.75, 3)?.cards);
+        Ok(())
+    }
+
+    #[test]
+    fn a_shoe_needs_decks_and_a_cut_card_inside_them() {
+        assert!(matches!(Shoe::new(0, 0.75, 1), Err(DeckError::NoDecks)));
+        for penetration in &[0.0, -0.5, 1.5, f64::NAN] {
+            assert!(matches!(
+                Shoe::new(1, *penetration, 1),
+                Err(DeckError::PenetrationError(_))
+            ));
+        }
+        assert!(Shoe::new(1, 1.0, 1).is_ok());
+    }
 }
@@ -1,657 +1,4449 @@
-use crate::deck::{Card, Deck};
+use crate::bankroll::{Bankroll, Chips};
+use crate::deck::{Card, DeckError, Rank, Shoe};
 use crate::hand::{DealerHand, Hand, Score};
+use crate::rules::{
+    DealerStoppingRule, DoubleRule, HoleCardRule, PontoonRank, RulesError, SurrenderRule,
+    TableRules, TwentyOneBonus, FIVE_CARD_TRICK,
+};
+use crate::side_bet::{SideBet, SideBetOutcome, SideBetResolver};
 use im::{vector, Vector};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use thiserror::Error;
 
+pub const MAX_SEATS: usize = 7;
+
 #[derive(Error, Debug)]
 pub enum BlackjackError {
     #[error("transion is not allowed in this state")]
     InvalidStateError,
     #[error("Card not found")]
     NotFoundError,
+    #[error("Can only double down on the first two cards")]
+    DoubleDownError,
+    #[error("The table rules do not allow doubling down on this hand")]
+    DoubleDownNotAllowedError,
+    #[error("Can only split a pair of the same rank")]
+    SplitError,
+    #[error("Cannot split into more than {0} hands")]
+    SplitLimitError(usize),
+    #[error("Can only surrender on the first decision")]
+    SurrenderError,
+    #[error("Surrender is not allowed at this table")]
+    SurrenderNotAllowedError,
+    #[error("A table seats between 1 and 7 players, not {0}")]
+    SeatCountError(usize),
+    #[error("There is no seat {0} at this table")]
+    SeatNotFoundError(usize),
+    #[error("Can only switch two freshly dealt hands")]
+    SwitchError,
+    #[error("A doubled hand can only stand or be rescued")]
+    DoubledHandError,
+    #[error("This table does not offer {0}")]
+    SideBetNotOfferedError(SideBet),
+    #[error("Only Pontoon tables sell cards")]
+    BuyNotAllowedError,
+    #[error("Can only buy a card before twisting")]
+    BuyError,
+    #[error(transparent)]
+    ShoeError(#[from] DeckError),
+    #[error(transparent)]
+    RulesError(#[from] RulesError),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+enum Settlement {
+    Blackjack,
+    Bonus(TwentyOneBonus),
+    FiveCardTrick,
+    Win,
+    Push,
+    Surrender,
+    Loss,
+    OriginalBetLost,
+}
+
+impl Settlement {
+    fn units(self) -> i32 {
+        match self {
+            Settlement::Blackjack
+            | Settlement::Bonus(_)
+            | Settlement::FiveCardTrick
+            | Settlement::Win => 1,
+            Settlement::Push => 0,
+            Settlement::Surrender | Settlement::Loss | Settlement::OriginalBetLost => -1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct HandId {
+    pub seat: usize,
+    pub hand: usize,
+}
+
+impl HandId {
+    pub fn new(seat: usize, hand: usize) -> Self {
+        HandId { seat, hand }
+    }
 }
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub enum Action {
-    NewHand(Hand, DealerHand),
-    NewPlayerCard(Card),
+    NewHand(Vector<Hand>, DealerHand),
+    NewPlayerCard(HandId, Card),
     NewDealerCards(Vector<Card>),
-    PlayerWins,
-    PlayerBlackjack,
-    DealerWins,
+    PlayerWins(HandId),
+    PlayerBlackjack(usize),
+    DealerWins(HandId),
     DealerBusts,
-    PlayerBusts,
+    PlayerBusts(HandId),
     DealerBlackjack,
-    Draw,
+    Draw(HandId),
     ShowDealerHoleCard(Card),
+    ShowDealerUpcard(Card),
+    WagerPlaced(usize, Chips),
+    WagerWon(HandId, Chips),
+    WagerPushed(HandId, Chips),
+    WagerLost(HandId, Chips),
+    DoubleDown(HandId, Chips, Card),
+    CardBought(HandId, Chips),
+    Split(HandId),
+    Switch(usize),
+    DealerPushes22,
+    InsuranceOffered,
+    InsuranceWon(usize, Chips),
+    InsuranceLost(usize, Chips),
+    EvenMoney(usize),
+    PlayerSurrenders(usize),
+    WagerSurrendered(HandId, Chips),
+    PlayerBonus(HandId, TwentyOneBonus),
+    PlayerCharlie(HandId),
+    PlayerFiveCardTrick(HandId),
+    DoubleDownRescued(HandId),
+    SideBetPlaced(usize, SideBet, Chips),
+    SideBetWon(usize, SideBet, SideBetOutcome, Chips),
+    SideBetLost(usize, SideBet, Chips),
+    Reshuffle,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub enum GameState {
     Ready(Context),
+    OfferingInsurance(Context),
     WaitingForPlayer(Context),
     DealerWins(Context),
     PlayerWins(Context),
     Draw(Context),
+    PlayerSurrenders(Context),
 }
 
 impl GameState {
-    pub fn new() -> Self {
-        GameState::Ready(Context::new_hand())
+    pub fn new(rules: TableRules, seed: u64) -> Result<Self, BlackjackError> {
+        GameState::new_table(rules, 1, seed)
+    }
+
+    pub fn new_table(rules: TableRules, seats: usize, seed: u64) -> Result<Self, BlackjackError> {
+        if seats == 0 || seats > MAX_SEATS {
+            return Err(BlackjackError::SeatCountError(seats));
+        }
+        rules.validate()?;
+        Ok(GameState::Ready(Context::new_hand(rules, seats, seed)?))
+    }
+
+    pub fn context(&self) -> &Context {
+        match self {
+            GameState::Ready(context)
+            | GameState::OfferingInsurance(context)
+            | GameState::WaitingForPlayer(context)
+            | GameState::DealerWins(context)
+            | GameState::PlayerWins(context)
+            | GameState::Draw(context)
+            | GameState::PlayerSurrenders(context) => context,
+        }
+    }
+
+    pub fn can_surrender(&self) -> bool {
+        match self {
+            GameState::WaitingForPlayer(context) => context.can_surrender(),
+            GameState::OfferingInsurance(context) => {
+                context.rules.surrender == SurrenderRule::Early && context.can_surrender()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        GameState::new(TableRules::default(), rand::random())
+            .expect("The default rules always make a table")
     }
 }
 
 const BLACKJACK: Score = Score(21);
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct Context {
-    deck: Deck,
-    pub player_hand: Hand,
-    pub dealer_hand: DealerHand,
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct PlayerHand {
+    pub hand: Hand,
+    pub wager: Chips,
+    pub doubled: bool,
+    pub split: bool,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub split_off: bool,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub bought: usize,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    settlement: Option<Settlement>,
 }
 
-impl Context {
-    fn new(deck: Deck) -> Self {
-        Context {
-            deck,
-            player_hand: Hand::new(),
-            dealer_hand: DealerHand::new(),
+impl PlayerHand {
+    fn new(hand: Hand, wager: Chips) -> Self {
+        PlayerHand {
+            hand,
+            wager,
+            doubled: false,
+            split: false,
+            split_off: false,
+            bought: 0,
+            settlement: None,
+        }
+    }
+
+    fn add(&self, card: Card) -> Self {
+        PlayerHand {
+            hand: self.hand.add(card),
+            ..self.clone()
         }
     }
 
-    fn new_hand() -> Self {
-        Context::new(Deck::standard_deck().shuffle())
+    pub fn score(&self) -> Score {
+        self.hand.score()
     }
 
-    fn deal_initial_hands(&self) -> Result<Context, Box<dyn std::error::Error>> {
-        let (new_deck, first_card) = self.deck.deal()?;
-        let (new_deck, second_card) = new_deck.deal()?;
-        let (new_deck, third_card) = new_deck.deal()?;
-        let (new_deck, fourth_card) = new_deck.deal()?;
-        let player_hand = Hand::new().add(first_card).add(third_card);
-        let dealer_hand = DealerHand::new().add(second_card).add(fourth_card);
+    pub fn is_pair(&self) -> bool {
+        let cards = self.hand.cards();
+        cards.len() == 2 && cards[0].rank == cards[1].rank
+    }
 
-        Ok(Context {
-            player_hand,
-            dealer_hand,
-            deck: new_deck,
-        })
+    pub fn is_settled(&self) -> bool {
+        self.settlement.is_some()
     }
 
-    fn deal_player_card(&self) -> Result<Context, Box<dyn std::error::Error>> {
-        let (deck, card) = self.deck.deal()?;
-        let player_hand = self.player_hand.add(card);
+    fn is_natural(&self) -> bool {
+        !self.split && self.hand.cards().len() == 2 && self.score() == BLACKJACK
+    }
 
-        Ok(Context {
-            player_hand,
-            dealer_hand: self.dealer_hand.clone(),
-            deck,
-        })
+    fn is_split_aces(&self) -> bool {
+        self.split && self.hand.cards().front().map(|card| card.rank) == Some(Rank::Ace)
     }
 
-    fn play_dealer_hand(&self) -> Result<Context, Box<dyn std::error::Error>> {
-        let mut new_context = self.clone();
-        while new_context.dealer_score() < Score(17) {
-            let (deck, card) = new_context.deck.deal()?;
-            new_context.deck = deck;
-            new_context.dealer_hand = new_context.dealer_hand.add(card);
-        }
-        Ok(new_context)
+    fn is_finished(&self, rules: &TableRules) -> bool {
+        self.is_settled()
+            || (self.is_split_aces() && !rules.hit_split_aces)
+            || (rules.pontoon && self.hand.cards().len() >= FIVE_CARD_TRICK)
+            || self.score() >= BLACKJACK
     }
 
-    fn double_blackjack(&self) -> bool {
-        self.player_blackjack() && self.dealer_blackjack()
+    fn is_live(&self) -> bool {
+        !self.is_settled() && !self.busts()
     }
 
-    fn player_blackjack(&self) -> bool {
-        self.player_score() == BLACKJACK
+    fn busts(&self) -> bool {
+        self.score() > BLACKJACK
     }
 
-    fn dealer_blackjack(&self) -> bool {
-        self.dealer_score() == BLACKJACK
+    fn units(&self) -> i32 {
+        let weight = match self.settlement {
+            Some(Settlement::OriginalBetLost) | Some(Settlement::Surrender) => 1,
+            _ if self.doubled => 2,
+            _ => 1 + self.bought as i32,
+        };
+        self.settlement
+            .map_or(0, |settlement| settlement.units() * weight)
     }
+}
 
-    fn player_score(&self) -> Score {
-        self.player_hand.score()
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Seat {
+    pub player_hands: Vector<PlayerHand>,
+    pub active_hand: usize,
+    pub bankroll: Bankroll,
+    pub wager: Chips,
+    pub insurance: Chips,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub side_bets: Vector<(SideBet, Chips)>,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub switched: bool,
+}
+
+impl Seat {
+    fn new(bankroll: Bankroll) -> Self {
+        Seat {
+            player_hands: Vector::new(),
+            active_hand: 0,
+            bankroll,
+            wager: Chips(0),
+            insurance: Chips(0),
+            side_bets: Vector::new(),
+            switched: false,
+        }
     }
 
-    fn dealer_score(&self) -> Score {
-        self.dealer_hand.score()
+    pub fn player_hand(&self) -> Hand {
+        self.active_player_hand()
+            .map(|player_hand| player_hand.hand.clone())
+            .unwrap_or_default()
     }
 
-    fn player_busts(&self) -> bool {
-        self.player_score() > BLACKJACK
+    fn active_player_hand(&self) -> Option<&PlayerHand> {
+        self.player_hands.get(self.active_hand)
     }
 
-    fn dealer_busts(&self) -> bool {
-        self.dealer_score() > BLACKJACK
+    fn with_active_hand(&self, player_hand: PlayerHand) -> Seat {
+        Seat {
+            player_hands: self.player_hands.update(self.active_hand, player_hand),
+            ..self.clone()
+        }
     }
 
-    fn player_wins(&self) -> bool {
-        self.player_score() > self.dealer_score() || self.dealer_busts()
+    fn has_natural(&self) -> bool {
+        self.player_hands.len() == 1 && self.player_hands[0].is_natural()
     }
 
-    fn dealer_wins(&self) -> bool {
-        self.dealer_score() > self.player_score() && !self.dealer_busts()
+    fn first_unfinished_hand(&self, rules: &TableRules) -> Option<usize> {
+        self.player_hands
+            .iter()
+            .position(|player_hand| !player_hand.is_finished(rules))
     }
 
-    fn draw(&self) -> bool {
-        self.player_score() == self.dealer_score()
+    fn is_finished(&self, rules: &TableRules) -> bool {
+        self.player_hands
+            .iter()
+            .all(|player_hand| player_hand.is_finished(rules))
     }
 }
 
-pub fn deal(state: &GameState) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
-    match state {
-        GameState::Ready(context) => {
-            let new_context = context.deal_initial_hands()?;
-
-            Ok(match new_context {
-                _ if new_context.double_blackjack() => {
-                    let actions = vector![
-                        Action::Draw,
-                        Action::ShowDealerHoleCard(
-                            new_context.dealer_hand.hole_card().unwrap().clone()
-                        ),
-                        new_hand_action(&new_context)
-                    ];
-                    (GameState::Draw(new_context), actions)
-                }
-                _ if new_context.dealer_blackjack() => {
-                    let actions = vector![
-                        Action::DealerBlackjack,
-                        Action::ShowDealerHoleCard(
-                            new_context.dealer_hand.hole_card().unwrap().clone()
-                        ),
-                        new_hand_action(&new_context)
-                    ];
-                    (GameState::DealerWins(new_context), actions)
-                }
-                _ if new_context.player_blackjack() => {
-                    let actions = vector![
-                        Action::PlayerBlackjack,
-                        Action::ShowDealerHoleCard(
-                            new_context.dealer_hand.hole_card().unwrap().clone()
-                        ),
-                        new_hand_action(&new_context)
-                    ];
-                    (GameState::PlayerWins(new_context), actions)
-                }
-                _ => {
-                    let actions = vector![new_hand_action(&new_context)];
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Context {
+    shoe: Shoe,
+    pub seats: Vector<Seat>,
+    pub active_seat: usize,
+    pub dealer_hand: DealerHand,
+    rules: TableRules,
+    peek_pending: bool,
+}
 
-                    (GameState::WaitingForPlayer(new_context), actions)
-                }
-            })
+impl Context {
+    fn new(shoe: Shoe, rules: TableRules, seats: usize) -> Self {
+        Context {
+            shoe,
+            seats: (0..seats).map(|_| Seat::new(Bankroll::default())).collect(),
+            active_seat: 0,
+            dealer_hand: DealerHand::new(),
+            rules,
+            peek_pending: false,
         }
-        GameState::DealerWins(_) | GameState::PlayerWins(_) | GameState::Draw(_) => {
-            let start = GameState::Ready(Context::new_hand());
-            deal(&start)
+    }
+
+    fn new_hand(rules: TableRules, seats: usize, seed: u64) -> Result<Self, DeckError> {
+        Ok(Context::new(
+            Shoe::new_with_composition(rules.deck, rules.decks, rules.penetration, seed)?,
+            rules,
+            seats,
+        ))
+    }
+
+    fn next_round(&self) -> Self {
+        Context {
+            seats: self
+                .seats
+                .iter()
+                .map(|seat| Seat::new(seat.bankroll.clone()))
+                .collect(),
+            ..Context::new(self.shoe.clone(), self.rules.clone(), 0)
         }
-        _ => Err(Box::new(BlackjackError::InvalidStateError)),
     }
-}
 
-pub fn hit(state: &GameState) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
-    if let GameState::WaitingForPlayer(context) = state {
-        let new_context = context.deal_player_card()?;
-        let dealt_card = new_context
-            .player_hand
-            .cards()
-            .last()
-            .ok_or(BlackjackError::NotFoundError)?
-            .clone();
+    pub fn rules(&self) -> &TableRules {
+        &self.rules
+    }
 
-        Ok(match new_context {
-            _ if new_context.player_blackjack() => {
-                let (final_state, mut actions) = stand(&GameState::WaitingForPlayer(new_context))?;
-                actions.push_front(Action::NewPlayerCard(dealt_card));
-                (final_state, actions)
-            }
-            _ if new_context.player_busts() => {
-                let hole_card = new_context.dealer_hand.hole_card().unwrap().clone();
+    pub fn decks_remaining(&self) -> f64 {
+        self.shoe.decks_remaining()
+    }
 
-                (
-                    GameState::DealerWins(new_context),
-                    vector![
-                        Action::NewPlayerCard(dealt_card),
-                        Action::PlayerBusts,
-                        Action::ShowDealerHoleCard(hole_card)
-                    ],
-                )
-            }
-            _ => (
-                GameState::WaitingForPlayer(new_context),
-                vector![Action::NewPlayerCard(dealt_card)],
-            ),
-        })
-    } else {
-        Err(Box::new(BlackjackError::InvalidStateError))
+    pub fn seat(&self) -> &Seat {
+        &self.seats[self.active_seat]
     }
-}
 
-pub fn stand(state: &GameState) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
-    match state {
-        GameState::WaitingForPlayer(context) => {
-            let new_context = context.play_dealer_hand()?;
-            let next_dealer_cards = new_context.dealer_hand.cards().skip(2);
-            let mut actions = if next_dealer_cards.len() > 0 {
-                vector![
-                    Action::ShowDealerHoleCard(
-                        new_context.dealer_hand.hole_card().unwrap().clone()
-                    ),
-                    Action::NewDealerCards(next_dealer_cards)
-                ]
-            } else {
-                vector![Action::ShowDealerHoleCard(
-                    new_context.dealer_hand.hole_card().unwrap().clone()
-                )]
-            };
-            Ok(match new_context {
-                _ if new_context.dealer_wins() => {
-                    actions.push_front(Action::DealerWins);
-                    (GameState::DealerWins(new_context), actions)
-                }
-                _ if new_context.dealer_busts() => {
-                    actions.push_front(Action::DealerBusts);
-                    (GameState::PlayerWins(new_context), actions)
-                }
-                _ if new_context.player_wins() => {
-                    actions.push_front(Action::PlayerWins);
-                    (GameState::PlayerWins(new_context), actions)
-                }
-                _ if new_context.draw() => {
-                    actions.push_front(Action::Draw);
-                    (GameState::Draw(new_context), actions)
-                }
-                _ => (GameState::WaitingForPlayer(new_context), Vector::new()),
-            })
-        }
-        _ => Err(Box::new(BlackjackError::InvalidStateError)),
+    pub fn player_hand(&self) -> Hand {
+        self.seat().player_hand()
     }
-}
 
-fn new_hand_action(context: &Context) -> Action {
-    Action::NewHand(context.player_hand.clone(), context.dealer_hand.clone())
-}
+    pub fn can_double_down(&self) -> bool {
+        self.active_player_hand().is_some_and(|player_hand| {
+            player_hand.hand.cards().len() == 2
+                && self.double_allowed(player_hand)
+                && self.seat().bankroll.balance() >= player_hand.wager
+        })
+    }
 
-#[cfg(test)]
-mod game_state_machine {
-    use super::*;
-    use crate::deck::{Card, Rank, Suit};
-    use im::{vector, HashSet, Vector};
+    fn double_allowed(&self, player_hand: &PlayerHand) -> bool {
+        let total_allowed = match self.rules.double_rule {
+            DoubleRule::AnyTwoCards => true,
+            DoubleRule::NineToEleven => {
+                Score(9) <= player_hand.score() && player_hand.score() <= Score(11)
+            }
+        };
+        total_allowed
+            && !self.rules.pontoon
+            && (self.rules.double_after_split || !player_hand.split)
+    }
 
-    #[derive(Debug, Error)]
-    enum TestError {
-        #[error("Action occured that is not expected")]
-        InvalidActionError,
-        #[error("Transitioned to the wrong state")]
-        IncorrectTransitionError,
+    pub fn can_buy(&self) -> bool {
+        self.active_player_hand().is_some_and(|player_hand| {
+            self.buy_allowed(player_hand) && self.seat().bankroll.balance() >= self.seat().wager
+        })
     }
 
-    impl Deck {
-        pub fn new() -> Self {
-            Deck { cards: vector!() }
-        }
+    // Cards can be bought one at a time until the player first twists
+    fn buy_allowed(&self, player_hand: &PlayerHand) -> bool {
+        self.rules.pontoon
+            && player_hand.hand.cards().len() == 2 + player_hand.bought
+            && !player_hand.is_finished(&self.rules)
     }
 
-    impl Context {
-        fn empty() -> Self {
-            Context::new(Deck::new())
-        }
+    pub fn offers_even_money(&self) -> bool {
+        self.seat().has_natural()
+    }
 
-        fn new_with_cards(cards: Vector<Card>) -> Self {
-            Context::new(Deck::new_with_cards(cards))
-        }
+    pub fn can_split(&self) -> bool {
+        self.active_player_hand().is_some_and(|player_hand| {
+            player_hand.is_pair()
+                && self.seat().player_hands.len() < self.rules.split_limit
+                && self.seat().bankroll.balance() >= player_hand.wager
+        })
     }
 
-    fn cards(ranks: Vector<Rank>) -> Vector<Card> {
-        ranks
-            .iter()
-            .map(|rank| Card {
-                rank: *rank,
-                suit: Suit::Heart,
-            })
-            .collect()
+    pub fn can_hit(&self) -> bool {
+        self.active_player_hand().is_some_and(|player_hand| {
+            !player_hand.doubled && !player_hand.is_finished(&self.rules)
+        })
     }
 
-    fn minimal_cards() -> Vector<Card> {
-        cards(vector!(Rank::Nine, Rank::Ace, Rank::Nine, Rank::Ace))
+    pub fn can_switch(&self) -> bool {
+        let seat = self.seat();
+        self.rules.switch
+            && !seat.switched
+            && seat.player_hands.len() == 2
+            && seat.player_hands.iter().all(|player_hand| {
+                player_hand.hand.cards().len() == 2
+                    && !player_hand.doubled
+                    && !player_hand.is_settled()
+            })
+            && seat.first_unfinished_hand(&self.rules) == Some(seat.active_hand)
     }
 
-    #[test]
-    fn context_deal_intial_hand_deals_in_the_right_order() -> Result<(), Box<dyn std::error::Error>>
-    {
-        let cards = cards(vector!(Rank::Ace, Rank::Two, Rank::Three, Rank::Four));
-        let context = Context::new_with_cards(cards.clone());
+    fn switch_second_cards(&self) -> Context {
+        let seat = self.seat();
+        let first = seat.player_hands[0].hand.cards();
+        let second = seat.player_hands[1].hand.cards();
+        let switched = |player_hand: &PlayerHand, first_card: Card, second_card: Card| PlayerHand {
+            hand: Hand::new().add(first_card).add(second_card),
+            ..player_hand.clone()
+        };
+        self.with_seat(Seat {
+            player_hands: vector![
+                switched(&seat.player_hands[0], first[0], second[1]),
+                switched(&seat.player_hands[1], second[0], first[1])
+            ],
+            switched: true,
+            ..seat.clone()
+        })
+    }
 
-        let new_context = context.deal_initial_hands()?;
+    fn can_rescue(&self) -> bool {
+        self.rules.double_down_rescue
+            && self
+                .active_player_hand()
+                .is_some_and(|player_hand| player_hand.doubled && player_hand.is_live())
+    }
 
-        assert_eq!(
-            new_context.player_hand,
-            Hand::new().add(cards[0]).add(cards[2])
-        );
-        assert_eq!(
-            new_context.dealer_hand,
-            DealerHand::new().add(cards[1]).add(cards[3])
-        );
-        assert_eq!(*new_context.dealer_hand.hole_card().unwrap(), cards[1]);
-        Ok(())
+    fn can_surrender(&self) -> bool {
+        self.can_rescue()
+            || self.rules.surrender != SurrenderRule::NoSurrender
+                && self.seat().player_hands.len() == 1
+                && self.player_hand().cards().len() == 2
+                && !self.seat().has_natural()
     }
 
-    #[test]
-    fn context_new_hand_creates_new_context_with_new_shuffled_deck() {
-        let context = Context::new_hand();
+    fn active_hand_id(&self) -> HandId {
+        HandId::new(self.active_seat, self.seat().active_hand)
+    }
 
-        let full_deck = Deck::standard_deck();
-        assert_ne!(context.deck.cards, full_deck.cards);
+    fn active_player_hand(&self) -> Option<&PlayerHand> {
+        self.seat().active_player_hand()
+    }
 
-        let shuffled_deck_set = full_deck.cards.into_iter().collect::<HashSet<Card>>();
-        let new_deck_set = context.deck.cards.into_iter().collect::<HashSet<Card>>();
-        assert_eq!(new_deck_set, shuffled_deck_set);
+    fn hand_ids(&self) -> Vec<HandId> {
+        self.seats
+            .iter()
+            .enumerate()
+            .flat_map(|(seat_index, seat)| {
+                (0..seat.player_hands.len()).map(move |hand| HandId::new(seat_index, hand))
+            })
+            .collect()
+    }
 
-        assert_eq!(context.player_hand, Hand::new());
-        assert_eq!(context.dealer_hand, DealerHand::new());
+    fn player_hand_at(&self, id: HandId) -> &PlayerHand {
+        &self.seats[id.seat].player_hands[id.hand]
     }
 
-    #[test]
-    fn deal_transitions_from_ready_to_waiting_for_player() -> Result<(), Box<dyn std::error::Error>>
-    {
-        let game_state = GameState::Ready(Context::new_with_cards(minimal_cards()));
+    fn with_seat(&self, seat: Seat) -> Context {
+        self.with_seat_at(self.active_seat, seat)
+    }
 
-        let (new_game_state, _) = deal(&game_state)?;
-        match new_game_state {
-            GameState::WaitingForPlayer(_) => Ok(()),
-            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+    fn with_seat_at(&self, index: usize, seat: Seat) -> Context {
+        Context {
+            seats: self.seats.update(index, seat),
+            ..self.clone()
         }
     }
 
-    #[test]
-    fn other_transitions_fail() {
-        let game_state = GameState::WaitingForPlayer(Context::empty());
+    fn with_active_hand(&self, player_hand: PlayerHand) -> Context {
+        self.with_seat(self.seat().with_active_hand(player_hand))
+    }
 
-        let result = deal(&game_state);
+    fn place_wager(
+        &self,
+        seat_index: usize,
+        wager: Chips,
+    ) -> Result<Context, Box<dyn std::error::Error>> {
+        let seat = self
+            .seats
+            .get(seat_index)
+            .ok_or(BlackjackError::SeatNotFoundError(seat_index))?;
+        let hands = self.rules.hands_per_seat() as u32;
+        let bankroll = seat
+            .bankroll
+            .deposit(seat.wager.scale(hands, 1))
+            .withdraw(wager.scale(hands, 1))?;
 
-        assert!(result.is_err(), "deal is only a transition from ready")
+        Ok(self.with_seat_at(
+            seat_index,
+            Seat {
+                bankroll,
+                wager,
+                ..seat.clone()
+            },
+        ))
     }
 
-    #[test]
-    fn deal_gives_the_player_and_dealer_hands() -> Result<(), Box<dyn std::error::Error>> {
-        let cards = minimal_cards();
-        let context = Context::new_with_cards(cards.clone());
-        let game_state = GameState::Ready(context);
+    fn place_side_bet(
+        &self,
+        seat_index: usize,
+        side_bet: SideBet,
+        wager: Chips,
+    ) -> Result<Context, Box<dyn std::error::Error>> {
+        if self.rules.paytable(side_bet).is_none() {
+            return Err(Box::new(BlackjackError::SideBetNotOfferedError(side_bet)));
+        }
+        let seat = self
+            .seats
+            .get(seat_index)
+            .ok_or(BlackjackError::SeatNotFoundError(seat_index))?;
+        let previous = seat
+            .side_bets
+            .iter()
+            .find(|(placed, _)| *placed == side_bet)
+            .map(|(_, chips)| *chips)
+            .unwrap_or_default();
+        let bankroll = seat.bankroll.deposit(previous).withdraw(wager)?;
+        let mut side_bets: Vector<(SideBet, Chips)> = seat
+            .side_bets
+            .iter()
+            .filter(|(placed, _)| *placed != side_bet)
+            .cloned()
+            .collect();
+        if wager > Chips(0) {
+            side_bets.push_back((side_bet, wager));
+        }
 
-        if let (GameState::WaitingForPlayer(context), _) = deal(&game_state)? {
-            assert_eq!(Deck::new(), context.deck);
-            assert_eq!(Hand::new().add(cards[0]).add(cards[2]), context.player_hand);
-            assert_eq!(
-                DealerHand::new().add(cards[1]).add(cards[3]),
-                context.dealer_hand
-            );
-            Ok(())
+        Ok(self.with_seat_at(
+            seat_index,
+            Seat {
+                bankroll,
+                side_bets,
+                ..seat.clone()
+            },
+        ))
+    }
+
+    fn settle_side_bets(&self) -> (Context, Vector<Action>) {
+        self.settle_side_bets_by(|hand, side_bet| Some(side_bet.resolve(hand, &self.dealer_hand)))
+    }
+
+    // Without a hole card a dealer blackjack isn't known at the deal, so a
+    // queen of hearts pair keeps its Lucky Ladies bet riding until the
+    // dealer's hand is complete.
+    fn settle_side_bets_without_hole_card(&self) -> (Context, Vector<Action>) {
+        self.settle_side_bets_by(
+            |hand, side_bet| match side_bet.resolve(hand, &self.dealer_hand) {
+                Some(SideBetOutcome::QueenOfHeartsPair) => None,
+                outcome => Some(outcome),
+            },
+        )
+    }
+
+    fn settle_held_side_bets(&self) -> (Context, Vector<Action>) {
+        let outcome = if self.dealer_has_natural() {
+            SideBetOutcome::QueenOfHeartsWithDealerBlackjack
         } else {
-            Err(Box::new(TestError::IncorrectTransitionError))
-        }
+            SideBetOutcome::QueenOfHeartsPair
+        };
+        self.settle_side_bets_by(|_, _| Some(Some(outcome)))
     }
 
-    fn assert_actions_contains_new_hand(
-        actions: &Vector<Action>,
-        cards: &Vector<Card>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let new_hand = actions
+    fn has_held_side_bets(&self) -> bool {
+        self.seats.iter().any(|seat| !seat.side_bets.is_empty())
+    }
+
+    // `resolve` gives None for a bet that keeps riding
+    fn settle_side_bets_by(
+        &self,
+        resolve: impl Fn(&Hand, SideBet) -> Option<Option<SideBetOutcome>>,
+    ) -> (Context, Vector<Action>) {
+        let mut actions = Vector::new();
+        let seats = self
+            .seats
             .iter()
-            .find(|action| match action {
-                Action::NewHand(_, _) => true,
-                _ => false,
+            .enumerate()
+            .map(|(index, seat)| {
+                let hand = seat.player_hand();
+                let mut held = Vector::new();
+                let bankroll = seat.side_bets.iter().fold(
+                    seat.bankroll.clone(),
+                    |bankroll, (side_bet, wager)| {
+                        let outcome = match resolve(&hand, *side_bet) {
+                            Some(outcome) => outcome,
+                            None => {
+                                held.push_back((*side_bet, *wager));
+                                return bankroll;
+                            }
+                        };
+                        let won = outcome.and_then(|outcome| {
+                            self.rules
+                                .paytable(*side_bet)
+                                .and_then(|paytable| paytable.pays(outcome))
+                                .map(|pays| (outcome, wager.scale(pays, 1)))
+                        });
+                        match won {
+                            Some((outcome, winnings)) => {
+                                actions.push_back(Action::SideBetWon(
+                                    index, *side_bet, outcome, winnings,
+                                ));
+                                bankroll.deposit(*wager + winnings)
+                            }
+                            None => {
+                                actions.push_back(Action::SideBetLost(index, *side_bet, *wager));
+                                bankroll
+                            }
+                        }
+                    },
+                );
+                Seat {
+                    bankroll,
+                    side_bets: held,
+                    ..seat.clone()
+                }
             })
-            .ok_or(BlackjackError::NotFoundError)?;
+            .collect();
 
-        match new_hand {
-            Action::NewHand(player_hand, dealer_hand) => {
-                assert_eq!(&Hand::new().add(cards[0]).add(cards[2]), player_hand);
-                assert_eq!(&DealerHand::new().add(cards[1]).add(cards[3]), dealer_hand);
-                Ok(())
-            }
-            _ => Err(Box::new(TestError::InvalidActionError)),
-        }
+        (
+            Context {
+                seats,
+                ..self.clone()
+            },
+            actions,
+        )
     }
 
-    #[test]
-    fn deal_also_issues_a_new_hand_action() -> Result<(), Box<dyn std::error::Error>> {
-        let cards = minimal_cards();
-        let context = Context::new_with_cards(cards.clone());
-        let game_state = GameState::Ready(context);
+    fn double_active_wager(&self) -> Result<Context, Box<dyn std::error::Error>> {
+        let seat = self.seat();
+        let player_hand = seat
+            .active_player_hand()
+            .ok_or(BlackjackError::NotFoundError)?;
+        let bankroll = seat.bankroll.withdraw(player_hand.wager)?;
+        let doubled_hand = PlayerHand {
+            wager: player_hand.wager + player_hand.wager,
+            doubled: true,
+            ..player_hand.clone()
+        };
 
-        let (_, actions) = deal(&game_state)?;
-        assert_eq!(actions.len(), 1);
-        assert_actions_contains_new_hand(&actions, &cards)
+        Ok(self.with_seat(Seat {
+            bankroll,
+            ..seat.with_active_hand(doubled_hand)
+        }))
     }
 
-    #[test]
-    fn deal_goes_to_dealer_won_when_dealer_has_blackjack() -> Result<(), Box<dyn std::error::Error>>
-    {
-        let dealer_blackjack_hand = cards(vector!(Rank::Two, Rank::Ace, Rank::Two, Rank::Ten));
-        let context = Context::new_with_cards(dealer_blackjack_hand);
-        let game_state = GameState::Ready(context);
+    fn buy_for_active_hand(&self) -> Result<Context, Box<dyn std::error::Error>> {
+        let seat = self.seat();
+        let player_hand = seat
+            .active_player_hand()
+            .ok_or(BlackjackError::NotFoundError)?;
+        let bankroll = seat.bankroll.withdraw(seat.wager)?;
+        let bought_hand = PlayerHand {
+            wager: player_hand.wager + seat.wager,
+            bought: player_hand.bought + 1,
+            ..player_hand.clone()
+        };
 
-        let (new_state, _) = deal(&game_state)?;
-        match new_state {
-            GameState::DealerWins(_) => Ok(()),
-            _ => Err(Box::new(TestError::IncorrectTransitionError)),
-        }
+        Ok(self.with_seat(Seat {
+            bankroll,
+            ..seat.with_active_hand(bought_hand)
+        }))
     }
 
-    #[test]
-    fn deal_has_three_actions_when_dealer_wins_with_blackjack(
+    fn split_active_hand(&self) -> Result<(Context, bool), Box<dyn std::error::Error>> {
+        let seat = self.seat();
+        let player_hand = seat
+            .active_player_hand()
+            .ok_or(BlackjackError::NotFoundError)?;
+        let bankroll = seat.bankroll.withdraw(player_hand.wager)?;
+        let cards = player_hand.hand.cards();
+        let (shoe, first_card, first_reshuffled) = self.draw()?;
+        let (shoe, second_card, second_reshuffled) = shoe.deal_or_reshuffle(|| {
+            let mut in_play = self.cards_in_play();
+            in_play.push(first_card);
+            in_play
+        })?;
+        let split_hand = |card: Card, dealt_card: Card| PlayerHand {
+            hand: Hand::new().add(card).add(dealt_card),
+            split: true,
+            ..player_hand.clone()
+        };
+        let mut player_hands = seat.player_hands.clone();
+        player_hands.set(seat.active_hand, split_hand(cards[0], first_card));
+        player_hands.insert(
+            seat.active_hand + 1,
+            PlayerHand {
+                split_off: true,
+                ..split_hand(cards[1], second_card)
+            },
+        );
+
+        Ok((
+            Context {
+                shoe,
+                ..self.with_seat(Seat {
+                    player_hands,
+                    bankroll,
+                    ..seat.clone()
+                })
+            },
+            first_reshuffled || second_reshuffled,
+        ))
+    }
+
+    fn settle(&self, id: HandId, settlement: Settlement) -> (Context, Action) {
+        let seat = &self.seats[id.seat];
+        let player_hand = self.player_hand_at(id);
+        let wager = player_hand.wager;
+        let (returned, action) = match settlement {
+            Settlement::Blackjack => {
+                let (numerator, denominator) = self.rules.blackjack_payout.ratio();
+                let winnings = wager.scale(numerator, denominator);
+                (wager + winnings, Action::WagerWon(id, winnings))
+            }
+            Settlement::Bonus(bonus) => {
+                let (numerator, denominator) = bonus.ratio();
+                let winnings = wager.scale(numerator, denominator);
+                (wager + winnings, Action::WagerWon(id, winnings))
+            }
+            Settlement::FiveCardTrick => {
+                let winnings = wager.scale(2, 1);
+                (wager + winnings, Action::WagerWon(id, winnings))
+            }
+            Settlement::Win => (wager + wager, Action::WagerWon(id, wager)),
+            Settlement::Push => (wager, Action::WagerPushed(id, wager)),
+            Settlement::Surrender => {
+                let refund = wager.scale(1, 2);
+                (refund, Action::WagerSurrendered(id, refund))
+            }
+            Settlement::Loss => (Chips(0), Action::WagerLost(id, wager)),
+            Settlement::OriginalBetLost => {
+                let original = wager.scale(1, 2);
+                (original, Action::WagerLost(id, original))
+            }
+        };
+        let settled_hand = PlayerHand {
+            settlement: Some(settlement),
+            ..player_hand.clone()
+        };
+
+        (
+            self.with_seat_at(
+                id.seat,
+                Seat {
+                    player_hands: seat.player_hands.update(id.hand, settled_hand),
+                    bankroll: seat.bankroll.deposit(returned),
+                    ..seat.clone()
+                },
+            ),
+            action,
+        )
+    }
+
+    fn place_insurance(&self) -> Result<Context, Box<dyn std::error::Error>> {
+        let seat = self.seat();
+        let stake = seat
+            .player_hands
+            .iter()
+            .fold(Chips(0), |stake, player_hand| stake + player_hand.wager);
+        let insurance = stake.scale(1, 2);
+
+        Ok(self.with_seat(Seat {
+            bankroll: seat.bankroll.withdraw(insurance)?,
+            insurance,
+            ..seat.clone()
+        }))
+    }
+
+    fn settle_insurance(&self) -> (Context, Vector<Action>) {
+        (0..self.seats.len())
+            .filter(|index| self.seats[*index].insurance > Chips(0))
+            .fold(
+                (self.clone(), Vector::new()),
+                |(context, mut actions), index| {
+                    let seat = context.seats[index].clone();
+                    if context.dealer_blackjack() {
+                        let winnings = seat.insurance.scale(2, 1);
+                        actions.push_back(Action::InsuranceWon(index, winnings));
+                        let paid = Seat {
+                            bankroll: seat.bankroll.deposit(seat.insurance + winnings),
+                            ..seat
+                        };
+                        (context.with_seat_at(index, paid), actions)
+                    } else {
+                        actions.push_back(Action::InsuranceLost(index, seat.insurance));
+                        (context, actions)
+                    }
+                },
+            )
+    }
+
+    fn settle_naturals(&self) -> (Context, Vector<Action>, Vector<Action>) {
+        self.hand_ids()
+            .into_iter()
+            .filter(|id| {
+                let player_hand = self.player_hand_at(*id);
+                player_hand.is_natural() && !player_hand.is_settled()
+            })
+            .fold(
+                (self.clone(), Vector::new(), Vector::new()),
+                |(context, mut blackjacks, mut settled), id| {
+                    let (context, paid) = context.settle(id, Settlement::Blackjack);
+                    blackjacks.push_back(Action::PlayerBlackjack(id.seat));
+                    settled.push_back(paid);
+                    (context, blackjacks, settled)
+                },
+            )
+    }
+
+    fn settle_hands(&self) -> (Context, Vector<Action>) {
+        self.hand_ids()
+            .into_iter()
+            .filter(|id| !self.player_hand_at(*id).is_settled())
+            .fold(
+                (self.clone(), Vector::new()),
+                |(context, mut actions), id| {
+                    let player_hand = context.player_hand_at(id).clone();
+                    let natural = player_hand.is_natural();
+                    let (settlement, result) = match player_hand {
+                        _ if natural
+                            && context.dealer_has_natural()
+                            && !context.rules.player_21_always_wins =>
+                        {
+                            (Settlement::Push, Some(Action::Draw(id)))
+                        }
+                        _ if natural => (
+                            Settlement::Blackjack,
+                            Some(Action::PlayerBlackjack(id.seat)),
+                        ),
+                        _ if player_hand.busts() => (Settlement::Loss, None),
+                        _ if context.rules.pontoon => context.pontoon_settlement(id, &player_hand),
+                        _ if player_hand.score() == BLACKJACK
+                            && context.rules.player_21_always_wins =>
+                        {
+                            context.winning_settlement(
+                                id,
+                                &player_hand,
+                                Some(Action::PlayerWins(id)),
+                            )
+                        }
+                        _ if context.dealer_has_natural() => {
+                            (context.dealer_natural_settlement(&player_hand), None)
+                        }
+                        _ if context.dealer_pushes_22() => {
+                            (Settlement::Push, Some(Action::Draw(id)))
+                        }
+                        _ if context.dealer_busts() => {
+                            context.winning_settlement(id, &player_hand, None)
+                        }
+                        _ if context.dealer_wins(&player_hand) => {
+                            (Settlement::Loss, Some(Action::DealerWins(id)))
+                        }
+                        _ if context.player_wins(&player_hand) => context.winning_settlement(
+                            id,
+                            &player_hand,
+                            Some(Action::PlayerWins(id)),
+                        ),
+                        _ if context.rules.dealer_wins_ties => {
+                            (Settlement::Loss, Some(Action::DealerWins(id)))
+                        }
+                        _ => (Settlement::Push, Some(Action::Draw(id))),
+                    };
+                    let (context, settled) = context.settle(id, settlement);
+                    actions.extend(result);
+                    actions.push_back(settled);
+                    (context, actions)
+                },
+            )
+    }
+
+    fn winning_settlement(
+        &self,
+        id: HandId,
+        player_hand: &PlayerHand,
+        announced: Option<Action>,
+    ) -> (Settlement, Option<Action>) {
+        match TwentyOneBonus::of(&player_hand.hand) {
+            Some(bonus) if self.rules.twenty_one_bonuses && !player_hand.doubled => (
+                Settlement::Bonus(bonus),
+                Some(Action::PlayerBonus(id, bonus)),
+            ),
+            _ => (Settlement::Win, announced),
+        }
+    }
+
+    fn pontoon_settlement(
+        &self,
+        id: HandId,
+        player_hand: &PlayerHand,
+    ) -> (Settlement, Option<Action>) {
+        let player = match PontoonRank::of(&player_hand.hand) {
+            PontoonRank::Pontoon if player_hand.split => PontoonRank::Total(BLACKJACK),
+            rank => rank,
+        };
+        let dealer = PontoonRank::of(self.dealer_hand.hand());
+        match player {
+            PontoonRank::FiveCardTrick if player > dealer => (
+                Settlement::FiveCardTrick,
+                Some(Action::PlayerFiveCardTrick(id)),
+            ),
+            _ if player > dealer => (Settlement::Win, Some(Action::PlayerWins(id))),
+            _ if player == dealer && !self.rules.dealer_wins_ties => {
+                (Settlement::Push, Some(Action::Draw(id)))
+            }
+            _ => (Settlement::Loss, Some(Action::DealerWins(id))),
+        }
+    }
+
+    // Without a hole card doubled and split bets ride on the dealer not making
+    // a natural. Original bets only returns everything but the original bet on
+    // each hand that was dealt.
+    fn dealer_natural_settlement(&self, player_hand: &PlayerHand) -> Settlement {
+        match self.rules.hole_card {
+            HoleCardRule::OriginalBetsOnly if player_hand.split_off => Settlement::Push,
+            HoleCardRule::OriginalBetsOnly if player_hand.doubled => Settlement::OriginalBetLost,
+            _ => Settlement::Loss,
+        }
+    }
+
+    fn deal_initial_hands(&self) -> Result<(Context, bool), Box<dyn std::error::Error>> {
+        let mut shoe = self.shoe.clone();
+        let mut reshuffled = false;
+        let hands_per_seat = self.rules.hands_per_seat();
+        let mut hands = vec![Hand::new(); self.seats.len() * hands_per_seat];
+        let hole_card = self.rules.hole_card.dealer_has_hole_card();
+        let mut dealer_hand = if self.rules.hole_card.exposes_hole_card() {
+            DealerHand::exposed()
+        } else if self.rules.hole_card.conceals_upcard() {
+            DealerHand::concealed()
+        } else if hole_card {
+            DealerHand::new()
+        } else {
+            DealerHand::without_hole_card()
+        };
+        let in_play = |hands: &[Hand], dealer_hand: &DealerHand| {
+            let mut cards: Vec<Card> = hands.iter().flat_map(Hand::cards).collect();
+            cards.extend(dealer_hand.cards());
+            cards
+        };
+        for round in 0..2 {
+            for index in 0..hands.len() {
+                let (next_shoe, card, shuffled) =
+                    shoe.deal_or_reshuffle(|| in_play(&hands, &dealer_hand))?;
+                shoe = next_shoe;
+                reshuffled |= shuffled;
+                hands[index] = hands[index].add(card);
+            }
+            if round == 0 || hole_card {
+                let (next_shoe, card, shuffled) =
+                    shoe.deal_or_reshuffle(|| in_play(&hands, &dealer_hand))?;
+                shoe = next_shoe;
+                reshuffled |= shuffled;
+                dealer_hand = dealer_hand.add(card);
+            }
+        }
+        let seats = self
+            .seats
+            .iter()
+            .zip(hands.chunks(hands_per_seat))
+            .map(|(seat, seat_hands)| Seat {
+                player_hands: seat_hands
+                    .iter()
+                    .map(|hand| PlayerHand::new(hand.clone(), seat.wager))
+                    .collect(),
+                active_hand: 0,
+                ..seat.clone()
+            })
+            .collect();
+
+        Ok((
+            Context {
+                shoe,
+                seats,
+                active_seat: 0,
+                dealer_hand,
+                ..self.clone()
+            },
+            reshuffled,
+        ))
+    }
+
+    fn cards_in_play(&self) -> Vec<Card> {
+        self.seats
+            .iter()
+            .flat_map(|seat| seat.player_hands.iter())
+            .flat_map(|player_hand| player_hand.hand.cards())
+            .chain(self.dealer_hand.cards())
+            .collect()
+    }
+
+    fn draw(&self) -> Result<(Shoe, Card, bool), DeckError> {
+        self.shoe.deal_or_reshuffle(|| self.cards_in_play())
+    }
+
+    fn deal_player_card(&self) -> Result<(Context, bool), Box<dyn std::error::Error>> {
+        let (shoe, card, reshuffled) = self.draw()?;
+        let player_hand = self
+            .active_player_hand()
+            .ok_or(BlackjackError::NotFoundError)?
+            .add(card);
+
+        Ok((
+            Context {
+                shoe,
+                ..self.with_active_hand(player_hand)
+            },
+            reshuffled,
+        ))
+    }
+
+    fn deal_dealer_card(&self) -> Result<(Context, bool), Box<dyn std::error::Error>> {
+        let (shoe, card, reshuffled) = self.draw()?;
+        Ok((
+            Context {
+                shoe,
+                dealer_hand: self.dealer_hand.add(card),
+                ..self.clone()
+            },
+            reshuffled,
+        ))
+    }
+
+    fn play_dealer_hand(
+        &self,
+        stopping_rule: &impl DealerStoppingRule,
+    ) -> Result<(Context, bool), Box<dyn std::error::Error>> {
+        let mut new_context = self.clone();
+        let mut reshuffled = false;
+        while stopping_rule.hits(new_context.dealer_hand.total())
+            && !new_context.dealer_five_card_trick()
+        {
+            let (next_context, shuffled) = new_context.deal_dealer_card()?;
+            new_context = next_context;
+            reshuffled |= shuffled;
+        }
+        Ok((new_context, reshuffled))
+    }
+
+    fn seat_to_act(&self, from: usize) -> Option<usize> {
+        (from..self.seats.len()).find(|index| !self.seats[*index].is_finished(&self.rules))
+    }
+
+    fn every_seat_has_natural(&self) -> bool {
+        self.seats.iter().all(Seat::has_natural)
+    }
+
+    fn player_blackjack(&self) -> bool {
+        self.player_score() == BLACKJACK
+    }
+
+    fn reveal_dealer_cards(&self) -> Vector<Action> {
+        let hole_card = self
+            .dealer_hand
+            .hole_card()
+            .filter(|_| !self.dealer_hand.is_exposed())
+            .map(|hole_card| Action::ShowDealerHoleCard(*hole_card));
+        let upcard = self
+            .dealer_hand
+            .upcard()
+            .filter(|_| self.dealer_hand.is_concealed())
+            .map(|upcard| Action::ShowDealerUpcard(*upcard));
+        hole_card.into_iter().chain(upcard).collect()
+    }
+
+    fn dealer_shows_ace(&self) -> bool {
+        self.dealer_hand.upcard().map(|card| card.rank) == Some(Rank::Ace)
+    }
+
+    fn dealer_blackjack(&self) -> bool {
+        self.dealer_score() == BLACKJACK
+    }
+
+    fn dealer_has_natural(&self) -> bool {
+        self.dealer_hand.cards().len() == 2 && self.dealer_blackjack()
+    }
+
+    fn player_score(&self) -> Score {
+        self.player_hand().score()
+    }
+
+    fn dealer_score(&self) -> Score {
+        self.dealer_hand.score()
+    }
+
+    fn player_charlie(&self) -> bool {
+        let cards = self.player_hand().cards().len();
+        !self.player_busts()
+            && self
+                .rules
+                .charlie
+                .cards()
+                .is_some_and(|charlie| cards >= charlie)
+    }
+
+    fn player_five_card_trick(&self) -> bool {
+        self.rules.pontoon
+            && !self.player_busts()
+            && self.player_hand().cards().len() >= FIVE_CARD_TRICK
+    }
+
+    fn dealer_five_card_trick(&self) -> bool {
+        self.rules.pontoon && self.dealer_hand.cards().len() >= FIVE_CARD_TRICK
+    }
+
+    fn player_busts(&self) -> bool {
+        self.player_score() > BLACKJACK
+    }
+
+    fn has_live_hands(&self) -> bool {
+        self.seats
+            .iter()
+            .flat_map(|seat| seat.player_hands.iter())
+            .any(PlayerHand::is_live)
+    }
+
+    fn has_contested_hands(&self) -> bool {
+        self.seats
+            .iter()
+            .flat_map(|seat| seat.player_hands.iter())
+            .any(|player_hand| player_hand.is_live() && !player_hand.is_natural())
+    }
+
+    fn dealer_pushes_22(&self) -> bool {
+        self.rules.dealer_22_pushes && self.dealer_score() == Score(22)
+    }
+
+    fn dealer_busts(&self) -> bool {
+        self.dealer_score() > BLACKJACK && !self.dealer_pushes_22()
+    }
+
+    fn player_wins(&self, player_hand: &PlayerHand) -> bool {
+        player_hand.score() > self.dealer_score() || self.dealer_busts()
+    }
+
+    fn dealer_wins(&self, player_hand: &PlayerHand) -> bool {
+        self.dealer_score() > player_hand.score() && !self.dealer_busts()
+    }
+}
+
+type TransitionResult = Result<(GameState, Vector<Action>), Box<dyn std::error::Error>>;
+
+pub fn bet(
+    state: &GameState,
+    seat: usize,
+    wager: Chips,
+) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    let context = match state {
+        GameState::Ready(context) => context.clone(),
+        GameState::DealerWins(context)
+        | GameState::PlayerWins(context)
+        | GameState::Draw(context)
+        | GameState::PlayerSurrenders(context) => context.next_round(),
+        _ => return Err(Box::new(BlackjackError::InvalidStateError)),
+    };
+    let new_context = context.place_wager(seat, wager)?;
+
+    Ok((
+        GameState::Ready(new_context),
+        vector![Action::WagerPlaced(seat, wager)],
+    ))
+}
+
+pub fn place_side_bet(
+    state: &GameState,
+    seat: usize,
+    side_bet: SideBet,
+    wager: Chips,
+) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    let context = match state {
+        GameState::Ready(context) => context.clone(),
+        GameState::DealerWins(context)
+        | GameState::PlayerWins(context)
+        | GameState::Draw(context)
+        | GameState::PlayerSurrenders(context) => context.next_round(),
+        _ => return Err(Box::new(BlackjackError::InvalidStateError)),
+    };
+    let new_context = context.place_side_bet(seat, side_bet, wager)?;
+
+    Ok((
+        GameState::Ready(new_context),
+        vector![Action::SideBetPlaced(seat, side_bet, wager)],
+    ))
+}
+
+pub fn deal(state: &GameState) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    match state {
+        GameState::Ready(context) => {
+            let cut_card_reached = context.shoe.cut_card_reached();
+            let context = if cut_card_reached {
+                Context {
+                    shoe: context.shoe.reshuffle(),
+                    ..context.clone()
+                }
+            } else {
+                context.clone()
+            };
+            let (new_context, reshuffled) = context.deal_initial_hands()?;
+            let new_hand = new_hand_action(&new_context);
+
+            let (final_state, mut actions) = if !new_context.dealer_hand.has_hole_card() {
+                let (new_context, side_bets) = new_context.settle_side_bets_without_hole_card();
+                let (final_state, actions) = continue_play(new_context, 0)?;
+                (final_state, side_bets + actions)
+            } else if new_context.dealer_hand.is_exposed() || new_context.dealer_hand.is_concealed()
+            {
+                peek(new_context)?
+            } else if new_context.dealer_shows_ace() {
+                (
+                    GameState::OfferingInsurance(new_context),
+                    vector![Action::InsuranceOffered],
+                )
+            } else if new_context.rules.surrender == SurrenderRule::Early
+                && !new_context.every_seat_has_natural()
+            {
+                continue_play(
+                    Context {
+                        peek_pending: true,
+                        ..new_context
+                    },
+                    0,
+                )?
+            } else {
+                peek(new_context)?
+            };
+            actions.push_front(new_hand);
+            if reshuffled {
+                actions.push_front(Action::Reshuffle);
+            }
+            if cut_card_reached {
+                actions.push_front(Action::Reshuffle);
+            }
+            Ok((final_state, actions))
+        }
+        GameState::DealerWins(context)
+        | GameState::PlayerWins(context)
+        | GameState::Draw(context)
+        | GameState::PlayerSurrenders(context) => {
+            let start = GameState::Ready(context.next_round());
+            deal(&start)
+        }
+        _ => Err(Box::new(BlackjackError::InvalidStateError)),
+    }
+}
+
+pub fn take_insurance(
+    state: &GameState,
+) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    match state {
+        GameState::OfferingInsurance(context) if context.offers_even_money() => {
+            let seat = context.active_seat;
+            let (new_context, settled) = context.settle(context.active_hand_id(), Settlement::Win);
+            let (final_state, actions) = next_insurance_decision(new_context)?;
+            Ok((
+                final_state,
+                vector![Action::EvenMoney(seat), settled] + actions,
+            ))
+        }
+        GameState::OfferingInsurance(context) => {
+            next_insurance_decision(context.place_insurance()?)
+        }
+        _ => Err(Box::new(BlackjackError::InvalidStateError)),
+    }
+}
+
+pub fn decline_insurance(
+    state: &GameState,
+) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    match state {
+        GameState::OfferingInsurance(context) => next_insurance_decision(context.clone()),
+        _ => Err(Box::new(BlackjackError::InvalidStateError)),
+    }
+}
+
+fn next_insurance_decision(context: Context) -> TransitionResult {
+    let next_seat = context.active_seat + 1;
+    if next_seat < context.seats.len() {
+        return Ok((
+            GameState::OfferingInsurance(Context {
+                active_seat: next_seat,
+                ..context
+            }),
+            Vector::new(),
+        ));
+    }
+
+    let (context, insurance_settled) = Context {
+        active_seat: 0,
+        ..context
+    }
+    .settle_insurance();
+    let (final_state, actions) = peek(context)?;
+    Ok((final_state, insurance_settled + actions))
+}
+
+fn peek(context: Context) -> TransitionResult {
+    let (context, side_bets) = Context {
+        peek_pending: false,
+        ..context
+    }
+    .settle_side_bets();
+    let (final_state, actions) = peek_hole_card(context)?;
+    Ok((final_state, side_bets + actions))
+}
+
+fn peek_hole_card(context: Context) -> TransitionResult {
+    let dealer_cards = context.reveal_dealer_cards();
+
+    if context.dealer_blackjack() {
+        let (context, naturals, settled) = context
+            .hand_ids()
+            .into_iter()
+            .filter(|id| !context.player_hand_at(*id).is_settled())
+            .fold(
+                (context.clone(), Vector::new(), Vector::new()),
+                |(context, mut naturals, mut settled), id| {
+                    let natural = context.player_hand_at(id).is_natural();
+                    let (context, result) = if natural && context.rules.player_21_always_wins {
+                        naturals.push_back(Action::PlayerBlackjack(id.seat));
+                        context.settle(id, Settlement::Blackjack)
+                    } else if natural && !context.rules.pontoon {
+                        naturals.push_back(Action::Draw(id));
+                        context.settle(id, Settlement::Push)
+                    } else {
+                        context.settle(id, Settlement::Loss)
+                    };
+                    settled.push_back(result);
+                    (context, naturals, settled)
+                },
+            );
+        let mut actions = naturals;
+        if settled.len() > actions.len() {
+            actions.push_back(Action::DealerBlackjack);
+        }
+        actions.extend(dealer_cards);
+        return Ok((round_over(context), actions + settled));
+    }
+
+    // Switch hands keep their naturals open until the player has had the
+    // chance to switch them away
+    let (context, blackjacks, settled) = if context.rules.switch {
+        (context, Vector::new(), Vector::new())
+    } else {
+        context.settle_naturals()
+    };
+    let from = context.active_seat;
+    let (final_state, actions) = continue_play(context, from)?;
+    Ok((final_state, blackjacks + settled + actions))
+}
+
+fn peek_before(
+    state: &GameState,
+    transition: fn(&GameState) -> TransitionResult,
+) -> Option<TransitionResult> {
+    match state {
+        GameState::WaitingForPlayer(context) if context.peek_pending => {
+            Some(peek(context.clone()).and_then(|(peeked_state, actions)| {
+                match peeked_state {
+                    GameState::WaitingForPlayer(_) => transition(&peeked_state)
+                        .map(|(final_state, next_actions)| (final_state, actions + next_actions)),
+                    _ => Ok((peeked_state, actions)),
+                }
+            }))
+        }
+        _ => None,
+    }
+}
+
+pub fn surrender(
+    state: &GameState,
+) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    match state {
+        _ if state.can_surrender() => {
+            let context = state.context();
+            let seat = context.active_seat;
+            let id = context.active_hand_id();
+            let rescue = context.can_rescue();
+            let (new_context, settled) = context.settle(id, Settlement::Surrender);
+            if rescue {
+                let (final_state, actions) = next_player_hand(new_context)?;
+                return Ok((
+                    final_state,
+                    vector![Action::DoubleDownRescued(id), settled] + actions,
+                ));
+            }
+            let (final_state, actions) = match state {
+                GameState::OfferingInsurance(_) => next_insurance_decision(new_context)?,
+                _ => continue_play(new_context, seat + 1)?,
+            };
+            Ok((
+                final_state,
+                vector![Action::PlayerSurrenders(seat), settled] + actions,
+            ))
+        }
+        GameState::WaitingForPlayer(context)
+            if context.rules.surrender == SurrenderRule::NoSurrender =>
+        {
+            Err(Box::new(BlackjackError::SurrenderNotAllowedError))
+        }
+        GameState::WaitingForPlayer(_) => Err(Box::new(BlackjackError::SurrenderError)),
+        _ => Err(Box::new(BlackjackError::InvalidStateError)),
+    }
+}
+
+pub fn hit(state: &GameState) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    if let Some(result) = peek_before(state, hit) {
+        return result;
+    }
+    if let GameState::WaitingForPlayer(context) = state {
+        if context
+            .active_player_hand()
+            .is_some_and(|player_hand| player_hand.doubled)
+        {
+            return Err(Box::new(BlackjackError::DoubledHandError));
+        }
+        if !context.can_hit() {
+            return Err(Box::new(BlackjackError::InvalidStateError));
+        }
+        let id = context.active_hand_id();
+        let (new_context, reshuffled) = context.deal_player_card()?;
+        let dealt_card = *new_context
+            .player_hand()
+            .cards()
+            .last()
+            .ok_or(BlackjackError::NotFoundError)?;
+
+        let (final_state, mut actions) = match new_context {
+            _ if new_context.player_charlie() => charlie(new_context)?,
+            _ if new_context.player_blackjack()
+                || new_context.player_busts()
+                || new_context.player_five_card_trick() =>
+            {
+                finish_hand(new_context)?
+            }
+            _ => (GameState::WaitingForPlayer(new_context), Vector::new()),
+        };
+        actions.push_front(Action::NewPlayerCard(id, dealt_card));
+        if reshuffled {
+            actions.push_front(Action::Reshuffle);
+        }
+        Ok((final_state, actions))
+    } else {
+        Err(Box::new(BlackjackError::InvalidStateError))
+    }
+}
+
+pub fn stand(state: &GameState) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    if let Some(result) = peek_before(state, stand) {
+        return result;
+    }
+    match state {
+        GameState::WaitingForPlayer(context) => finish_hand(context.clone()),
+        _ => Err(Box::new(BlackjackError::InvalidStateError)),
+    }
+}
+
+pub fn double_down(
+    state: &GameState,
+) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    if let Some(result) = peek_before(state, double_down) {
+        return result;
+    }
+    match state {
+        GameState::WaitingForPlayer(context) if context.player_hand().cards().len() > 2 => {
+            Err(Box::new(BlackjackError::DoubleDownError))
+        }
+        GameState::WaitingForPlayer(context)
+            if !context
+                .active_player_hand()
+                .is_some_and(|player_hand| context.double_allowed(player_hand)) =>
+        {
+            Err(Box::new(BlackjackError::DoubleDownNotAllowedError))
+        }
+        GameState::WaitingForPlayer(context) => {
+            let id = context.active_hand_id();
+            let new_context = context.double_active_wager()?;
+            let (new_context, reshuffled) = new_context.deal_player_card()?;
+            let dealt_card = *new_context
+                .player_hand()
+                .cards()
+                .last()
+                .ok_or(BlackjackError::NotFoundError)?;
+            let wager = new_context.player_hand_at(id).wager;
+
+            let (final_state, mut actions) =
+                if new_context.can_rescue() && new_context.player_score() < BLACKJACK {
+                    (GameState::WaitingForPlayer(new_context), Vector::new())
+                } else {
+                    finish_hand(new_context)?
+                };
+            actions.push_front(Action::DoubleDown(id, wager, dealt_card));
+            if reshuffled {
+                actions.push_front(Action::Reshuffle);
+            }
+            Ok((final_state, actions))
+        }
+        _ => Err(Box::new(BlackjackError::InvalidStateError)),
+    }
+}
+
+pub fn buy(state: &GameState) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    if let Some(result) = peek_before(state, buy) {
+        return result;
+    }
+    match state {
+        GameState::WaitingForPlayer(context) if !context.rules.pontoon => {
+            Err(Box::new(BlackjackError::BuyNotAllowedError))
+        }
+        GameState::WaitingForPlayer(context)
+            if !context
+                .active_player_hand()
+                .is_some_and(|player_hand| context.buy_allowed(player_hand)) =>
+        {
+            Err(Box::new(BlackjackError::BuyError))
+        }
+        GameState::WaitingForPlayer(context) => {
+            let id = context.active_hand_id();
+            let (new_context, reshuffled) = context.buy_for_active_hand()?.deal_player_card()?;
+            let dealt_card = *new_context
+                .player_hand()
+                .cards()
+                .last()
+                .ok_or(BlackjackError::NotFoundError)?;
+            let wager = new_context.player_hand_at(id).wager;
+
+            let (final_state, mut actions) = match new_context {
+                _ if new_context.player_blackjack()
+                    || new_context.player_busts()
+                    || new_context.player_five_card_trick() =>
+                {
+                    finish_hand(new_context)?
+                }
+                _ => (GameState::WaitingForPlayer(new_context), Vector::new()),
+            };
+            actions.push_front(Action::NewPlayerCard(id, dealt_card));
+            actions.push_front(Action::CardBought(id, wager));
+            if reshuffled {
+                actions.push_front(Action::Reshuffle);
+            }
+            Ok((final_state, actions))
+        }
+        _ => Err(Box::new(BlackjackError::InvalidStateError)),
+    }
+}
+
+pub fn switch(
+    state: &GameState,
+) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    if let Some(result) = peek_before(state, switch) {
+        return result;
+    }
+    match state {
+        GameState::WaitingForPlayer(context) if context.can_switch() => {
+            let seat = context.active_seat;
+            let (final_state, mut actions) = continue_play(context.switch_second_cards(), seat)?;
+            actions.push_front(Action::Switch(seat));
+            Ok((final_state, actions))
+        }
+        GameState::WaitingForPlayer(_) => Err(Box::new(BlackjackError::SwitchError)),
+        _ => Err(Box::new(BlackjackError::InvalidStateError)),
+    }
+}
+
+pub fn split(state: &GameState) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    if let Some(result) = peek_before(state, split) {
+        return result;
+    }
+    match state {
+        GameState::WaitingForPlayer(context)
+            if !context
+                .active_player_hand()
+                .is_some_and(PlayerHand::is_pair) =>
+        {
+            Err(Box::new(BlackjackError::SplitError))
+        }
+        GameState::WaitingForPlayer(context)
+            if context.seat().player_hands.len() >= context.rules.split_limit =>
+        {
+            Err(Box::new(BlackjackError::SplitLimitError(
+                context.rules.split_limit,
+            )))
+        }
+        GameState::WaitingForPlayer(context) => {
+            let id = context.active_hand_id();
+            let next_id = HandId::new(id.seat, id.hand + 1);
+            let (new_context, reshuffled) = context.split_active_hand()?;
+            let mut split_actions = vector![
+                Action::Split(id),
+                Action::NewPlayerCard(id, new_context.player_hand_at(id).hand.cards()[1]),
+                Action::NewPlayerCard(next_id, new_context.player_hand_at(next_id).hand.cards()[1])
+            ];
+
+            let (final_state, mut actions) = match new_context.active_player_hand() {
+                Some(player_hand) if player_hand.is_finished(&new_context.rules) => {
+                    finish_hand(new_context)?
+                }
+                _ => (GameState::WaitingForPlayer(new_context), Vector::new()),
+            };
+            if reshuffled {
+                split_actions.push_front(Action::Reshuffle);
+            }
+            actions = split_actions + actions;
+            Ok((final_state, actions))
+        }
+        _ => Err(Box::new(BlackjackError::InvalidStateError)),
+    }
+}
+
+fn finish_hand(
+    context: Context,
+) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    let id = context.active_hand_id();
+    let busted = context.player_busts();
+    let (final_state, mut actions) = next_player_hand(context)?;
+    if busted {
+        actions.push_front(Action::PlayerBusts(id));
+    }
+    Ok((final_state, actions))
+}
+
+fn charlie(context: Context) -> TransitionResult {
+    let id = context.active_hand_id();
+    let player_hand = context.player_hand_at(id).clone();
+    let (settlement, bonus) = context.winning_settlement(id, &player_hand, None);
+    let (context, settled) = context.settle(id, settlement);
+    let (final_state, actions) = next_player_hand(context)?;
+
+    let mut charlie = vector![Action::PlayerCharlie(id)];
+    charlie.extend(bonus);
+    charlie.push_back(settled);
+    Ok((final_state, charlie + actions))
+}
+
+fn next_player_hand(
+    context: Context,
+) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    let seat = context.seat().clone();
+    let next_hand = seat.active_hand + 1;
+    let next_seat_context = Context {
+        seats: context.seats.update(
+            context.active_seat,
+            Seat {
+                active_hand: next_hand,
+                ..seat.clone()
+            },
+        ),
+        ..context.clone()
+    };
+    match seat.player_hands.get(next_hand) {
+        Some(player_hand) if player_hand.is_finished(&context.rules) => {
+            next_player_hand(next_seat_context)
+        }
+        Some(_) => Ok((
+            GameState::WaitingForPlayer(next_seat_context),
+            Vector::new(),
+        )),
+        None => {
+            let from = context.active_seat + 1;
+            continue_play(context, from)
+        }
+    }
+}
+
+fn continue_play(context: Context, from: usize) -> TransitionResult {
+    match context.seat_to_act(from) {
+        Some(active_seat) => {
+            let seat = &context.seats[active_seat];
+            let seat = Seat {
+                active_hand: seat.first_unfinished_hand(&context.rules).unwrap_or(0),
+                ..seat.clone()
+            };
+            Ok((
+                GameState::WaitingForPlayer(Context {
+                    active_seat,
+                    ..context.with_seat_at(active_seat, seat)
+                }),
+                Vector::new(),
+            ))
+        }
+        None if context.peek_pending => peek(context),
+        None => play_dealer(context),
+    }
+}
+
+fn play_dealer(
+    context: Context,
+) -> Result<(GameState, Vector<Action>), Box<dyn std::error::Error>> {
+    let initial_cards = context.dealer_hand.cards().len();
+    let (new_context, reshuffled) = if context.has_contested_hands() {
+        context.play_dealer_hand(&context.rules.dealer)?
+    } else if (context.has_live_hands() || context.has_held_side_bets())
+        && !context.dealer_hand.has_hole_card()
+    {
+        // Only naturals and held side bets are waiting on a dealer without a
+        // hole card
+        context.deal_dealer_card()?
+    } else {
+        (context, false)
+    };
+    let mut actions = new_context.reveal_dealer_cards();
+    if reshuffled {
+        actions.push_front(Action::Reshuffle);
+    }
+    let next_dealer_cards = new_context.dealer_hand.cards().skip(initial_cards);
+    if !next_dealer_cards.is_empty() {
+        actions.push_back(Action::NewDealerCards(next_dealer_cards));
+    }
+    if new_context.dealer_busts() {
+        actions.push_back(Action::DealerBusts);
+    }
+    if new_context.dealer_pushes_22() && new_context.has_contested_hands() {
+        actions.push_back(Action::DealerPushes22);
+    }
+    if new_context.dealer_has_natural() && new_context.has_contested_hands() {
+        actions.push_back(Action::DealerBlackjack);
+    }
+
+    let (new_context, side_bets) = new_context.settle_held_side_bets();
+    actions.extend(side_bets);
+    let (new_context, settled) = new_context.settle_hands();
+    actions.extend(settled);
+    Ok((round_over(new_context), actions))
+}
+
+fn round_over(context: Context) -> GameState {
+    let player_hands: Vec<&PlayerHand> = context
+        .seats
+        .iter()
+        .flat_map(|seat| seat.player_hands.iter())
+        .collect();
+    if player_hands
+        .iter()
+        .all(|player_hand| player_hand.settlement == Some(Settlement::Surrender))
+    {
+        return GameState::PlayerSurrenders(context);
+    }
+
+    let units: i32 = player_hands
+        .iter()
+        .map(|player_hand| player_hand.units())
+        .sum();
+    match units.cmp(&0) {
+        Ordering::Greater => GameState::PlayerWins(context),
+        Ordering::Less => GameState::DealerWins(context),
+        Ordering::Equal => GameState::Draw(context),
+    }
+}
+
+fn new_hand_action(context: &Context) -> Action {
+    Action::NewHand(
+        context
+            .seats
+            .iter()
+            .flat_map(|seat| seat.player_hands.iter())
+            .map(|player_hand| player_hand.hand.clone())
+            .collect(),
+        context.dealer_hand.clone(),
+    )
+}
+
+#[cfg(test)]
+mod game_state_machine {
+    use super::*;
+    use crate::deck::{Card, Deck, Rank, Suit, DEFAULT_PENETRATION};
+    use crate::rules::{BlackjackPayout, CharlieRule, DealerRule};
+    use im::{vector, HashSet, Vector};
+    use strum::IntoEnumIterator;
+
+    #[derive(Debug, Error)]
+    enum TestError {
+        #[error("Action occured that is not expected")]
+        InvalidActionError,
+        #[error("Transitioned to the wrong state")]
+        IncorrectTransitionError,
+    }
+
+    impl Context {
+        fn empty() -> Self {
+            Context::new_with_cards(vector!())
+        }
+
+        fn new_with_cards(cards: Vector<Card>) -> Self {
+            Context::new_with_rules(cards, TableRules::default())
+        }
+
+        fn new_with_rules(cards: Vector<Card>, rules: TableRules) -> Self {
+            Context::new(Shoe::new_with_cards(cards), rules, 1)
+        }
+    }
+
+    fn cards(ranks: Vector<Rank>) -> Vector<Card> {
+        ranks
+            .iter()
+            .map(|rank| Card {
+                rank: *rank,
+                suit: Suit::Heart,
+            })
+            .collect()
+    }
+
+    fn minimal_cards() -> Vector<Card> {
+        cards(vector!(Rank::Nine, Rank::Ace, Rank::Nine, Rank::Nine))
+    }
+
+    #[test]
+    fn context_deal_intial_hand_deals_in_the_right_order() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let cards = cards(vector!(Rank::Ace, Rank::Two, Rank::Three, Rank::Four));
+        let context = Context::new_with_cards(cards.clone());
+
+        let (new_context, _) = context.deal_initial_hands()?;
+
+        assert_eq!(
+            new_context.player_hand(),
+            Hand::new().add(cards[0]).add(cards[2])
+        );
+        assert_eq!(
+            new_context.dealer_hand,
+            DealerHand::new().add(cards[1]).add(cards[3])
+        );
+        assert_eq!(*new_context.dealer_hand.hole_card().unwrap(), cards[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn context_new_hand_creates_new_context_with_new_shuffled_deck() -> Result<(), DeckError> {
+        let context = Context::new_hand(TableRules::default(), 1, 1)?;
+
+        let full_deck = Deck::standard_deck();
+        assert_ne!(context.shoe.cards, full_deck.cards);
+
+        let shuffled_deck_set = full_deck.cards.into_iter().collect::<HashSet<Card>>();
+        let new_deck_set = context
+            .shoe
+            .cards
+            .iter()
+            .cloned()
+            .collect::<HashSet<Card>>();
+        assert_eq!(new_deck_set, shuffled_deck_set);
+
+        assert_eq!(context.seat().player_hands, Vector::new());
+        assert_eq!(context.dealer_hand, DealerHand::new());
+        Ok(())
+    }
+
+    #[test]
+    fn deal_transitions_from_ready_to_waiting_for_player() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let game_state = GameState::Ready(Context::new_with_cards(minimal_cards()));
+
+        let (new_game_state, _) = deal(&game_state)?;
+        match new_game_state {
+            GameState::WaitingForPlayer(_) => Ok(()),
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn other_transitions_fail() {
+        let game_state = GameState::WaitingForPlayer(Context::empty());
+
+        let result = deal(&game_state);
+
+        assert!(result.is_err(), "deal is only a transition from ready")
+    }
+
+    #[test]
+    fn deal_gives_the_player_and_dealer_hands() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = minimal_cards();
+        let context = Context::new_with_cards(cards.clone());
+        let game_state = GameState::Ready(context);
+
+        if let (GameState::WaitingForPlayer(context), _) = deal(&game_state)? {
+            assert_eq!(Vector::new(), context.shoe.cards);
+            assert_eq!(
+                Hand::new().add(cards[0]).add(cards[2]),
+                context.player_hand()
+            );
+            assert_eq!(
+                DealerHand::new().add(cards[1]).add(cards[3]),
+                context.dealer_hand
+            );
+            Ok(())
+        } else {
+            Err(Box::new(TestError::IncorrectTransitionError))
+        }
+    }
+
+    fn assert_actions_contains_new_hand(
+        actions: &Vector<Action>,
+        cards: &Vector<Card>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let new_hand = actions
+            .iter()
+            .find(|action| matches!(action, Action::NewHand(_, _)))
+            .ok_or(BlackjackError::NotFoundError)?;
+
+        match new_hand {
+            Action::NewHand(player_hands, dealer_hand) => {
+                assert_eq!(
+                    &vector![Hand::new().add(cards[0]).add(cards[2])],
+                    player_hands
+                );
+                assert_eq!(&DealerHand::new().add(cards[1]).add(cards[3]), dealer_hand);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::InvalidActionError)),
+        }
+    }
+
+    #[test]
+    fn deal_also_issues_a_new_hand_action() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = minimal_cards();
+        let context = Context::new_with_cards(cards.clone());
+        let game_state = GameState::Ready(context);
+
+        let (_, actions) = deal(&game_state)?;
+        assert_eq!(actions.len(), 1);
+        assert_actions_contains_new_hand(&actions, &cards)
+    }
+
+    #[test]
+    fn deal_goes_to_dealer_won_when_dealer_has_blackjack() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let dealer_blackjack_hand = cards(vector!(Rank::Two, Rank::Ace, Rank::Two, Rank::Ten));
+        let context = Context::new_with_cards(dealer_blackjack_hand);
+        let game_state = GameState::Ready(context);
+
+        let (new_state, _) = deal(&game_state)?;
+        match new_state {
+            GameState::DealerWins(_) => Ok(()),
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn deal_has_four_actions_when_dealer_wins_with_blackjack(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let dealer_blackjack_hand = cards(vector!(Rank::Two, Rank::Ace, Rank::Two, Rank::Ten));
+        let context = Context::new_with_cards(dealer_blackjack_hand.clone());
+        let game_state = GameState::Ready(context);
+
+        let (_, actions) = deal(&game_state)?;
+
+        assert_eq!(4, actions.len());
+        assert!(actions.contains(&Action::DealerBlackjack));
+        assert!(actions.contains(&Action::ShowDealerHoleCard(dealer_blackjack_hand[1])));
+        assert_actions_contains_new_hand(&actions, &dealer_blackjack_hand)
+    }
+
+    #[test]
+    fn deal_keeps_the_non_dealt_cards_in_the_deck() -> Result<(), Box<dyn std::error::Error>> {
+        let typical_hand = cards(vector!(
+            Rank::Two,
+            Rank::Two,
+            Rank::Two,
+            Rank::Ten,
+            Rank::Nine
+        ));
+        let context = Context::new_with_cards(typical_hand);
+
+        let game_state = GameState::Ready(context);
+
+        if let (GameState::WaitingForPlayer(context), _) = deal(&game_state)? {
+            assert_eq!(cards(vector!(Rank::Nine)), context.shoe.cards);
+            Ok(())
+        } else {
+            Err(Box::new(TestError::IncorrectTransitionError))
+        }
+    }
+
+    #[test]
+    fn dealer_has_blackjack_and_player_has_blackjack_leads_to_draw(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let double_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::Ten, Rank::Ten));
+        let context = Context::new_with_cards(double_blackjack);
+
+        let (new_state, _) = deal(&GameState::Ready(context))?;
+
+        match new_state {
+            GameState::Draw(_) => Ok(()),
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn dealer_has_blackjack_and_player_has_blackjack_returns_new_hand_and_draw_actions(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let double_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::Ten, Rank::Ten));
+        let context = Context::new_with_cards(double_blackjack.clone());
+
+        let (_, actions) = deal(&GameState::Ready(context))?;
+        assert_eq!(4, actions.len());
+        assert!(actions.contains(&Action::Draw(HandId::new(0, 0))));
+        assert!(actions.contains(&Action::ShowDealerHoleCard(double_blackjack[1])));
+        assert_actions_contains_new_hand(&actions, &double_blackjack)
+    }
+
+    #[test]
+    fn player_wins_with_blackjack() -> Result<(), Box<dyn std::error::Error>> {
+        let player_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::Ten, Rank::Nine));
+        let context = Context::new_with_cards(player_blackjack);
+
+        let (new_state, _) = deal(&GameState::Ready(context))?;
+
+        match new_state {
+            GameState::PlayerWins(_) => Ok(()),
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn player_wins_with_blackjack_has_player_blackjack_action(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let player_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::Ten, Rank::Nine));
+        let context = Context::new_with_cards(player_blackjack.clone());
+
+        let (_, actions) = deal(&GameState::Ready(context))?;
+        assert_eq!(4, actions.len());
+        assert!(actions.contains(&Action::PlayerBlackjack(0)));
+        assert!(actions.contains(&Action::ShowDealerHoleCard(player_blackjack[1])));
+        assert_actions_contains_new_hand(&actions, &player_blackjack)
+    }
+
+    #[test]
+    fn player_hits_hand_and_gets_one_card() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ace,
+            Rank::Two,
+            Rank::Four,
+            Rank::Ten,
+            Rank::Four
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (player_hits, actions) = hit(&game)?;
+
+        match player_hits {
+            GameState::WaitingForPlayer(context) => {
+                assert_eq!(context.player_score(), Score(19));
+                assert_eq!(actions.len(), 1);
+                if let Action::NewPlayerCard(_, new_card) =
+                    actions.front().ok_or(TestError::InvalidActionError)?
+                {
+                    assert_eq!(&cards[4], new_card);
+                }
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn player_hits_and_busts_transitions_to_player_lose() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Six,
+            Rank::Ten,
+            Rank::Eight
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (player_hits, actions) = hit(&game)?;
+
+        match player_hits {
+            GameState::DealerWins(context) => {
+                assert_eq!(context.player_score(), Score(24));
+                assert_eq!(actions.len(), 4);
+                assert!(actions.contains(&Action::PlayerBusts(HandId::new(0, 0))));
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                let new_card_action = actions
+                    .iter()
+                    .find(|action| matches!(action, Action::NewPlayerCard(_, _)))
+                    .ok_or(BlackjackError::NotFoundError)?;
+                if let Action::NewPlayerCard(_, card) = new_card_action {
+                    assert_eq!(&cards[4], card);
+                }
+
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn player_hits_and_gets_blackjack_transitions_to_stand(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Seven,
+            Rank::Ace
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (player_hits, actions) = hit(&game)?;
+
+        match player_hits {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.player_score(), BLACKJACK);
+                assert_eq!(actions.len(), 4);
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                assert!(actions.contains(&Action::PlayerWins(HandId::new(0, 0))));
+                let new_card_action = actions
+                    .iter()
+                    .find(|action| matches!(action, Action::NewPlayerCard(_, _)))
+                    .ok_or(BlackjackError::NotFoundError)?;
+                if let Action::NewPlayerCard(_, card) = new_card_action {
+                    assert_eq!(&cards[4], card);
+                }
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn player_stands_with_twenty_and_dealer_has_seventeen_player_wins(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Ten, Rank::Seven));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (player_stands, actions) = stand(&game)?;
+
+        match player_stands {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.player_score(), Score(20));
+                assert_eq!(context.dealer_score(), Score(17));
+                assert_eq!(actions.len(), 3);
+                assert!(actions.contains(&Action::PlayerWins(HandId::new(0, 0))));
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn player_stands_with_seventeen_and_dealer_has_twenty_dealer_wins(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Seven, Rank::Ten));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (player_stands, actions) = stand(&game)?;
+
+        match player_stands {
+            GameState::DealerWins(context) => {
+                assert_eq!(context.player_score(), Score(17));
+                assert_eq!(context.dealer_score(), Score(20));
+                assert_eq!(actions.len(), 3);
+                assert!(actions.contains(&Action::DealerWins(HandId::new(0, 0))));
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn player_stands_with_seventeen_and_dealer_hits_to_win(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Seven,
+            Rank::Six,
+            Rank::Three
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (player_stands, actions) = stand(&game)?;
+
+        match player_stands {
+            GameState::DealerWins(context) => {
+                assert_eq!(context.player_score(), Score(17));
+                assert_eq!(context.dealer_score(), Score(19));
+                assert_eq!(actions.len(), 4);
+                assert!(actions.contains(&Action::DealerWins(HandId::new(0, 0))));
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                assert_new_dealer_cards_are(actions, vector![Rank::Three]);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn player_stands_with_twenty_and_dealer_has_twenty_draw(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Ten, Rank::Ten));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (player_stands, actions) = stand(&game)?;
+
+        match player_stands {
+            GameState::Draw(context) => {
+                assert_eq!(context.player_score(), Score(20));
+                assert_eq!(context.dealer_score(), Score(20));
+                assert_eq!(actions.len(), 3);
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                assert!(actions.contains(&Action::Draw(HandId::new(0, 0))));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    fn assert_new_dealer_cards_are(actions: Vector<Action>, expected_cards: Vector<Rank>) {
+        let new_cards: Vector<Rank> = actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::NewDealerCards(cards) => Some(cards.clone()),
+                _ => None,
+            })
+            .flatten()
+            .map(|card| card.rank)
+            .collect();
+
+        assert_eq!(new_cards, expected_cards);
+    }
+
+    #[test]
+    fn dealer_hits_if_under_seventeen() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Six,
+            Rank::Ace
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (player_stands, actions) = stand(&game)?;
+
+        match player_stands {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.dealer_score(), Score(17));
+                assert_eq!(actions.len(), 4);
+                assert!(actions.contains(&Action::PlayerWins(HandId::new(0, 0))));
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                assert_new_dealer_cards_are(actions, vector![Rank::Ace]);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn dealer_hits_until_they_get_to_seventeen() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Two,
+            Rank::Ace,
+            Rank::Four
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (player_stands, actions) = stand(&game)?;
+
+        match player_stands {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.dealer_score(), Score(17));
+                assert_eq!(actions.len(), 4);
+                assert!(actions.contains(&Action::PlayerWins(HandId::new(0, 0))));
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                assert_new_dealer_cards_are(actions, vector![Rank::Ace, Rank::Four]);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn dealer_plays_their_hand_if_player_gets_blackjack_on_hit(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Two,
+            Rank::Ace,
+            Rank::Nine
+        ));
+
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (player_hits, actions) = hit(&game)?;
+
+        match player_hits {
+            GameState::Draw(context) => {
+                assert_eq!(context.dealer_score(), BLACKJACK);
+                assert_eq!(context.player_score(), BLACKJACK);
+                assert_eq!(actions.len(), 5);
+                assert!(actions.contains(&Action::Draw(HandId::new(0, 0))));
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                assert!(actions.contains(&Action::NewPlayerCard(
+                    HandId::new(0, 0),
+                    Card {
+                        rank: Rank::Ace,
+                        suit: Suit::Heart
+                    }
+                )));
+                assert_new_dealer_cards_are(actions, vector![Rank::Nine]);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn dealer_loses_if_they_bust() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Six,
+            Rank::Six
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (dealer_busts, actions) = stand(&game)?;
+
+        match dealer_busts {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.dealer_score(), Score(22));
+                assert_eq!(context.player_score(), Score(20));
+                assert_eq!(actions.len(), 4);
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                assert!(actions.contains(&Action::DealerBusts));
+                assert_new_dealer_cards_are(actions, vector![Rank::Six]);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn bet_moves_the_wager_out_of_the_bankroll() -> Result<(), Box<dyn std::error::Error>> {
+        let context = Context::new_with_cards(minimal_cards());
+
+        let (new_state, actions) = bet(&GameState::Ready(context), 0, Chips(100))?;
+
+        match new_state {
+            GameState::Ready(context) => {
+                assert_eq!(context.seat().wager, Chips(100));
+                assert_eq!(context.seat().bankroll.balance(), Chips(900));
+                assert_eq!(actions, vector![Action::WagerPlaced(0, Chips(100))]);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn betting_again_replaces_the_previous_wager() -> Result<(), Box<dyn std::error::Error>> {
+        let context = Context::new_with_cards(minimal_cards());
+
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(100))?;
+        let (game, _) = bet(&game, 0, Chips(50))?;
+
+        assert_eq!(game.context().seat().wager, Chips(50));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(950));
+        Ok(())
+    }
+
+    #[test]
+    fn cannot_bet_more_than_the_bankroll() {
+        let context = Context::new_with_cards(minimal_cards());
+
+        let result = bet(&GameState::Ready(context), 0, Chips(1001));
+
+        assert!(result.is_err(), "wager is larger than the bankroll");
+    }
+
+    #[test]
+    fn cannot_bet_while_waiting_for_player() {
+        let result = bet(&GameState::WaitingForPlayer(Context::empty()), 0, Chips(10));
+
+        assert!(result.is_err(), "bets are only placed before the deal");
+    }
+
+    #[test]
+    fn player_blackjack_pays_three_to_two() -> Result<(), Box<dyn std::error::Error>> {
+        let player_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::Ten, Rank::Nine));
+        let context = Context::new_with_cards(player_blackjack);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+
+        let (game, actions) = deal(&game)?;
+
+        assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(15))));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(1015));
+        Ok(())
+    }
+
+    #[test]
+    fn player_win_pays_one_to_one() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Ten, Rank::Seven));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = stand(&game)?;
+
+        assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(10))));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(1010));
+        Ok(())
+    }
+
+    #[test]
+    fn draw_returns_the_wager() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Ten, Rank::Ten));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = stand(&game)?;
+
+        assert!(actions.contains(&Action::WagerPushed(HandId::new(0, 0), Chips(10))));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(1000));
+        Ok(())
+    }
+
+    #[test]
+    fn player_bust_loses_the_wager() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Six,
+            Rank::Ten,
+            Rank::Eight
+        ));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = hit(&game)?;
+
+        assert!(actions.contains(&Action::WagerLost(HandId::new(0, 0), Chips(10))));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(990));
+        Ok(())
+    }
+
+    #[test]
+    fn betting_after_a_hand_carries_the_bankroll_into_the_next_hand(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Ten, Rank::Seven));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = stand(&game)?;
+
+        let (game, _) = bet(&game, 0, Chips(20))?;
+
+        match game {
+            GameState::Ready(context) => {
+                assert_eq!(context.seat().wager, Chips(20));
+                assert_eq!(context.seat().bankroll.balance(), Chips(990));
+                assert_eq!(context.seat().player_hands, Vector::new());
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn double_down_doubles_the_wager_and_deals_one_card() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let cards = cards(vector!(
+            Rank::Six,
+            Rank::Ten,
+            Rank::Five,
+            Rank::Seven,
+            Rank::Ten
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (doubled, actions) = double_down(&game)?;
+
+        match doubled {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.player_score(), Score(21));
+                assert_eq!(context.player_hand().cards().len(), 3);
+                assert_eq!(context.seat().player_hands[0].wager, Chips(20));
+                assert_eq!(context.seat().bankroll.balance(), Chips(1020));
+                assert_eq!(
+                    actions.front(),
+                    Some(&Action::DoubleDown(HandId::new(0, 0), Chips(20), cards[4]))
+                );
+                assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(20))));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn double_down_forces_the_dealer_to_play() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Six,
+            Rank::Ten,
+            Rank::Two,
+            Rank::Four,
+            Rank::Three,
+            Rank::Five
+        ));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (doubled, actions) = double_down(&game)?;
+
+        match doubled {
+            GameState::DealerWins(context) => {
+                assert_eq!(context.player_score(), Score(11));
+                assert_eq!(context.dealer_score(), Score(19));
+                assert!(actions.contains(&Action::DealerWins(HandId::new(0, 0))));
+                assert_new_dealer_cards_are(actions, vector![Rank::Five]);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn double_down_can_bust_the_player() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Six,
+            Rank::Seven,
+            Rank::Ten
+        ));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (doubled, actions) = double_down(&game)?;
+
+        match doubled {
+            GameState::DealerWins(context) => {
+                assert_eq!(context.seat().bankroll.balance(), Chips(980));
+                assert!(actions.contains(&Action::PlayerBusts(HandId::new(0, 0))));
+                assert!(actions.contains(&Action::WagerLost(HandId::new(0, 0), Chips(20))));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn cannot_double_down_after_hitting() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Two,
+            Rank::Ten,
+            Rank::Three,
+            Rank::Seven,
+            Rank::Two
+        ));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = deal(&GameState::Ready(context))?;
+        let (game, _) = hit(&game)?;
+
+        let result = double_down(&game);
+
+        assert!(result.is_err(), "double down is only allowed on two cards");
+        Ok(())
+    }
+
+    #[test]
+    fn cannot_double_down_without_the_chips_to_cover_it() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let context = Context::new_with_cards(minimal_cards());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(600))?;
+        let (game, _) = deal(&game)?;
+
+        let result = double_down(&game);
+
+        assert!(result.is_err(), "double down needs a second wager");
+        Ok(())
+    }
+
+    #[test]
+    fn split_turns_a_pair_into_two_hands() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Eight,
+            Rank::Ten,
+            Rank::Eight,
+            Rank::Seven,
+            Rank::Three,
+            Rank::Two
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (split_game, actions) = split(&game)?;
+
+        match split_game {
+            GameState::WaitingForPlayer(context) => {
+                assert_eq!(context.seat().player_hands.len(), 2);
+                assert_eq!(context.seat().active_hand, 0);
+                assert_eq!(
+                    context.seat().player_hands[0].hand,
+                    Hand::new().add(cards[0]).add(cards[4])
+                );
+                assert_eq!(
+                    context.seat().player_hands[1].hand,
+                    Hand::new().add(cards[2]).add(cards[5])
+                );
+                assert_eq!(context.seat().player_hands[1].wager, Chips(10));
+                assert_eq!(context.seat().bankroll.balance(), Chips(980));
+                assert_eq!(
+                    actions,
+                    vector![
+                        Action::Split(HandId::new(0, 0)),
+                        Action::NewPlayerCard(HandId::new(0, 0), cards[4]),
+                        Action::NewPlayerCard(HandId::new(0, 1), cards[5])
+                    ]
+                );
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn cannot_split_cards_of_different_ranks() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::King, Rank::Ten, Rank::Queen, Rank::Seven));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let result = split(&game);
+
+        assert!(result.is_err(), "only a pair of the same rank can split");
+        Ok(())
+    }
+
+    #[test]
+    fn standing_on_a_split_hand_moves_to_the_next_hand() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Eight,
+            Rank::Ten,
+            Rank::Eight,
+            Rank::Seven,
+            Rank::Ten,
+            Rank::Two
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+        let (game, _) = split(&game)?;
+
+        let (game, actions) = stand(&game)?;
+
+        match game {
+            GameState::WaitingForPlayer(context) => {
+                assert_eq!(context.seat().active_hand, 1);
+                assert_eq!(context.player_score(), Score(10));
+                assert!(actions.is_empty());
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn the_dealer_plays_after_the_last_split_hand() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Eight,
+            Rank::Ten,
+            Rank::Eight,
+            Rank::Six,
+            Rank::Ten,
+            Rank::Two,
+            Rank::Nine,
+            Rank::Two
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = split(&game)?;
+        let (game, _) = stand(&game)?;
+        let (game, hit_actions) = hit(&game)?;
+        assert_eq!(
+            hit_actions,
+            vector![Action::NewPlayerCard(HandId::new(0, 1), cards[6])]
+        );
+
+        let (game, actions) = stand(&game)?;
+
+        match game {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.dealer_score(), Score(18));
+                assert!(actions.contains(&Action::PlayerWins(HandId::new(0, 1))));
+                assert!(actions.contains(&Action::WagerWon(HandId::new(0, 1), Chips(10))));
+                assert!(actions.contains(&Action::Draw(HandId::new(0, 0))));
+                assert!(actions.contains(&Action::WagerPushed(HandId::new(0, 0), Chips(10))));
+                assert_eq!(context.seat().bankroll.balance(), Chips(1010));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn split_hands_are_settled_one_by_one_when_the_dealer_busts(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Eight,
+            Rank::Ten,
+            Rank::Eight,
+            Rank::Six,
+            Rank::Ten,
+            Rank::Nine,
+            Rank::Ten
+        ));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = split(&game)?;
+        let (game, _) = stand(&game)?;
+
+        let (game, actions) = stand(&game)?;
+
+        match game {
+            GameState::PlayerWins(context) => {
+                assert!(actions.contains(&Action::DealerBusts));
+                assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(10))));
+                assert!(actions.contains(&Action::WagerWon(HandId::new(0, 1), Chips(10))));
+                assert_eq!(context.seat().bankroll.balance(), Chips(1020));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn split_hands_can_be_resplit_up_to_the_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Eight,
+            Rank::Ten,
+            Rank::Eight,
+            Rank::Seven,
+            Rank::Eight,
+            Rank::Two,
+            Rank::Eight,
+            Rank::Three
+        ));
+        let context = Context::new_with_rules(
+            cards,
+            TableRules {
+                split_limit: 3,
+                ..TableRules::default()
+            },
+        );
+        let (game, _) = deal(&GameState::Ready(context))?;
+        let (game, _) = split(&game)?;
+        let (game, actions) = split(&game)?;
+
+        assert_eq!(actions[0], Action::Split(HandId::new(0, 0)));
+        assert_eq!(game.context().seat().player_hands.len(), 3);
+        assert!(!game.context().can_split());
+        let result = split(&game);
+
+        assert!(result.is_err(), "split limit has been reached");
+        Ok(())
+    }
+
+    #[test]
+    fn split_aces_get_one_card_each() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ace,
+            Rank::Ten,
+            Rank::Ace,
+            Rank::Seven,
+            Rank::Five,
+            Rank::Ten
+        ));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = split(&game)?;
+
+        match game {
+            GameState::Draw(context) => {
+                assert_eq!(context.seat().player_hands[0].score(), Score(16));
+                assert_eq!(context.seat().player_hands[1].score(), Score(21));
+                assert!(actions.contains(&Action::DealerWins(HandId::new(0, 0))));
+                assert!(actions.contains(&Action::PlayerWins(HandId::new(0, 1))));
+                assert!(actions.contains(&Action::WagerWon(HandId::new(0, 1), Chips(10))));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn deal_offers_insurance_when_the_dealer_shows_an_ace() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let cards = cards(vector!(Rank::Nine, Rank::Ten, Rank::Seven, Rank::Ace));
+        let context = Context::new_with_cards(cards.clone());
+
+        let (game, actions) = deal(&GameState::Ready(context))?;
+
+        match game {
+            GameState::OfferingInsurance(context) => {
+                assert!(!context.offers_even_money());
+                assert_eq!(actions.len(), 2);
+                assert!(actions.contains(&Action::InsuranceOffered));
+                assert_actions_contains_new_hand(&actions, &cards)
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn cannot_hit_while_insurance_is_offered() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Nine, Rank::Ten, Rank::Seven, Rank::Ace));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let result = hit(&game);
+
+        assert!(result.is_err(), "insurance must be taken or declined first");
+        Ok(())
+    }
+
+    #[test]
+    fn insurance_pays_two_to_one_when_the_dealer_has_blackjack(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Nine, Rank::Ten, Rank::Seven, Rank::Ace));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (insured, actions) = take_insurance(&game)?;
+
+        match insured {
+            GameState::DealerWins(context) => {
+                assert_eq!(context.seat().insurance, Chips(5));
+                assert_eq!(context.seat().bankroll.balance(), Chips(1000));
+                assert_eq!(actions.front(), Some(&Action::InsuranceWon(0, Chips(10))));
+                assert!(actions.contains(&Action::DealerBlackjack));
+                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+                assert!(actions.contains(&Action::WagerLost(HandId::new(0, 0), Chips(10))));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn insurance_is_lost_and_play_continues_without_dealer_blackjack(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Nine, Rank::Six, Rank::Seven, Rank::Ace));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (insured, actions) = take_insurance(&game)?;
+
+        match insured {
+            GameState::WaitingForPlayer(context) => {
+                assert_eq!(context.seat().bankroll.balance(), Chips(985));
+                assert_eq!(actions, vector![Action::InsuranceLost(0, Chips(5))]);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn declining_insurance_still_checks_for_dealer_blackjack(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Nine, Rank::Ten, Rank::Seven, Rank::Ace));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (declined, actions) = decline_insurance(&game)?;
+
+        match declined {
+            GameState::DealerWins(context) => {
+                assert_eq!(context.seat().bankroll.balance(), Chips(990));
+                assert!(actions.contains(&Action::DealerBlackjack));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn even_money_pays_a_natural_one_to_one() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ace, Rank::Ten, Rank::King, Rank::Ace));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        assert!(game.context().offers_even_money());
+
+        let (even_money, actions) = take_insurance(&game)?;
+
+        match even_money {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.seat().bankroll.balance(), Chips(1010));
+                assert!(actions.contains(&Action::EvenMoney(0)));
+                assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(10))));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn surrender_refunds_half_the_wager() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Six, Rank::Nine));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (surrendered, actions) = surrender(&game)?;
+
+        match surrendered {
+            GameState::PlayerSurrenders(context) => {
+                assert_eq!(context.seat().bankroll.balance(), Chips(995));
+                assert_eq!(
+                    actions,
+                    vector![
+                        Action::PlayerSurrenders(0),
+                        Action::WagerSurrendered(HandId::new(0, 0), Chips(5)),
+                        Action::ShowDealerHoleCard(cards[1])
+                    ]
+                );
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn cannot_surrender_after_hitting() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Two,
+            Rank::Ten,
+            Rank::Three,
+            Rank::Nine,
+            Rank::Four
+        ));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = deal(&GameState::Ready(context))?;
+        let (game, _) = hit(&game)?;
+
+        let result = surrender(&game);
+
+        assert!(
+            result.is_err(),
+            "surrender is only allowed on the first decision"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cannot_surrender_when_the_table_does_not_allow_it() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let context = Context::new_with_rules(
+            minimal_cards(),
+            TableRules {
+                surrender: SurrenderRule::NoSurrender,
+                ..TableRules::default()
+            },
+        );
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let result = surrender(&game);
+
+        assert!(result.is_err(), "the table does not offer surrender");
+        Ok(())
+    }
+
+    #[test]
+    fn late_surrender_comes_after_the_dealer_checks_for_blackjack(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ace, Rank::Six, Rank::Ten));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let result = surrender(&game);
+
+        assert!(matches!(game, GameState::DealerWins(_)));
+        assert!(result.is_err(), "the hand is already over");
+        Ok(())
+    }
+
+    #[test]
+    fn early_surrender_comes_before_the_dealer_checks_for_blackjack(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ace, Rank::Six, Rank::Ten));
+        let context = Context::new_with_rules(
+            cards,
+            TableRules {
+                surrender: SurrenderRule::Early,
+                ..TableRules::default()
+            },
+        );
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (surrendered, _) = surrender(&game)?;
+
+        match surrendered {
+            GameState::PlayerSurrenders(context) => {
+                assert_eq!(context.seat().bankroll.balance(), Chips(995));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn early_surrender_is_offered_alongside_insurance() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Six, Rank::Ace));
+        let context = Context::new_with_rules(
+            cards,
+            TableRules {
+                surrender: SurrenderRule::Early,
+                ..TableRules::default()
+            },
+        );
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        assert!(game.can_surrender());
+
+        let (surrendered, _) = surrender(&game)?;
+
+        assert!(matches!(surrendered, GameState::PlayerSurrenders(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn late_surrender_is_not_offered_alongside_insurance() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Six, Rank::Ace));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        assert!(!game.can_surrender());
+        assert!(surrender(&game).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn with_early_surrender_the_dealer_checks_for_blackjack_before_other_actions(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ace,
+            Rank::Six,
+            Rank::Ten,
+            Rank::Two
+        ));
+        let context = Context::new_with_rules(
+            cards,
+            TableRules {
+                surrender: SurrenderRule::Early,
+                ..TableRules::default()
+            },
+        );
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = hit(&game)?;
+
+        match game {
+            GameState::DealerWins(context) => {
+                assert_eq!(context.player_hand().cards().len(), 2);
+                assert!(actions.contains(&Action::DealerBlackjack));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn with_early_surrender_play_continues_when_the_dealer_has_no_blackjack(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Nine,
+            Rank::Six,
+            Rank::Ten,
+            Rank::Two
+        ));
+        let context = Context::new_with_rules(
+            cards.clone(),
+            TableRules {
+                surrender: SurrenderRule::Early,
+                ..TableRules::default()
+            },
+        );
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (game, actions) = hit(&game)?;
+
+        match game {
+            GameState::WaitingForPlayer(context) => {
+                assert_eq!(context.player_score(), Score(18));
+                assert_eq!(
+                    actions,
+                    vector![Action::NewPlayerCard(HandId::new(0, 0), cards[4])]
+                );
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn the_shoe_carries_over_into_the_next_hand() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Seven,
+            Rank::Two,
+            Rank::Three,
+            Rank::Four,
+            Rank::Five,
+            Rank::Six
+        ));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = deal(&GameState::Ready(context))?;
+        let (game, _) = stand(&game)?;
+
+        let (game, actions) = deal(&game)?;
+
+        assert!(!actions.contains(&Action::Reshuffle));
+        assert_eq!(
+            game.context().player_hand(),
+            Hand::new().add(cards[4]).add(cards[6])
+        );
+        assert_eq!(game.context().shoe.cards, vector![cards[8]]);
+        Ok(())
+    }
+
+    #[test]
+    fn deal_reshuffles_once_the_cut_card_comes_out() -> Result<(), Box<dyn std::error::Error>> {
+        let mut shoe = Shoe::new(2, 0.5, 1)?;
+        while !shoe.cut_card_reached() {
+            shoe = shoe.deal()?.0;
+        }
+        let context = Context::new(shoe, TableRules::default(), 1);
+
+        let (game, actions) = deal(&GameState::Ready(context))?;
+
+        assert_eq!(actions.front(), Some(&Action::Reshuffle));
+        assert_eq!(game.context().shoe.cards.len(), 2 * 52 - 4);
+        Ok(())
+    }
+
+    #[test]
+    fn a_cut_card_out_after_the_reshuffle_still_deals_once(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let rules = TableRules {
+            penetration: 0.001,
+            ..TableRules::default()
+        };
+        let game = GameState::new(rules, 1)?;
+        assert!(game.context().shoe.cut_card_reached());
+
+        let (game, actions) = deal(&game)?;
+
+        assert_eq!(actions.front(), Some(&Action::Reshuffle));
+        assert_eq!(game.context().shoe.cards.len(), 52 - 4);
+        Ok(())
+    }
+
+    #[test]
+    fn a_table_needs_a_shoe_it_can_deal_from() {
+        let no_decks = TableRules {
+            decks: 0,
+            ..TableRules::default()
+        };
+        let no_penetration = TableRules {
+            penetration: 0.0,
+            ..TableRules::default()
+        };
+
+        assert!(matches!(
+            GameState::new(no_decks, 1),
+            Err(BlackjackError::RulesError(RulesError::DeckCountError(0)))
+        ));
+        assert!(matches!(
+            GameState::new_table(no_penetration, 2, 1),
+            Err(BlackjackError::RulesError(RulesError::PenetrationError(_)))
+        ));
+    }
+
+    #[test]
+    fn deal_does_not_reshuffle_before_the_cut_card() -> Result<(), Box<dyn std::error::Error>> {
+        let context = Context::new(
+            Shoe::new(6, DEFAULT_PENETRATION, 1)?,
+            TableRules::default(),
+            1,
+        );
+
+        let (game, actions) = deal(&GameState::Ready(context))?;
+
+        assert!(!actions.contains(&Action::Reshuffle));
+        assert_eq!(game.context().shoe.cards.len(), 6 * 52 - 4);
+        Ok(())
+    }
+
+    #[test]
+    fn dealer_stands_on_soft_seventeen_by_default() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ace,
+            Rank::Ten,
+            Rank::Six,
+            Rank::Four
+        ));
+        let context = Context::new_with_cards(cards);
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (game, _) = stand(&game)?;
+
+        match game {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.dealer_score(), Score(17));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn dealer_hits_soft_seventeen_when_the_table_says_so() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ace,
+            Rank::Ten,
+            Rank::Six,
+            Rank::Four
+        ));
+        let rules = TableRules {
+            dealer: DealerRule::HitsSoft17,
+            ..TableRules::default()
+        };
+        let context = Context::new_with_rules(cards, rules);
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (game, actions) = stand(&game)?;
+
+        match game {
+            GameState::DealerWins(context) => {
+                assert_eq!(context.dealer_score(), Score(21));
+                assert_new_dealer_cards_are(actions, vector![Rank::Four]);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn blackjack_pays_what_the_table_says() -> Result<(), Box<dyn std::error::Error>> {
+        let player_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::Ten, Rank::Nine));
+        let rules = TableRules {
+            blackjack_payout: BlackjackPayout::SixToFive,
+            ..TableRules::default()
+        };
+        let context = Context::new_with_rules(player_blackjack, rules);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+
+        let (game, actions) = deal(&game)?;
+
+        assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(12))));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(1012));
+        Ok(())
+    }
+
+    #[test]
+    fn cannot_double_down_outside_nine_to_eleven_when_the_table_restricts_it(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Nine, Rank::Seven, Rank::Nine));
+        let rules = TableRules {
+            double_rule: DoubleRule::NineToEleven,
+            ..TableRules::default()
+        };
+        let context = Context::new_with_rules(cards, rules);
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let result = double_down(&game);
+
+        assert!(!game.context().can_double_down());
+        assert!(result.is_err(), "seventeen is not nine to eleven");
+        Ok(())
+    }
+
+    #[test]
+    fn cannot_double_after_splitting_when_the_table_forbids_it(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Eight,
+            Rank::Ten,
+            Rank::Eight,
+            Rank::Nine,
+            Rank::Three,
+            Rank::Four
+        ));
+        let rules = TableRules {
+            double_after_split: false,
+            ..TableRules::default()
+        };
+        let context = Context::new_with_rules(cards, rules);
+        let (game, _) = deal(&GameState::Ready(context))?;
+        let (game, _) = split(&game)?;
+
+        let result = double_down(&game);
+
+        assert!(!game.context().can_double_down());
+        assert!(result.is_err(), "no doubling after a split");
+        Ok(())
+    }
+
+    #[test]
+    fn split_aces_can_be_hit_when_the_table_allows_it() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ace,
+            Rank::Ten,
+            Rank::Ace,
+            Rank::Seven,
+            Rank::Five,
+            Rank::Ten
+        ));
+        let rules = TableRules {
+            hit_split_aces: true,
+            ..TableRules::default()
+        };
+        let context = Context::new_with_rules(cards, rules);
+        let (game, _) = deal(&GameState::Ready(context))?;
+
+        let (game, _) = split(&game)?;
+
+        match game {
+            GameState::WaitingForPlayer(context) => {
+                assert_eq!(context.seat().active_hand, 0);
+                assert_eq!(context.player_score(), Score(16));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn a_seed_replays_the_whole_session() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = TableRules {
+            penetration: 0.25,
+            ..TableRules::default()
+        };
+        let play_session = || -> Result<Vector<Action>, Box<dyn std::error::Error>> {
+            let mut game = GameState::new(rules.clone(), 2020)?;
+            let mut session = Vector::new();
+            for _ in 0..6 {
+                let (dealt, actions) = deal(&game)?;
+                session.extend(actions);
+                game = match dealt {
+                    GameState::OfferingInsurance(_) => decline_insurance(&dealt)?.0,
+                    _ => dealt,
+                };
+                if let GameState::WaitingForPlayer(_) = game {
+                    let (stood, actions) = stand(&game)?;
+                    session.extend(actions);
+                    game = stood;
+                }
+            }
+            Ok(session)
+        };
+
+        let first_session = play_session()?;
+
+        assert!(first_session.contains(&Action::Reshuffle));
+        assert_eq!(first_session, play_session()?);
+        Ok(())
+    }
+
+    #[test]
+    fn a_table_must_seat_one_to_seven_players() {
+        assert!(matches!(
+            GameState::new_table(TableRules::default(), 0, 1),
+            Err(BlackjackError::SeatCountError(0))
+        ));
+        assert!(matches!(
+            GameState::new_table(TableRules::default(), MAX_SEATS + 1, 1),
+            Err(BlackjackError::SeatCountError(8))
+        ));
+        assert_eq!(
+            GameState::new_table(TableRules::default(), MAX_SEATS, 1)
+                .unwrap()
+                .context()
+                .seats
+                .len(),
+            MAX_SEATS
+        );
+    }
+
+    #[test]
+    fn bets_are_placed_at_a_seat() -> Result<(), Box<dyn std::error::Error>> {
+        let game = GameState::new_table(TableRules::default(), 2, 1)?;
+
+        let (game, actions) = bet(&game, 1, Chips(25))?;
+
+        assert_eq!(actions, vector![Action::WagerPlaced(1, Chips(25))]);
+        assert_eq!(game.context().seats[0].wager, Chips(0));
+        assert_eq!(game.context().seats[1].wager, Chips(25));
+        assert_eq!(game.context().seats[1].bankroll.balance(), Chips(975));
+        assert!(bet(&game, 2, Chips(25)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn the_deal_goes_around_the_table_before_the_dealer() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let cards = cards(vector!(
+            Rank::Ace,
+            Rank::Two,
+            Rank::Three,
+            Rank::Four,
+            Rank::Five,
+            Rank::Six,
+            Rank::Seven,
+            Rank::Eight
+        ));
+        let context = Context::new(
+            Shoe::new_with_cards(cards.clone()),
+            TableRules::default(),
+            3,
+        );
+
+        let (new_context, _) = context.deal_initial_hands()?;
+
+        for seat in 0..3 {
+            assert_eq!(
+                new_context.seats[seat].player_hand(),
+                Hand::new().add(cards[seat]).add(cards[seat + 4])
+            );
+        }
+        assert_eq!(
+            new_context.dealer_hand,
+            DealerHand::new().add(cards[3]).add(cards[7])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn turns_pass_seat_by_seat_and_the_dealer_plays_once() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Seven,
+            Rank::Six,
+            Rank::Six,
+            Rank::Ten,
+            Rank::Two
+        ));
+        let context = Context::new(
+            Shoe::new_with_cards(cards.clone()),
+            TableRules::default(),
+            2,
+        );
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = bet(&game, 1, Chips(20))?;
+        let (game, _) = deal(&game)?;
+        assert_eq!(game.context().active_seat, 0);
+
+        let (game, actions) = stand(&game)?;
+        assert!(actions.is_empty());
+        assert_eq!(game.context().active_seat, 1);
+
+        let (game, actions) = hit(&game)?;
+        assert_eq!(
+            actions,
+            vector![Action::NewPlayerCard(HandId::new(1, 0), cards[6])]
+        );
+
+        let (game, actions) = stand(&game)?;
+
+        let hole_cards_shown = actions
+            .iter()
+            .filter(|action| matches!(action, Action::ShowDealerHoleCard(_)))
+            .count();
+        assert_eq!(hole_cards_shown, 1);
+        assert!(actions.contains(&Action::DealerWins(HandId::new(0, 0))));
+        assert!(actions.contains(&Action::PlayerWins(HandId::new(1, 0))));
+        assert!(actions.contains(&Action::WagerWon(HandId::new(1, 0), Chips(20))));
+        assert_eq!(game.context().seats[0].bankroll.balance(), Chips(990));
+        assert_eq!(game.context().seats[1].bankroll.balance(), Chips(1020));
+        Ok(())
+    }
+
+    #[test]
+    fn a_natural_is_paid_while_the_other_seats_play() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ace,
+            Rank::Ten,
+            Rank::Seven,
+            Rank::Ten,
+            Rank::Six,
+            Rank::Ten
+        ));
+        let context = Context::new(
+            Shoe::new_with_cards(cards.clone()),
+            TableRules::default(),
+            2,
+        );
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = bet(&game, 1, Chips(10))?;
+
+        let (game, actions) = deal(&game)?;
+
+        match &game {
+            GameState::WaitingForPlayer(context) => {
+                assert_eq!(context.active_seat, 1);
+                assert!(actions.contains(&Action::PlayerBlackjack(0)));
+                assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(15))));
+                assert!(!actions.contains(&Action::ShowDealerHoleCard(cards[2])));
+                assert_eq!(context.seats[0].bankroll.balance(), Chips(1015));
+            }
+            _ => return Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+
+        let (game, actions) = stand(&game)?;
+
+        assert!(actions.contains(&Action::WagerLost(HandId::new(1, 0), Chips(10))));
+        assert!(!actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(15))));
+        assert_eq!(game.context().seats[0].bankroll.balance(), Chips(1015));
+        assert_eq!(game.context().seats[1].bankroll.balance(), Chips(990));
+        Ok(())
+    }
+
+    #[test]
+    fn insurance_is_offered_to_each_seat_in_turn() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Ten,
+            Rank::Nine,
+            Rank::Nine,
+            Rank::Eight,
+            Rank::Ace
+        ));
+        let context = Context::new(
+            Shoe::new_with_cards(cards.clone()),
+            TableRules::default(),
+            2,
+        );
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = bet(&game, 1, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = take_insurance(&game)?;
+        assert!(actions.is_empty());
+        assert!(matches!(game, GameState::OfferingInsurance(_)));
+        assert_eq!(game.context().active_seat, 1);
+
+        let (game, actions) = decline_insurance(&game)?;
+
+        match game {
+            GameState::WaitingForPlayer(context) => {
+                assert_eq!(context.active_seat, 0);
+                assert_eq!(actions, vector![Action::InsuranceLost(0, Chips(5))]);
+                assert_eq!(context.seats[0].bankroll.balance(), Chips(985));
+                assert_eq!(context.seats[1].bankroll.balance(), Chips(990));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    fn no_hole_card_rules(hole_card: HoleCardRule) -> TableRules {
+        TableRules {
+            hole_card,
+            ..TableRules::default()
+        }
+    }
+
+    #[test]
+    fn without_a_hole_card_the_dealer_is_dealt_only_the_upcard(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ace, Rank::Seven));
+        let context =
+            Context::new_with_rules(cards.clone(), no_hole_card_rules(HoleCardRule::NoHoleCard));
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+
+        let (game, actions) = deal(&game)?;
+
+        match game {
+            GameState::WaitingForPlayer(context) => {
+                assert_eq!(context.dealer_hand.cards(), vector![cards[1]]);
+                assert_eq!(context.dealer_hand.upcard(), Some(&cards[1]));
+                assert_eq!(context.dealer_hand.hole_card(), None);
+                assert_eq!(
+                    context.player_hand(),
+                    Hand::new().add(cards[0]).add(cards[2])
+                );
+                assert!(!actions.contains(&Action::InsuranceOffered));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn without_a_hole_card_the_dealer_draws_after_the_players(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Six,
+            Rank::Nine,
+            Rank::Ten,
+            Rank::Two
+        ));
+        let context =
+            Context::new_with_rules(cards.clone(), no_hole_card_rules(HoleCardRule::NoHoleCard));
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = stand(&game)?;
+
+        match game {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.dealer_score(), Score(18));
+                assert!(!actions
+                    .iter()
+                    .any(|action| matches!(action, Action::ShowDealerHoleCard(_))));
+                assert!(actions.contains(&Action::NewDealerCards(vector![cards[3], cards[4]])));
+                assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(10))));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn without_a_hole_card_a_dealer_natural_takes_doubled_bets(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Six,
+            Rank::Ace,
+            Rank::Five,
+            Rank::Nine,
+            Rank::King
+        ));
+        let context =
+            Context::new_with_rules(cards.clone(), no_hole_card_rules(HoleCardRule::NoHoleCard));
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = double_down(&game)?;
+
+        match game {
+            GameState::DealerWins(context) => {
+                assert!(actions.contains(&Action::DealerBlackjack));
+                assert!(actions.contains(&Action::WagerLost(HandId::new(0, 0), Chips(20))));
+                assert_eq!(context.seat().bankroll.balance(), Chips(980));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn original_bets_only_returns_the_double_to_a_dealer_natural(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Six,
+            Rank::Ace,
+            Rank::Five,
+            Rank::Nine,
+            Rank::King
+        ));
+        let context = Context::new_with_rules(
+            cards.clone(),
+            no_hole_card_rules(HoleCardRule::OriginalBetsOnly),
+        );
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = double_down(&game)?;
+
+        match game {
+            GameState::DealerWins(context) => {
+                assert!(actions.contains(&Action::WagerLost(HandId::new(0, 0), Chips(10))));
+                assert_eq!(context.seat().bankroll.balance(), Chips(990));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn original_bets_only_returns_split_bets_to_a_dealer_natural(
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let dealer_blackjack_hand = cards(vector!(Rank::Two, Rank::Ace, Rank::Two, Rank::Ten));
-        let context = Context::new_with_cards(dealer_blackjack_hand.clone());
-        let game_state = GameState::Ready(context);
+        let cards = cards(vector!(
+            Rank::Eight,
+            Rank::Ace,
+            Rank::Eight,
+            Rank::Two,
+            Rank::Three,
+            Rank::King
+        ));
+        let context = Context::new_with_rules(
+            cards.clone(),
+            no_hole_card_rules(HoleCardRule::OriginalBetsOnly),
+        );
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = split(&game)?;
+        let (game, _) = stand(&game)?;
 
-        let (_, actions) = deal(&game_state)?;
+        let (game, actions) = stand(&game)?;
 
-        assert_eq!(3, actions.len());
-        assert!(actions.contains(&Action::DealerBlackjack));
-        assert!(actions.contains(&Action::ShowDealerHoleCard(dealer_blackjack_hand[1])));
-        assert_actions_contains_new_hand(&actions, &dealer_blackjack_hand)
+        assert!(actions.contains(&Action::WagerLost(HandId::new(0, 0), Chips(10))));
+        assert!(actions.contains(&Action::WagerPushed(HandId::new(0, 1), Chips(10))));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(990));
+        Ok(())
     }
 
     #[test]
-    fn deal_keeps_the_non_dealt_cards_in_the_deck() -> Result<(), Box<dyn std::error::Error>> {
-        let typical_hand = cards(vector!(
+    fn without_a_hole_card_a_natural_pushes_against_a_dealer_natural(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ace, Rank::Ten, Rank::King, Rank::Ace));
+        let context =
+            Context::new_with_rules(cards.clone(), no_hole_card_rules(HoleCardRule::NoHoleCard));
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+
+        let (game, actions) = deal(&game)?;
+
+        match game {
+            GameState::Draw(context) => {
+                assert!(actions.contains(&Action::Draw(HandId::new(0, 0))));
+                assert!(!actions.contains(&Action::DealerBlackjack));
+                assert_eq!(context.seat().bankroll.balance(), Chips(1000));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn without_a_hole_card_a_natural_only_waits_for_the_dealers_second_card(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ace,
+            Rank::Ten,
+            Rank::King,
+            Rank::Six,
+            Rank::Five
+        ));
+        let context =
+            Context::new_with_rules(cards.clone(), no_hole_card_rules(HoleCardRule::NoHoleCard));
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+
+        let (game, actions) = deal(&game)?;
+
+        match game {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.dealer_score(), Score(16));
+                assert!(actions.contains(&Action::NewDealerCards(vector![cards[3]])));
+                assert!(actions.contains(&Action::PlayerBlackjack(0)));
+                assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(15))));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn running_the_shoe_dry_in_mid_round_announces_the_reshuffle(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Two, Rank::Six, Rank::Nine));
+        let context = Context::new_with_cards(cards.clone());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, actions) = deal(&game)?;
+        assert!(!actions.contains(&Action::Reshuffle));
+
+        let (_, actions) = hit(&game)?;
+
+        assert_eq!(actions.front(), Some(&Action::Reshuffle));
+        match actions.get(1) {
+            Some(Action::NewPlayerCard(_, card)) => {
+                assert!(!cards.contains(card));
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::InvalidActionError)),
+        }
+    }
+
+    #[test]
+    fn a_full_table_never_runs_the_shoe_dry() -> Result<(), Box<dyn std::error::Error>> {
+        let rules = TableRules {
+            penetration: 1.0,
+            ..TableRules::default()
+        };
+        let mut game = GameState::new_table(rules, MAX_SEATS, 3)?;
+
+        for _ in 0..50 {
+            for seat in 0..MAX_SEATS {
+                game = bet(&game, seat, Chips(10))?.0;
+            }
+            game = deal(&game)?.0;
+            loop {
+                game = match game {
+                    GameState::OfferingInsurance(_) => decline_insurance(&game)?.0,
+                    GameState::WaitingForPlayer(ref context)
+                        if context.player_score() < Score(17) =>
+                    {
+                        hit(&game)?.0
+                    }
+                    GameState::WaitingForPlayer(_) => stand(&game)?.0,
+                    _ => break,
+                };
+            }
+        }
+        Ok(())
+    }
+
+    fn side_bet_rules(hole_card: HoleCardRule) -> TableRules {
+        TableRules {
+            hole_card,
+            side_bets: SideBet::iter()
+                .map(|side_bet| (side_bet, side_bet.paytable()))
+                .collect(),
+            ..TableRules::default()
+        }
+    }
+
+    #[test]
+    fn side_bets_must_be_offered_by_the_table() -> Result<(), Box<dyn std::error::Error>> {
+        let game = GameState::new_table(TableRules::default(), 1, 1)?;
+        assert!(place_side_bet(&game, 0, SideBet::PerfectPairs, Chips(5)).is_err());
+
+        let game = GameState::new_table(side_bet_rules(HoleCardRule::Peek), 1, 1)?;
+        let (game, actions) = place_side_bet(&game, 0, SideBet::PerfectPairs, Chips(5))?;
+        assert_eq!(
+            actions,
+            vector![Action::SideBetPlaced(0, SideBet::PerfectPairs, Chips(5))]
+        );
+
+        let (game, _) = place_side_bet(&game, 0, SideBet::PerfectPairs, Chips(20))?;
+        assert_eq!(
+            game.context().seat().side_bets,
+            vector![(SideBet::PerfectPairs, Chips(20))]
+        );
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(980));
+        Ok(())
+    }
+
+    #[test]
+    fn side_bets_settle_from_the_initial_cards() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Eight, Rank::Ten, Rank::Eight, Rank::Seven));
+        let context = Context::new_with_rules(cards, side_bet_rules(HoleCardRule::Peek));
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = place_side_bet(&game, 0, SideBet::PerfectPairs, Chips(5))?;
+        let (game, _) = place_side_bet(&game, 0, SideBet::TwentyOnePlusThree, Chips(5))?;
+        let (game, _) = place_side_bet(&game, 0, SideBet::LuckyLadies, Chips(5))?;
+
+        let (game, actions) = deal(&game)?;
+
+        assert!(actions.contains(&Action::SideBetWon(
+            0,
+            SideBet::PerfectPairs,
+            SideBetOutcome::PerfectPair,
+            Chips(125)
+        )));
+        assert!(actions.contains(&Action::SideBetWon(
+            0,
+            SideBet::TwentyOnePlusThree,
+            SideBetOutcome::Flush,
+            Chips(25)
+        )));
+        assert!(actions.contains(&Action::SideBetLost(0, SideBet::LuckyLadies, Chips(5))));
+        assert_eq!(game.context().seat().side_bets, Vector::new());
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(1135));
+        Ok(())
+    }
+
+    #[test]
+    fn lucky_ladies_settles_after_the_insurance_decisions() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let cards = cards(vector!(Rank::Queen, Rank::King, Rank::Queen, Rank::Ace));
+        let context = Context::new_with_rules(cards, side_bet_rules(HoleCardRule::Peek));
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = place_side_bet(&game, 0, SideBet::LuckyLadies, Chips(10))?;
+
+        let (game, actions) = deal(&game)?;
+        assert!(!actions
+            .iter()
+            .any(|action| matches!(action, Action::SideBetWon(..))));
+
+        let (game, actions) = decline_insurance(&game)?;
+        assert_eq!(
+            actions[0],
+            Action::SideBetWon(
+                0,
+                SideBet::LuckyLadies,
+                SideBetOutcome::QueenOfHeartsWithDealerBlackjack,
+                Chips(10000)
+            )
+        );
+        match game {
+            GameState::DealerWins(_) => Ok(()),
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn without_a_hole_card_lucky_ladies_waits_for_the_dealers_hand(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let play = |dealer_card| -> Result<Vector<Action>, Box<dyn std::error::Error>> {
+            let cards = cards(vector!(Rank::Queen, Rank::Ace, Rank::Queen, dealer_card));
+            let context = Context::new_with_rules(cards, side_bet_rules(HoleCardRule::NoHoleCard));
+            let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+            let (game, _) = place_side_bet(&game, 0, SideBet::LuckyLadies, Chips(10))?;
+
+            let (game, actions) = deal(&game)?;
+            assert!(!actions
+                .iter()
+                .any(|action| matches!(action, Action::SideBetWon(..) | Action::SideBetLost(..))));
+
+            Ok(stand(&game)?.1)
+        };
+
+        assert!(play(Rank::King)?.contains(&Action::SideBetWon(
+            0,
+            SideBet::LuckyLadies,
+            SideBetOutcome::QueenOfHeartsWithDealerBlackjack,
+            Chips(10000)
+        )));
+        assert!(play(Rank::Nine)?.contains(&Action::SideBetWon(
+            0,
+            SideBet::LuckyLadies,
+            SideBetOutcome::QueenOfHeartsPair,
+            Chips(2000)
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn without_a_hole_card_side_bets_settle_on_the_deal() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let cards = cards(vector!(Rank::Eight, Rank::Ace, Rank::Eight));
+        let context = Context::new_with_rules(cards, side_bet_rules(HoleCardRule::NoHoleCard));
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = place_side_bet(&game, 0, SideBet::PerfectPairs, Chips(5))?;
+
+        let (_, actions) = deal(&game)?;
+
+        assert!(actions.contains(&Action::SideBetWon(
+            0,
+            SideBet::PerfectPairs,
+            SideBetOutcome::PerfectPair,
+            Chips(125)
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn spanish_21_deals_from_shoes_without_tens() -> Result<(), Box<dyn std::error::Error>> {
+        let game = GameState::new_table(TableRules::spanish_21(), 1, 1)?;
+
+        let shoe = &game.context().shoe;
+        assert_eq!(shoe.cards.len(), 6 * 48);
+        assert!(shoe.cards.iter().all(|card| card.rank != Rank::Ten));
+        Ok(())
+    }
+
+    #[test]
+    fn a_five_card_21_pays_a_bonus() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Two,
+            Rank::King,
+            Rank::Three,
+            Rank::Seven,
+            Rank::Four,
+            Rank::Five,
+            Rank::Seven
+        ));
+        let context = Context::new_with_rules(cards, TableRules::spanish_21());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = hit(&game)?;
+        let (game, _) = hit(&game)?;
+
+        let (game, actions) = hit(&game)?;
+
+        let id = HandId::new(0, 0);
+        assert!(actions.contains(&Action::PlayerBonus(id, TwentyOneBonus::FiveCards)));
+        assert!(actions.contains(&Action::WagerWon(id, Chips(15))));
+        match game {
+            GameState::PlayerWins(_) => Ok(()),
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn a_player_21_beats_a_dealer_21() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Nine,
+            Rank::Nine,
+            Rank::Two,
+            Rank::Five,
+            Rank::King,
+            Rank::Seven
+        ));
+        let rules = TableRules {
+            player_21_always_wins: true,
+            ..TableRules::default()
+        };
+        let context = Context::new_with_rules(cards, rules);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = hit(&game)?;
+
+        assert_eq!(game.context().dealer_score(), BLACKJACK);
+        assert!(actions.contains(&Action::PlayerWins(HandId::new(0, 0))));
+        match game {
+            GameState::PlayerWins(_) => Ok(()),
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn a_player_blackjack_beats_a_dealer_blackjack() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ace, Rank::Ace, Rank::King, Rank::King));
+        let rules = TableRules {
+            player_21_always_wins: true,
+            ..TableRules::default()
+        };
+        let context = Context::new_with_rules(cards, rules);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+
+        let (game, actions) = deal(&game)?;
+
+        assert!(actions.contains(&Action::PlayerBlackjack(0)));
+        assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(15))));
+        match game {
+            GameState::PlayerWins(_) => Ok(()),
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    #[test]
+    fn a_doubled_hand_can_be_rescued_for_the_double() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Five,
+            Rank::King,
+            Rank::Six,
+            Rank::Seven,
+            Rank::Two
+        ));
+        let context = Context::new_with_rules(cards, TableRules::spanish_21());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, _) = double_down(&game)?;
+        assert!(game.can_surrender());
+        assert!(hit(&game).is_err());
+
+        let id = HandId::new(0, 0);
+        let (game, actions) = surrender(&game)?;
+        assert_eq!(actions[0], Action::DoubleDownRescued(id));
+        assert_eq!(actions[1], Action::WagerSurrendered(id, Chips(10)));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(990));
+        match game {
+            GameState::PlayerSurrenders(_) => Ok(()),
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
+        }
+    }
+
+    fn charlie_rules(charlie: CharlieRule) -> TableRules {
+        TableRules {
+            charlie,
+            ..TableRules::default()
+        }
+    }
+
+    #[test]
+    fn a_five_card_charlie_wins_without_the_dealer_playing(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
             Rank::Two,
+            Rank::King,
             Rank::Two,
+            Rank::Six,
             Rank::Two,
-            Rank::Ten,
+            Rank::Two,
+            Rank::Three,
             Rank::Nine
         ));
-        let context = Context::new_with_cards(typical_hand);
+        let context = Context::new_with_rules(cards, charlie_rules(CharlieRule::FiveCard));
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = hit(&game)?;
+        let (game, _) = hit(&game)?;
 
-        let game_state = GameState::Ready(context);
+        let (game, actions) = hit(&game)?;
 
-        if let (GameState::WaitingForPlayer(context), _) = deal(&game_state)? {
-            assert_eq!(
-                Deck::new_with_cards(cards(vector!(Rank::Nine))),
-                context.deck
-            );
-            Ok(())
-        } else {
-            Err(Box::new(TestError::IncorrectTransitionError))
+        let id = HandId::new(0, 0);
+        assert!(actions.contains(&Action::PlayerCharlie(id)));
+        assert!(actions.contains(&Action::WagerWon(id, Chips(10))));
+        match game {
+            GameState::PlayerWins(context) => {
+                assert_eq!(context.dealer_hand.cards().len(), 2);
+                Ok(())
+            }
+            _ => Err(Box::new(TestError::IncorrectTransitionError)),
         }
     }
 
     #[test]
-    fn dealer_has_blackjack_and_player_has_blackjack_leads_to_draw(
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let double_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::Ten, Rank::Ten));
-        let context = Context::new_with_cards(double_blackjack);
+    fn the_charlie_card_count_is_configurable() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Two,
+            Rank::King,
+            Rank::Two,
+            Rank::Six,
+            Rank::Two,
+            Rank::Two,
+            Rank::Three,
+            Rank::Nine
+        ));
+        let context = Context::new_with_rules(cards, charlie_rules(CharlieRule::SixCard));
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = hit(&game)?;
+        let (game, _) = hit(&game)?;
 
-        let (new_state, _) = deal(&GameState::Ready(context))?;
+        let (game, actions) = hit(&game)?;
 
-        match new_state {
-            GameState::Draw(_) => Ok(()),
+        assert!(!actions.contains(&Action::PlayerCharlie(HandId::new(0, 0))));
+        match game {
+            GameState::WaitingForPlayer(_) => Ok(()),
             _ => Err(Box::new(TestError::IncorrectTransitionError)),
         }
     }
 
     #[test]
-    fn dealer_has_blackjack_and_player_has_blackjack_returns_new_hand_and_draw_actions(
+    fn the_dealer_still_plays_for_the_seats_without_a_charlie(
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let double_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::Ten, Rank::Ten));
-        let context = Context::new_with_cards(double_blackjack.clone());
+        let cards = cards(vector!(
+            Rank::Two,
+            Rank::Ten,
+            Rank::King,
+            Rank::Two,
+            Rank::Eight,
+            Rank::Six,
+            Rank::Two,
+            Rank::Two,
+            Rank::Three,
+            Rank::Nine
+        ));
+        let context = Context::new(
+            Shoe::new_with_cards(cards),
+            charlie_rules(CharlieRule::FiveCard),
+            2,
+        );
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = bet(&game, 1, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = hit(&game)?;
+        let (game, _) = hit(&game)?;
+        let (game, actions) = hit(&game)?;
+        assert!(actions.contains(&Action::PlayerCharlie(HandId::new(0, 0))));
 
-        let (_, actions) = deal(&GameState::Ready(context))?;
-        assert_eq!(3, actions.len());
-        assert!(actions.contains(&Action::Draw));
-        assert!(actions.contains(&Action::ShowDealerHoleCard(double_blackjack[1])));
-        assert_actions_contains_new_hand(&actions, &double_blackjack)
+        let (game, _) = stand(&game)?;
+
+        assert_eq!(game.context().dealer_hand.cards().len(), 3);
+        assert_eq!(game.context().dealer_score(), Score(25));
+        Ok(())
     }
 
     #[test]
-    fn player_wins_with_blackjack() -> Result<(), Box<dyn std::error::Error>> {
-        let player_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::Ten, Rank::Ace));
-        let context = Context::new_with_cards(player_blackjack);
+    fn a_switch_seat_bets_on_two_hands_dealt_together() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Five,
+            Rank::Seven,
+            Rank::Six,
+            Rank::Ace,
+            Rank::Nine
+        ));
+        let context = Context::new_with_rules(cards.clone(), TableRules::blackjack_switch());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(980));
 
-        let (new_state, _) = deal(&GameState::Ready(context))?;
+        let (game, actions) = deal(&game)?;
 
-        match new_state {
+        let first = Hand::new().add(cards[0]).add(cards[3]);
+        let second = Hand::new().add(cards[1]).add(cards[4]);
+        match &actions[0] {
+            Action::NewHand(hands, _) => assert_eq!(hands, &vector![first, second]),
+            _ => return Err(Box::new(TestError::InvalidActionError)),
+        }
+        assert!(game.context().can_switch());
+        Ok(())
+    }
+
+    #[test]
+    fn switching_swaps_the_second_cards_and_a_switched_natural_pays_even(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Five,
+            Rank::Seven,
+            Rank::Six,
+            Rank::Ace,
+            Rank::Nine,
+            Rank::Ten
+        ));
+        let context = Context::new_with_rules(cards.clone(), TableRules::blackjack_switch());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = switch(&game)?;
+
+        assert_eq!(actions, vector![Action::Switch(0)]);
+        let seat = game.context().seat();
+        assert_eq!(
+            seat.player_hands[0].hand,
+            Hand::new().add(cards[0]).add(cards[4])
+        );
+        assert_eq!(
+            seat.player_hands[1].hand,
+            Hand::new().add(cards[1]).add(cards[3])
+        );
+        assert_eq!(seat.active_hand, 1);
+        assert!(!game.context().can_switch());
+
+        let (game, actions) = stand(&game)?;
+        assert!(actions.contains(&Action::PlayerBlackjack(0)));
+        assert!(actions.contains(&Action::WagerWon(HandId::new(0, 0), Chips(10))));
+        assert!(actions.contains(&Action::WagerWon(HandId::new(0, 1), Chips(10))));
+        match game {
             GameState::PlayerWins(_) => Ok(()),
             _ => Err(Box::new(TestError::IncorrectTransitionError)),
         }
     }
 
     #[test]
-    fn player_wins_with_blackjack_has_player_blackjack_action(
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let player_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::Ten, Rank::Ace));
-        let context = Context::new_with_cards(player_blackjack.clone());
+    fn a_switch_seat_insures_both_of_its_hands() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Nine,
+            Rank::Six,
+            Rank::Seven,
+            Rank::Eight,
+            Rank::Ace
+        ));
+        let context = Context::new_with_rules(cards, TableRules::blackjack_switch());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
 
-        let (_, actions) = deal(&GameState::Ready(context))?;
-        assert_eq!(3, actions.len());
-        assert!(actions.contains(&Action::PlayerBlackjack));
-        assert!(actions.contains(&Action::ShowDealerHoleCard(player_blackjack[1])));
-        assert_actions_contains_new_hand(&actions, &player_blackjack)
+        let (game, actions) = take_insurance(&game)?;
+
+        assert!(actions.contains(&Action::InsuranceLost(0, Chips(10))));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(970));
+        Ok(())
     }
 
     #[test]
-    fn player_hits_hand_and_gets_one_card() -> Result<(), Box<dyn std::error::Error>> {
+    fn original_bets_only_takes_both_switch_bets_on_a_dealer_natural(
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let cards = cards(vector!(
-            Rank::Ace,
-            Rank::Two,
-            Rank::Four,
             Rank::Ten,
-            Rank::Four
+            Rank::Nine,
+            Rank::Ace,
+            Rank::Seven,
+            Rank::Eight,
+            Rank::King
         ));
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
+        let rules = TableRules {
+            hole_card: HoleCardRule::OriginalBetsOnly,
+            ..TableRules::blackjack_switch()
+        };
+        let context = Context::new_with_rules(cards, rules);
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = stand(&game)?;
 
-        let (player_hits, actions) = hit(&game)?;
+        let (game, actions) = stand(&game)?;
 
-        match player_hits {
-            GameState::WaitingForPlayer(context) => {
-                assert_eq!(context.player_score(), Score(19));
-                assert_eq!(actions.len(), 1);
-                if let Action::NewPlayerCard(new_card) =
-                    actions.front().ok_or(TestError::InvalidActionError)?
-                {
-                    assert_eq!(&cards[4], new_card);
-                }
-                Ok(())
-            }
-            _ => Err(Box::new(TestError::IncorrectTransitionError)),
-        }
+        assert!(actions.contains(&Action::WagerLost(HandId::new(0, 0), Chips(10))));
+        assert!(actions.contains(&Action::WagerLost(HandId::new(0, 1), Chips(10))));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(980));
+        Ok(())
     }
 
     #[test]
-    fn player_hits_and_busts_transitions_to_player_lose() -> Result<(), Box<dyn std::error::Error>>
+    fn a_switch_is_only_allowed_before_the_first_decision() -> Result<(), Box<dyn std::error::Error>>
     {
         let cards = cards(vector!(
             Rank::Ten,
-            Rank::Ten,
+            Rank::Five,
+            Rank::Seven,
             Rank::Six,
-            Rank::Ten,
-            Rank::Eight
+            Rank::Four,
+            Rank::Nine
         ));
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
-
-        let (player_hits, actions) = hit(&game)?;
+        let context = Context::new_with_rules(cards, TableRules::blackjack_switch());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
 
-        match player_hits {
-            GameState::DealerWins(context) => {
-                assert_eq!(context.player_score(), Score(24));
-                assert_eq!(actions.len(), 3);
-                assert!(actions.contains(&Action::PlayerBusts));
-                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
-                let new_card_action = actions
-                    .iter()
-                    .find(|action| match action {
-                        Action::NewPlayerCard(_) => true,
-                        _ => false,
-                    })
-                    .ok_or(BlackjackError::NotFoundError)?;
-                if let Action::NewPlayerCard(card) = new_card_action {
-                    assert_eq!(&cards[4], card);
-                }
+        let (game, _) = stand(&game)?;
 
-                Ok(())
-            }
-            _ => Err(Box::new(TestError::IncorrectTransitionError)),
-        }
+        assert!(!game.context().can_switch());
+        assert!(switch(&game).is_err());
+        assert!(switch(&GameState::Ready(Context::new_with_cards(minimal_cards()))).is_err());
+        Ok(())
     }
 
     #[test]
-    fn player_hits_and_gets_blackjack_transitions_to_stand(
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    fn a_dealer_22_pushes_every_standing_hand() -> Result<(), Box<dyn std::error::Error>> {
         let cards = cards(vector!(
             Rank::Ten,
             Rank::Ten,
             Rank::Ten,
             Rank::Seven,
-            Rank::Ace
+            Rank::Eight,
+            Rank::Six,
+            Rank::Six
         ));
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
+        let context = Context::new_with_rules(cards, TableRules::blackjack_switch());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = stand(&game)?;
 
-        let (player_hits, actions) = hit(&game)?;
+        let (game, actions) = stand(&game)?;
 
-        match player_hits {
-            GameState::PlayerWins(context) => {
-                assert_eq!(context.player_score(), BLACKJACK);
-                assert_eq!(actions.len(), 3);
-                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
-                assert!(actions.contains(&Action::PlayerWins));
-                let new_card_action = actions
-                    .iter()
-                    .find(|action| match action {
-                        Action::NewPlayerCard(_) => true,
-                        _ => false,
-                    })
-                    .ok_or(BlackjackError::NotFoundError)?;
-                if let Action::NewPlayerCard(card) = new_card_action {
-                    assert_eq!(&cards[4], card);
-                }
-                Ok(())
-            }
+        assert_eq!(game.context().dealer_score(), Score(22));
+        assert!(actions.contains(&Action::DealerPushes22));
+        assert!(!actions.contains(&Action::DealerBusts));
+        assert!(actions.contains(&Action::WagerPushed(HandId::new(0, 0), Chips(10))));
+        assert!(actions.contains(&Action::WagerPushed(HandId::new(0, 1), Chips(10))));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(1000));
+        match game {
+            GameState::Draw(_) => Ok(()),
             _ => Err(Box::new(TestError::IncorrectTransitionError)),
         }
     }
 
     #[test]
-    fn player_stands_with_twenty_and_dealer_has_seventeen_player_wins(
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Ten, Rank::Seven));
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
+    fn double_exposure_deals_the_hole_card_face_up() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(
+            Rank::Ten,
+            Rank::Six,
+            Rank::Seven,
+            Rank::Ace,
+            Rank::Two
+        ));
+        let context = Context::new_with_rules(cards, TableRules::double_exposure());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
 
-        let (player_stands, actions) = stand(&game)?;
+        let (game, dealt) = deal(&game)?;
+        match &dealt[0] {
+            Action::NewHand(_, dealer_hand) => assert!(dealer_hand.is_exposed()),
+            _ => return Err(Box::new(TestError::InvalidActionError)),
+        }
+        assert!(!dealt.contains(&Action::InsuranceOffered));
 
-        match player_stands {
-            GameState::PlayerWins(context) => {
-                assert_eq!(context.player_score(), Score(20));
-                assert_eq!(context.dealer_score(), Score(17));
-                assert_eq!(actions.len(), 2);
-                assert!(actions.contains(&Action::PlayerWins));
-                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
+        let (game, played) = stand(&game)?;
+        assert!(!(dealt + played)
+            .iter()
+            .any(|action| matches!(action, Action::ShowDealerHoleCard(_))));
+        match game {
+            GameState::DealerWins(context) => {
+                assert_eq!(context.dealer_score(), Score(19));
                 Ok(())
             }
             _ => Err(Box::new(TestError::IncorrectTransitionError)),
@@ -659,113 +4451,100 @@ mod game_state_machine {
     }
 
     #[test]
-    fn player_stands_with_seventeen_and_dealer_has_twenty_dealer_wins(
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Seven, Rank::Ten));
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
+    fn double_exposure_ties_go_to_the_dealer() -> Result<(), Box<dyn std::error::Error>> {
+        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Eight, Rank::Eight));
+        let context = Context::new_with_rules(cards, TableRules::double_exposure());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
 
-        let (player_stands, actions) = stand(&game)?;
+        let (game, actions) = stand(&game)?;
 
-        match player_stands {
-            GameState::DealerWins(context) => {
-                assert_eq!(context.player_score(), Score(17));
-                assert_eq!(context.dealer_score(), Score(20));
-                assert_eq!(actions.len(), 2);
-                assert!(actions.contains(&Action::DealerWins));
-                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
-                Ok(())
-            }
+        let id = HandId::new(0, 0);
+        assert!(actions.contains(&Action::DealerWins(id)));
+        assert!(actions.contains(&Action::WagerLost(id, Chips(10))));
+        match game {
+            GameState::DealerWins(_) => Ok(()),
             _ => Err(Box::new(TestError::IncorrectTransitionError)),
         }
     }
 
     #[test]
-    fn player_stands_with_seventeen_and_dealer_hits_to_win(
+    fn double_exposure_naturals_pay_even_money_and_push_a_dealer_natural(
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let cards = cards(vector!(
-            Rank::Ten,
-            Rank::Ten,
-            Rank::Seven,
-            Rank::Six,
-            Rank::Three
-        ));
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
+        let id = HandId::new(0, 0);
+        let cards_against_nineteen = cards(vector!(Rank::Ace, Rank::Ten, Rank::King, Rank::Nine));
+        let context =
+            Context::new_with_rules(cards_against_nineteen, TableRules::double_exposure());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
 
-        let (player_stands, actions) = stand(&game)?;
+        let (_, actions) = deal(&game)?;
+        assert!(actions.contains(&Action::WagerWon(id, Chips(10))));
 
-        match player_stands {
-            GameState::DealerWins(context) => {
-                assert_eq!(context.player_score(), Score(17));
-                assert_eq!(context.dealer_score(), Score(19));
-                assert_eq!(actions.len(), 3);
-                assert!(actions.contains(&Action::DealerWins));
-                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
-                assert_new_dealer_cards_are(actions, vector![Rank::Three]);
-                Ok(())
-            }
+        let cards_against_blackjack = cards(vector!(Rank::Ace, Rank::Ace, Rank::King, Rank::King));
+        let context =
+            Context::new_with_rules(cards_against_blackjack, TableRules::double_exposure());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+
+        let (game, actions) = deal(&game)?;
+        assert!(actions.contains(&Action::WagerPushed(id, Chips(10))));
+        match game {
+            GameState::Draw(_) => Ok(()),
             _ => Err(Box::new(TestError::IncorrectTransitionError)),
         }
     }
 
     #[test]
-    fn player_stands_with_twenty_and_dealer_has_twenty_draw(
+    fn pontoon_deals_both_dealer_cards_face_down_and_a_dealer_pontoon_beats_everything(
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let cards = cards(vector!(Rank::Ten, Rank::Ten, Rank::Ten, Rank::Ten));
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
+        let cards = cards(vector!(Rank::Ace, Rank::Ten, Rank::King, Rank::Ace));
+        let context = Context::new_with_rules(cards, TableRules::pontoon());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
 
-        let (player_stands, actions) = stand(&game)?;
+        let (game, actions) = deal(&game)?;
 
-        match player_stands {
-            GameState::Draw(context) => {
-                assert_eq!(context.player_score(), Score(20));
-                assert_eq!(context.dealer_score(), Score(20));
-                assert_eq!(actions.len(), 2);
-                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
-                assert!(actions.contains(&Action::Draw));
-                Ok(())
-            }
+        match &actions[0] {
+            Action::NewHand(_, dealer_hand) => assert!(dealer_hand.visible_cards().is_empty()),
+            _ => return Err(Box::new(TestError::InvalidActionError)),
+        }
+        assert!(!actions.contains(&Action::InsuranceOffered));
+        assert!(actions.contains(&Action::ShowDealerUpcard(Card {
+            rank: Rank::Ace,
+            suit: Suit::Heart,
+        })));
+        assert!(actions.contains(&Action::WagerLost(HandId::new(0, 0), Chips(10))));
+        match game {
+            GameState::DealerWins(_) => Ok(()),
             _ => Err(Box::new(TestError::IncorrectTransitionError)),
         }
     }
 
-    fn assert_new_dealer_cards_are(actions: Vector<Action>, expected_cards: Vector<Rank>) {
-        let new_cards: Vector<Rank> = actions
-            .iter()
-            .filter_map(|action| match action {
-                Action::NewDealerCards(cards) => Some(cards.clone()),
-                _ => None,
-            })
-            .flatten()
-            .map(|card| card.rank)
-            .collect();
-
-        assert_eq!(new_cards, expected_cards);
-    }
-
     #[test]
-    fn dealer_hits_if_under_seventeen() -> Result<(), Box<dyn std::error::Error>> {
+    fn pontoon_five_card_tricks_beat_a_dealer_twenty_one() -> Result<(), Box<dyn std::error::Error>>
+    {
         let cards = cards(vector!(
+            Rank::Two,
             Rank::Ten,
-            Rank::Ten,
-            Rank::Ten,
+            Rank::Two,
             Rank::Six,
-            Rank::Ace
+            Rank::Three,
+            Rank::Three,
+            Rank::Four,
+            Rank::Five
         ));
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
+        let context = Context::new_with_rules(cards, TableRules::pontoon());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let (game, _) = hit(&game)?;
+        let (game, _) = hit(&game)?;
 
-        let (player_stands, actions) = stand(&game)?;
+        let (game, actions) = hit(&game)?;
 
-        match player_stands {
+        let id = HandId::new(0, 0);
+        assert!(actions.contains(&Action::PlayerFiveCardTrick(id)));
+        assert!(actions.contains(&Action::WagerWon(id, Chips(20))));
+        match game {
             GameState::PlayerWins(context) => {
-                assert_eq!(context.dealer_score(), Score(17));
-                assert_eq!(actions.len(), 3);
-                assert!(actions.contains(&Action::PlayerWins));
-                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
-                assert_new_dealer_cards_are(actions, vector![Rank::Ace]);
+                assert_eq!(context.dealer_score(), Score(21));
                 Ok(())
             }
             _ => Err(Box::new(TestError::IncorrectTransitionError)),
@@ -773,93 +4552,93 @@ mod game_state_machine {
     }
 
     #[test]
-    fn dealer_hits_until_they_get_to_seventeen() -> Result<(), Box<dyn std::error::Error>> {
-        let cards = cards(vector!(
-            Rank::Ten,
-            Rank::Ten,
-            Rank::Ten,
-            Rank::Two,
-            Rank::Ace,
-            Rank::Four
-        ));
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
+    fn pontoon_pays_double_and_gives_ties_to_the_dealer() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let id = HandId::new(0, 0);
+        let pontoon = cards(vector!(Rank::Ace, Rank::Ten, Rank::King, Rank::Nine));
+        let context = Context::new_with_rules(pontoon, TableRules::pontoon());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
 
-        let (player_stands, actions) = stand(&game)?;
+        let (_, actions) = deal(&game)?;
+        assert!(actions.contains(&Action::WagerWon(id, Chips(20))));
 
-        match player_stands {
-            GameState::PlayerWins(context) => {
-                assert_eq!(context.dealer_score(), Score(17));
-                assert_eq!(actions.len(), 3);
-                assert!(actions.contains(&Action::PlayerWins));
-                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
-                assert_new_dealer_cards_are(actions, vector![Rank::Ace, Rank::Four]);
-                Ok(())
-            }
+        let tie = cards(vector!(Rank::Ten, Rank::Ten, Rank::Eight, Rank::Eight));
+        let context = Context::new_with_rules(tie, TableRules::pontoon());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+
+        let (game, actions) = stand(&game)?;
+        assert!(actions.contains(&Action::DealerWins(id)));
+        match game {
+            GameState::DealerWins(_) => Ok(()),
             _ => Err(Box::new(TestError::IncorrectTransitionError)),
         }
     }
-
     #[test]
-    fn dealer_plays_their_hand_if_player_gets_blackjack_on_hit(
+    fn pontoon_buying_raises_the_stake_and_keeps_the_hand_open(
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let id = HandId::new(0, 0);
         let cards = cards(vector!(
-            Rank::Ten,
-            Rank::Ten,
-            Rank::Ten,
             Rank::Two,
-            Rank::Ace,
-            Rank::Nine
+            Rank::Ten,
+            Rank::Three,
+            Rank::Seven,
+            Rank::Four,
+            Rank::Five,
+            Rank::Six
         ));
+        let context = Context::new_with_rules(cards.clone(), TableRules::pontoon());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        assert!(!game.context().can_double_down());
+        assert!(game.context().can_buy());
 
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
-
-        let (player_hits, actions) = hit(&game)?;
+        let (game, actions) = buy(&game)?;
+        assert_eq!(
+            actions,
+            vector![
+                Action::CardBought(id, Chips(20)),
+                Action::NewPlayerCard(id, cards[4])
+            ]
+        );
+        let (game, _) = buy(&game)?;
+        assert_eq!(game.context().player_hand_at(id).wager, Chips(30));
+        assert_eq!(game.context().seat().bankroll.balance(), Chips(970));
+        assert!(game.context().can_hit());
 
-        match player_hits {
-            GameState::Draw(context) => {
-                assert_eq!(context.dealer_score(), BLACKJACK);
-                assert_eq!(context.player_score(), BLACKJACK);
-                assert_eq!(actions.len(), 4);
-                assert!(actions.contains(&Action::Draw));
-                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
-                assert!(actions.contains(&Action::NewPlayerCard(Card {
-                    rank: Rank::Ace,
-                    suit: Suit::Heart
-                })));
-                assert_new_dealer_cards_are(actions, vector![Rank::Nine]);
-                Ok(())
-            }
+        let (game, actions) = hit(&game)?;
+        assert!(actions.contains(&Action::PlayerFiveCardTrick(id)));
+        assert!(actions.contains(&Action::WagerWon(id, Chips(60))));
+        match game {
+            GameState::PlayerWins(_) => Ok(()),
             _ => Err(Box::new(TestError::IncorrectTransitionError)),
         }
     }
 
     #[test]
-    fn dealer_loses_if_they_bust() -> Result<(), Box<dyn std::error::Error>> {
+    fn cards_can_only_be_bought_at_pontoon_before_twisting(
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let cards = cards(vector!(
+            Rank::Two,
             Rank::Ten,
-            Rank::Ten,
-            Rank::Ten,
-            Rank::Six,
-            Rank::Six
+            Rank::Three,
+            Rank::Seven,
+            Rank::Four
         ));
-        let context = Context::new_with_cards(cards.clone());
-        let (game, _) = deal(&GameState::Ready(context))?;
+        let context = Context::new_with_rules(cards, TableRules::pontoon());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
 
-        let (dealer_busts, actions) = stand(&game)?;
+        let (game, _) = hit(&game)?;
 
-        match dealer_busts {
-            GameState::PlayerWins(context) => {
-                assert_eq!(context.dealer_score(), Score(22));
-                assert_eq!(context.player_score(), Score(20));
-                assert_eq!(actions.len(), 3);
-                assert!(actions.contains(&Action::ShowDealerHoleCard(cards[1])));
-                assert!(actions.contains(&Action::DealerBusts));
-                assert_new_dealer_cards_are(actions, vector![Rank::Six]);
-                Ok(())
-            }
-            _ => Err(Box::new(TestError::IncorrectTransitionError)),
-        }
+        assert!(!game.context().can_buy());
+        assert!(buy(&game).is_err());
+
+        let context = Context::new_with_cards(minimal_cards());
+        let (game, _) = bet(&GameState::Ready(context), 0, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        assert!(!game.context().can_buy());
+        assert!(buy(&game).is_err());
+        Ok(())
     }
 }
@@ -1,10 +1,21 @@
 use crate::deck::{Card, Rank};
 use im::{vector, Vector};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq, Debug, PartialOrd)]
+#[derive(Clone, Copy, PartialEq, Debug, PartialOrd)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct Score(pub u8);
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct HandTotal {
+    pub score: Score,
+    pub soft: bool,
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct Hand(Vector<Card>);
 
 impl Hand {
@@ -23,18 +34,26 @@ impl Hand {
     }
 
     pub fn score(&self) -> Score {
+        self.total().score
+    }
+
+    pub fn total(&self) -> HandTotal {
         let hard_value = self.0.iter().map(|card| card.rank.to_value().0).sum();
 
         let mut soft_value = hard_value;
-        for _ in 0..self.ace_count() {
-            if soft_value > 21 {
-                soft_value -= 10;
-                if soft_value <= 21 {
-                    break;
-                }
-            }
+        let mut aces_as_eleven = self.ace_count();
+        while soft_value > 21 && aces_as_eleven > 0 {
+            soft_value -= 10;
+            aces_as_eleven -= 1;
+        }
+        HandTotal {
+            score: Score(soft_value),
+            soft: aces_as_eleven > 0,
         }
-        Score(soft_value)
+    }
+
+    pub fn is_soft(&self) -> bool {
+        self.total().soft
     }
 
     fn ace_count(&self) -> usize {
@@ -46,14 +65,81 @@ impl Hand {
     }
 }
 
+impl Default for Hand {
+    fn default() -> Self {
+        Hand::new()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
 pub struct DealerHand {
     hand: Hand,
+    #[cfg_attr(feature = "serialization", serde(default = "dealt_a_hole_card"))]
+    hole_card: bool,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    exposed: bool,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    concealed: bool,
+}
+
+// Dealers always took a hole card before the no-hole-card rules existed
+#[cfg(feature = "serialization")]
+fn dealt_a_hole_card() -> bool {
+    true
 }
 
 impl DealerHand {
     pub fn new() -> Self {
-        DealerHand { hand: Hand::new() }
+        DealerHand {
+            hand: Hand::new(),
+            hole_card: true,
+            exposed: false,
+            concealed: false,
+        }
+    }
+
+    pub fn exposed() -> Self {
+        DealerHand {
+            exposed: true,
+            ..DealerHand::new()
+        }
+    }
+
+    pub fn concealed() -> Self {
+        DealerHand {
+            concealed: true,
+            ..DealerHand::new()
+        }
+    }
+
+    pub fn without_hole_card() -> Self {
+        DealerHand {
+            hole_card: false,
+            ..DealerHand::new()
+        }
+    }
+
+    pub fn has_hole_card(&self) -> bool {
+        self.hole_card
+    }
+
+    pub fn is_exposed(&self) -> bool {
+        self.exposed
+    }
+
+    pub fn is_concealed(&self) -> bool {
+        self.concealed
+    }
+
+    pub fn visible_cards(&self) -> Vector<Card> {
+        if self.exposed {
+            self.cards()
+        } else if self.concealed {
+            Vector::new()
+        } else {
+            self.upcard().cloned().into_iter().collect()
+        }
     }
 
     pub fn add(&self, card: Card) -> Self {
@@ -66,17 +152,43 @@ impl DealerHand {
         self.hand.score()
     }
 
+    pub fn total(&self) -> HandTotal {
+        self.hand.total()
+    }
+
+    pub fn is_soft(&self) -> bool {
+        self.hand.is_soft()
+    }
+
     pub fn hole_card(&self) -> Option<&Card> {
-        self.hand.0.front()
+        if self.hole_card {
+            self.hand.0.front()
+        } else {
+            None
+        }
     }
 
     pub fn upcard(&self) -> Option<&Card> {
-        self.hand.0.get(1)
+        if self.hole_card {
+            self.hand.0.get(1)
+        } else {
+            self.hand.0.front()
+        }
     }
 
     pub fn cards(&self) -> Vector<Card> {
         self.hand.cards()
     }
+
+    pub fn hand(&self) -> &Hand {
+        &self.hand
+    }
+}
+
+impl Default for DealerHand {
+    fn default() -> Self {
+        DealerHand::new()
+    }
 }
 
 #[cfg(test)]
@@ -208,6 +320,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn a_dealer_hand_without_a_hole_card_shows_its_first_card() {
+        let upcard = Card {
+            rank: Rank::Ace,
+            suit: Suit::Heart,
+        };
+        let dealer_hand = DealerHand::without_hole_card().add(upcard);
+
+        assert_eq!(dealer_hand.hole_card(), None);
+        assert_eq!(dealer_hand.upcard(), Some(&upcard));
+
+        let dealer_hand = dealer_hand.add(Card {
+            rank: Rank::King,
+            suit: Suit::Heart,
+        });
+
+        assert_eq!(dealer_hand.hole_card(), None);
+        assert_eq!(dealer_hand.upcard(), Some(&upcard));
+        assert_eq!(dealer_hand.score(), Score(21));
+    }
+
+    #[test]
+    fn an_exposed_dealer_hand_shows_both_cards() {
+        let hole_card = Card {
+            rank: Rank::Nine,
+            suit: Suit::Heart,
+        };
+        let upcard = Card {
+            rank: Rank::Three,
+            suit: Suit::Heart,
+        };
+        let dealer_hand = DealerHand::exposed().add(hole_card).add(upcard);
+
+        assert_eq!(dealer_hand.hole_card(), Some(&hole_card));
+        assert_eq!(dealer_hand.upcard(), Some(&upcard));
+        assert_eq!(dealer_hand.visible_cards(), vector![hole_card, upcard]);
+        assert_eq!(
+            DealerHand::new().add(hole_card).add(upcard).visible_cards(),
+            vector![upcard]
+        );
+    }
+
+    #[test]
+    fn a_concealed_dealer_hand_shows_neither_card() {
+        let dealer_hand = DealerHand::concealed()
+            .add(Card {
+                rank: Rank::Nine,
+                suit: Suit::Heart,
+            })
+            .add(Card {
+                rank: Rank::Three,
+                suit: Suit::Heart,
+            });
+
+        assert!(dealer_hand.is_concealed());
+        assert!(dealer_hand.visible_cards().is_empty());
+        assert_eq!(dealer_hand.score(), Score(12));
+    }
+
     #[test]
     fn a_dealer_hands_score_includes_its_invisible_card() {
         let dealer_hand = DealerHand::new()
@@ -252,4 +423,57 @@ mod tests {
 
         assert_eq!(hand.cards(), vector!(card_one, card_two));
     }
+
+    fn hand_of(ranks: Vec<Rank>) -> Hand {
+        ranks.into_iter().fold(Hand::new(), |hand, rank| {
+            hand.add(Card {
+                rank,
+                suit: Suit::Heart,
+            })
+        })
+    }
+
+    #[test]
+    fn ace_six_is_a_soft_seventeen() {
+        let total = hand_of(vec![Rank::Ace, Rank::Six]).total();
+
+        assert_eq!(
+            total,
+            HandTotal {
+                score: Score(17),
+                soft: true
+            }
+        );
+    }
+
+    #[test]
+    fn ace_ace_five_is_a_soft_seventeen() {
+        let total = hand_of(vec![Rank::Ace, Rank::Ace, Rank::Five]).total();
+
+        assert_eq!(
+            total,
+            HandTotal {
+                score: Score(17),
+                soft: true
+            }
+        );
+    }
+
+    #[test]
+    fn ace_six_ten_is_a_hard_seventeen() {
+        let total = hand_of(vec![Rank::Ace, Rank::Six, Rank::Ten]).total();
+
+        assert_eq!(
+            total,
+            HandTotal {
+                score: Score(17),
+                soft: false
+            }
+        );
+    }
+
+    #[test]
+    fn a_hand_without_aces_is_hard() {
+        assert!(!hand_of(vec![Rank::Ten, Rank::Seven]).is_soft());
+    }
 }
@@ -0,0 +1,568 @@
+use crate::bankroll::Chips;
+use crate::deck::{Card, Rank, Suit};
+use crate::game::{
+    bet, buy, deal, decline_insurance, double_down, hit, place_side_bet, split, stand, surrender,
+    switch, take_insurance, Action, BlackjackError, GameState, HandId,
+};
+use crate::hand::{DealerHand, Hand};
+use crate::rules::TableRules;
+use crate::side_bet::SideBet;
+use crate::strategy::Move;
+use im::Vector;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+type TransitionResult = Result<(GameState, Vector<Action>), Box<dyn std::error::Error>>;
+
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error("Replaying event {0} did not reproduce the recorded actions")]
+    DivergedError(usize),
+    #[error("The history has no event {0}")]
+    MissingEventError(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum Command {
+    Bet(usize, Chips),
+    SideBet(usize, SideBet, Chips),
+    Deal,
+    Hit,
+    Stand,
+    DoubleDown,
+    Buy,
+    Split,
+    Surrender,
+    Switch,
+    TakeInsurance,
+    DeclineInsurance,
+}
+
+impl Command {
+    pub fn apply(self, state: &GameState) -> TransitionResult {
+        match self {
+            Command::Bet(seat, wager) => bet(state, seat, wager),
+            Command::SideBet(seat, side_bet, wager) => place_side_bet(state, seat, side_bet, wager),
+            Command::Deal => deal(state),
+            Command::Hit => hit(state),
+            Command::Stand => stand(state),
+            Command::DoubleDown => double_down(state),
+            Command::Buy => buy(state),
+            Command::Split => split(state),
+            Command::Surrender => surrender(state),
+            Command::Switch => switch(state),
+            Command::TakeInsurance => take_insurance(state),
+            Command::DeclineInsurance => decline_insurance(state),
+        }
+    }
+
+    fn seat(self, state: &GameState) -> Option<usize> {
+        match self {
+            Command::Bet(seat, _) | Command::SideBet(seat, _, _) => Some(seat),
+            Command::Deal => None,
+            _ => Some(state.context().active_seat),
+        }
+    }
+
+    fn opens_hand(self) -> bool {
+        matches!(
+            self,
+            Command::Bet(_, _) | Command::SideBet(_, _, _) | Command::Deal
+        )
+    }
+}
+
+impl From<Move> for Command {
+    fn from(chosen: Move) -> Self {
+        match chosen {
+            Move::Hit => Command::Hit,
+            Move::Stand => Command::Stand,
+            Move::Double => Command::DoubleDown,
+            Move::Split => Command::Split,
+            Move::Surrender => Command::Surrender,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Event {
+    pub hand: u64,
+    pub seat: Option<usize>,
+    pub timestamp: SystemTime,
+    pub command: Command,
+    pub actions: Vector<Action>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct HandHistory {
+    rules: TableRules,
+    seats: usize,
+    seed: u64,
+    events: Vector<Event>,
+}
+
+impl HandHistory {
+    pub fn new(rules: TableRules, seats: usize, seed: u64) -> Self {
+        HandHistory {
+            rules,
+            seats,
+            seed,
+            events: Vector::new(),
+        }
+    }
+
+    pub fn initial_state(&self) -> Result<GameState, BlackjackError> {
+        GameState::new_table(self.rules.clone(), self.seats, self.seed)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn hands(&self) -> u64 {
+        self.events.last().map_or(0, |event| event.hand)
+    }
+
+    pub fn events(&self) -> &Vector<Event> {
+        &self.events
+    }
+
+    // Bets and the deal open the next hand, every other command plays the
+    // hand that was last dealt
+    pub fn record(&self, before: &GameState, command: Command, actions: &Vector<Action>) -> Self {
+        let hand = match self.events.last() {
+            None => 1,
+            Some(last)
+                if command.opens_hand()
+                    && (last.command == Command::Deal || !last.command.opens_hand()) =>
+            {
+                last.hand + 1
+            }
+            Some(last) => last.hand,
+        };
+        let mut events = self.events.clone();
+        events.push_back(Event {
+            hand,
+            seat: command.seat(before),
+            timestamp: SystemTime::now(),
+            command,
+            actions: actions.clone(),
+        });
+        HandHistory {
+            events,
+            ..self.clone()
+        }
+    }
+
+    pub fn replay(&self, events: usize) -> Result<GameState, Box<dyn std::error::Error>> {
+        (0..events.min(self.events.len())).try_fold(self.initial_state()?, |state, index| {
+            self.step(&state, index).map(|(next_state, _)| next_state)
+        })
+    }
+
+    pub fn step(&self, state: &GameState, index: usize) -> TransitionResult {
+        let event = self
+            .events
+            .get(index)
+            .ok_or(HistoryError::MissingEventError(index))?;
+        let (next_state, actions) = event.command.apply(state)?;
+        if actions != event.actions {
+            return Err(Box::new(HistoryError::DivergedError(index)));
+        }
+        Ok((next_state, actions))
+    }
+
+    pub fn hand_start(&self, index: usize) -> usize {
+        self.events
+            .get(index)
+            .map(|event| {
+                self.events
+                    .iter()
+                    .position(|other| other.hand == event.hand)
+                    .unwrap_or(index)
+            })
+            .unwrap_or_else(|| self.events.len())
+    }
+
+    pub fn replay_hand(&self, hand: u64) -> Result<GameState, Box<dyn std::error::Error>> {
+        self.replay(
+            self.events
+                .iter()
+                .take_while(|event| event.hand <= hand)
+                .count(),
+        )
+    }
+
+    fn describe(&self, action: &Action) -> Option<String> {
+        let line = match action {
+            Action::NewHand(hands, dealer_hand) => {
+                let hands_per_seat = self.rules.hands_per_seat();
+                let mut lines: Vec<String> = hands
+                    .iter()
+                    .enumerate()
+                    .map(|(index, hand)| {
+                        let id = HandId::new(index / hands_per_seat, index % hands_per_seat);
+                        format!("{}: dealt {}", hand_label(id), hand_text(hand))
+                    })
+                    .collect();
+                lines.push(format!("Dealer: dealt {}", dealer_text(dealer_hand)));
+                lines.join("\n  ")
+            }
+            Action::NewPlayerCard(id, card) => {
+                format!("{}: receives {}", hand_label(*id), card_text(card))
+            }
+            Action::NewDealerCards(cards) => format!("Dealer: draws {}", cards_text(cards)),
+            Action::PlayerWins(id) => format!("{}: beats the dealer", hand_label(*id)),
+            Action::PlayerBlackjack(seat) => format!("{}: blackjack", seat_label(*seat)),
+            Action::DealerWins(id) => format!("{}: loses to the dealer", hand_label(*id)),
+            Action::DealerBusts => "Dealer: busts".to_string(),
+            Action::PlayerBusts(id) => format!("{}: busts", hand_label(*id)),
+            Action::DealerBlackjack => "Dealer: blackjack".to_string(),
+            Action::Draw(id) => format!("{}: ties the dealer", hand_label(*id)),
+            Action::ShowDealerHoleCard(card) | Action::ShowDealerUpcard(card) => {
+                format!("Dealer: turns over {}", card_text(card))
+            }
+            Action::WagerPlaced(_, _) | Action::SideBetPlaced(_, _, _) => return None,
+            Action::WagerWon(id, chips) => format!("{}: wins {}", hand_label(*id), chips.0),
+            Action::WagerPushed(id, chips) => {
+                format!("{}: has {} returned", hand_label(*id), chips.0)
+            }
+            Action::WagerLost(id, chips) => format!("{}: loses {}", hand_label(*id), chips.0),
+            Action::DoubleDown(id, chips, card) => format!(
+                "{}: doubles to {} and receives {}",
+                hand_label(*id),
+                chips.0,
+                card_text(card)
+            ),
+            Action::CardBought(id, chips) => {
+                format!(
+                    "{}: buys a card for a stake of {}",
+                    hand_label(*id),
+                    chips.0
+                )
+            }
+            Action::Split(id) => format!("{}: is split", hand_label(*id)),
+            Action::Switch(seat) => format!("{}: switches the second cards", seat_label(*seat)),
+            Action::DealerPushes22 => "Dealer: makes 22 and pushes".to_string(),
+            Action::InsuranceOffered => "Dealer: offers insurance".to_string(),
+            Action::InsuranceWon(seat, chips) => {
+                format!("{}: insurance wins {}", seat_label(*seat), chips.0)
+            }
+            Action::InsuranceLost(seat, chips) => {
+                format!("{}: insurance loses {}", seat_label(*seat), chips.0)
+            }
+            Action::EvenMoney(seat) => format!("{}: takes even money", seat_label(*seat)),
+            Action::PlayerSurrenders(seat) => format!("{}: surrenders", seat_label(*seat)),
+            Action::WagerSurrendered(id, chips) => {
+                format!("{}: has {} returned", hand_label(*id), chips.0)
+            }
+            Action::PlayerBonus(id, bonus) => {
+                format!("{}: earns a {:?} bonus", hand_label(*id), bonus)
+            }
+            Action::PlayerCharlie(id) => format!("{}: makes a Charlie", hand_label(*id)),
+            Action::PlayerFiveCardTrick(id) => {
+                format!("{}: makes a five-card trick", hand_label(*id))
+            }
+            Action::DoubleDownRescued(id) => {
+                format!("{}: rescues the double down", hand_label(*id))
+            }
+            Action::SideBetWon(seat, side_bet, outcome, chips) => format!(
+                "{}: {} hits {} and wins {}",
+                seat_label(*seat),
+                side_bet,
+                outcome,
+                chips.0
+            ),
+            Action::SideBetLost(seat, side_bet, chips) => {
+                format!("{}: {} loses {}", seat_label(*seat), side_bet, chips.0)
+            }
+            Action::Reshuffle => "Dealer: reshuffles the shoe".to_string(),
+        };
+        Some(line)
+    }
+}
+
+impl Default for HandHistory {
+    fn default() -> Self {
+        HandHistory::new(TableRules::default(), 1, rand::random())
+    }
+}
+
+impl fmt::Display for HandHistory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut hand = 0;
+        for event in &self.events {
+            if event.hand != hand {
+                if hand != 0 {
+                    writeln!(f)?;
+                }
+                hand = event.hand;
+                writeln!(
+                    f,
+                    "Blackjack Hand #{} (seed {}) - {}",
+                    hand,
+                    self.seed,
+                    utc(event.timestamp)
+                )?;
+            }
+            let actor = event.seat.map_or("Dealer".to_string(), seat_label);
+            writeln!(f, "{}: {}", actor, command_text(event.command))?;
+            for line in event
+                .actions
+                .iter()
+                .filter_map(|action| self.describe(action))
+            {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn command_text(command: Command) -> String {
+    match command {
+        Command::Bet(_, chips) => format!("bets {}", chips.0),
+        Command::SideBet(_, side_bet, chips) => format!("bets {} on {}", chips.0, side_bet),
+        Command::Deal => "deals".to_string(),
+        Command::Hit => "hits".to_string(),
+        Command::Stand => "stands".to_string(),
+        Command::DoubleDown => "doubles down".to_string(),
+        Command::Buy => "buys a card".to_string(),
+        Command::Split => "splits".to_string(),
+        Command::Surrender => "surrenders".to_string(),
+        Command::Switch => "switches".to_string(),
+        Command::TakeInsurance => "takes insurance".to_string(),
+        Command::DeclineInsurance => "declines insurance".to_string(),
+    }
+}
+
+fn seat_label(seat: usize) -> String {
+    format!("Seat {}", seat + 1)
+}
+
+fn hand_label(id: HandId) -> String {
+    format!("Seat {} Hand {}", id.seat + 1, id.hand + 1)
+}
+
+fn card_text(card: &Card) -> String {
+    let rank = match card.rank {
+        Rank::Two => "2",
+        Rank::Three => "3",
+        Rank::Four => "4",
+        Rank::Five => "5",
+        Rank::Six => "6",
+        Rank::Seven => "7",
+        Rank::Eight => "8",
+        Rank::Nine => "9",
+        Rank::Ten => "T",
+        Rank::Jack => "J",
+        Rank::Queen => "Q",
+        Rank::King => "K",
+        Rank::Ace => "A",
+    };
+    let suit = match card.suit {
+        Suit::Heart => "h",
+        Suit::Diamond => "d",
+        Suit::Spade => "s",
+        Suit::Club => "c",
+    };
+    format!("{}{}", rank, suit)
+}
+
+fn cards_text(cards: &Vector<Card>) -> String {
+    let cards: Vec<String> = cards.iter().map(card_text).collect();
+    format!("[{}]", cards.join(" "))
+}
+
+fn hand_text(hand: &Hand) -> String {
+    cards_text(&hand.cards())
+}
+
+// The face down cards are always the first ones dealt to the dealer
+fn dealer_text(dealer_hand: &DealerHand) -> String {
+    let visible = dealer_hand.visible_cards();
+    let mut cards = vec!["??".to_string(); dealer_hand.cards().len() - visible.len()];
+    cards.extend(visible.iter().map(card_text));
+    format!("[{}]", cards.join(" "))
+}
+
+fn utc(timestamp: SystemTime) -> String {
+    let seconds = timestamp
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+
+    // Converts days since the epoch into a proleptic Gregorian date
+    let shifted = days + 719_468;
+    let era = shifted / 146_097;
+    let day_of_era = shifted % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Context;
+    use std::time::Duration;
+
+    fn play_hand(
+        history: HandHistory,
+        state: GameState,
+        wager: Chips,
+    ) -> Result<(HandHistory, GameState), Box<dyn std::error::Error>> {
+        let mut played = (history, state);
+        let mut commands = vec![Command::Bet(0, wager), Command::Deal].into_iter();
+        loop {
+            let (history, state) = played;
+            let command = match (&state, commands.next()) {
+                (_, Some(command)) => command,
+                (GameState::OfferingInsurance(_), None) => Command::DeclineInsurance,
+                (GameState::WaitingForPlayer(_), None) => Command::Stand,
+                _ => return Ok((history, state)),
+            };
+            let (next_state, actions) = command.apply(&state)?;
+            played = (history.record(&state, command, &actions), next_state);
+        }
+    }
+
+    fn two_hands() -> Result<(HandHistory, Context, GameState), Box<dyn std::error::Error>> {
+        let history = HandHistory::new(TableRules::default(), 1, 21);
+        let start = history.initial_state()?;
+        let (history, first) = play_hand(history, start, Chips(10))?;
+        let after_first = first.context().clone();
+        let (history, second) = play_hand(history, first, Chips(20))?;
+        Ok((history, after_first, second))
+    }
+
+    #[test]
+    fn replaying_every_event_rebuilds_the_final_state() -> Result<(), Box<dyn std::error::Error>> {
+        let (history, _, final_state) = two_hands()?;
+
+        assert_eq!(history.replay(history.events().len())?, final_state);
+        Ok(())
+    }
+
+    #[test]
+    fn replaying_a_hand_stops_after_its_last_event() -> Result<(), Box<dyn std::error::Error>> {
+        let (history, after_first, _) = two_hands()?;
+
+        assert_eq!(history.replay_hand(1)?.context(), &after_first);
+        assert_eq!(history.replay(0)?, history.initial_state()?);
+        Ok(())
+    }
+
+    #[test]
+    fn bets_and_the_deal_open_each_hand() -> Result<(), Box<dyn std::error::Error>> {
+        let (history, _, _) = two_hands()?;
+        let events = history.events();
+
+        assert_eq!(history.hands(), 2);
+        assert_eq!(events[0].command, Command::Bet(0, Chips(10)));
+        assert_eq!((events[0].hand, events[0].seat), (1, Some(0)));
+        assert_eq!((events[1].hand, events[1].seat), (1, None));
+        let second_bet = events
+            .iter()
+            .position(|event| event.command == Command::Bet(0, Chips(20)))
+            .unwrap();
+        assert!(events.iter().take(second_bet).all(|event| event.hand == 1));
+        assert!(events.iter().skip(second_bet).all(|event| event.hand == 2));
+        Ok(())
+    }
+
+    #[test]
+    fn stepping_through_events_follows_the_replay() -> Result<(), Box<dyn std::error::Error>> {
+        let (history, _, _) = two_hands()?;
+        let second_bet = history
+            .events()
+            .iter()
+            .position(|event| event.command == Command::Bet(0, Chips(20)))
+            .unwrap();
+
+        let (state, actions) = history.step(&history.replay(second_bet)?, second_bet)?;
+
+        assert_eq!(state, history.replay(second_bet + 1)?);
+        assert_eq!(actions, history.events()[second_bet].actions);
+        assert_eq!(history.hand_start(second_bet + 1), second_bet);
+        assert_eq!(history.hand_start(second_bet - 1), 0);
+        assert!(history.step(&state, history.events().len()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn a_replay_that_strays_from_the_record_is_rejected() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let (history, _, _) = two_hands()?;
+        let mut tampered = history.clone();
+        tampered.events[1].actions = Vector::new();
+
+        let result = tampered.replay(history.events().len());
+
+        assert!(matches!(
+            result
+                .err()
+                .and_then(|error| error.downcast::<HistoryError>().ok())
+                .as_deref(),
+            Some(HistoryError::DivergedError(1))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn the_text_history_reads_like_a_poker_hand_history() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let (history, _, _) = two_hands()?;
+
+        let text = history.to_string();
+
+        assert!(text.starts_with("Blackjack Hand #1 (seed 21) - "));
+        assert!(text.contains("\nSeat 1: bets 10\nDealer: deals\n  Seat 1 Hand 1: dealt ["));
+        assert!(text.contains("\n  Dealer: dealt [?? "));
+        assert!(text.contains("\n\nBlackjack Hand #2 (seed 21) - "));
+        assert!(text.contains("Seat 1: bets 20"));
+        Ok(())
+    }
+
+    #[test]
+    fn cards_and_timestamps_are_written_compactly() {
+        let card = Card {
+            rank: Rank::Ten,
+            suit: Suit::Diamond,
+        };
+
+        assert_eq!(card_text(&card), "Td");
+        assert_eq!(
+            utc(UNIX_EPOCH + Duration::from_secs(1_000_000_000)),
+            "2001-09-09 01:46:40 UTC"
+        );
+        assert_eq!(
+            utc(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29 00:00:00 UTC"
+        );
+    }
+}
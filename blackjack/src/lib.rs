@@ -1,3 +1,14 @@
+pub mod analysis;
+pub mod bankroll;
+pub mod counting;
 pub mod deck;
 pub mod game;
 pub mod hand;
+pub mod history;
+pub mod policy;
+pub mod rules;
+#[cfg(feature = "serialization")]
+pub mod save;
+pub mod side_bet;
+pub mod simulation;
+pub mod strategy;
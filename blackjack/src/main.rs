@@ -1,76 +1,640 @@
-use blackjack::game::{deal, hit, stand, Action, GameState};
+use blackjack::bankroll::Chips;
+use blackjack::counting::{CardCounter, CountingSystem};
+use blackjack::game::{Action, BlackjackError, Context, GameState, HandId};
+use blackjack::history::{Command, HandHistory};
+use blackjack::policy::{policy_named, BasicStrategy, PlayerPolicy, TableView, POLICY_NAMES};
+use blackjack::rules::TableRules;
+use blackjack::side_bet::SideBet;
+use blackjack::simulation::choose;
 use im::Vector;
+use std::env;
 use std::error::Error;
+use std::fs;
 use std::io;
+use strum::IntoEnumIterator;
+
+fn seed_from_args() -> u64 {
+    let args: Vec<String> = env::args().collect();
+    args.iter()
+        .position(|arg| arg == "--seed")
+        .and_then(|index| args.get(index + 1))
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(rand::random)
+}
+
+fn seats_from_args() -> usize {
+    let args: Vec<String> = env::args().collect();
+    args.iter()
+        .position(|arg| arg == "--seats")
+        .and_then(|index| args.get(index + 1))
+        .and_then(|seats| seats.parse().ok())
+        .unwrap_or(1)
+}
+
+fn history_path_from_args() -> Option<String> {
+    let args: Vec<String> = env::args().collect();
+    args.iter()
+        .position(|arg| arg == "--history")
+        .and_then(|index| args.get(index + 1))
+        .cloned()
+}
+
+#[cfg(feature = "serialization")]
+fn history_export(history: &HandHistory, path: &str) -> Result<String, Box<dyn Error>> {
+    if path.ends_with(".json") {
+        Ok(blackjack::save::history_to_json(history)?)
+    } else {
+        Ok(history.to_string())
+    }
+}
+
+#[cfg(not(feature = "serialization"))]
+fn history_export(history: &HandHistory, _path: &str) -> Result<String, Box<dyn Error>> {
+    Ok(history.to_string())
+}
+
+fn bot_from_args(seed: u64) -> Option<Box<dyn PlayerPolicy>> {
+    let args: Vec<String> = env::args().collect();
+    let index = args.iter().position(|arg| arg == "--bot")?;
+    let name = args.get(index + 1).map(String::as_str).unwrap_or("basic");
+    let bot = policy_named(name, seed);
+    if bot.is_none() {
+        println!(
+            "There is no {} bot. Choose one of: {}",
+            name,
+            POLICY_NAMES.join(", ")
+        );
+    }
+    bot
+}
+
+fn trainer_from_args() -> Option<CountingSystem> {
+    let args: Vec<String> = env::args().collect();
+    let index = args.iter().position(|arg| arg == "--trainer")?;
+    let system = match args.get(index + 1).map(|arg| arg.to_lowercase()) {
+        Some(ref name) if name == "ko" => CountingSystem::KnockOut,
+        Some(ref name) if name == "omega2" => CountingSystem::OmegaII,
+        Some(ref name) if name == "zen" => CountingSystem::Zen,
+        _ => CountingSystem::HiLo,
+    };
+    Some(system)
+}
+
+fn rules_from_args() -> TableRules {
+    let args: Vec<String> = env::args().collect();
+    let rules = if args.iter().any(|arg| arg == "--spanish21") {
+        println!("Playing Spanish 21: the tens are out and every 21 wins.");
+        TableRules::spanish_21()
+    } else if args.iter().any(|arg| arg == "--switch") {
+        println!("Playing Blackjack Switch: two hands each, and the dealer pushes on 22.");
+        TableRules::blackjack_switch()
+    } else if args.iter().any(|arg| arg == "--pontoon") {
+        println!("Playing Pontoon: the banker's cards are face down, and ties lose.");
+        println!("Buy cards to raise your stake until you first twist.");
+        TableRules::pontoon()
+    } else if args.iter().any(|arg| arg == "--double-exposure") {
+        println!("Playing Double Exposure: both dealer cards are face up, and ties lose.");
+        TableRules::double_exposure()
+    } else {
+        TableRules::default()
+    };
+    if args.iter().any(|arg| arg == "--side-bets") {
+        TableRules {
+            side_bets: SideBet::iter()
+                .map(|side_bet| (side_bet, side_bet.paytable()))
+                .collect(),
+            ..rules
+        }
+    } else {
+        rules
+    }
+}
+
+struct Vocabulary {
+    hit: &'static str,
+    stand: &'static str,
+    double_down: &'static str,
+}
+
+fn vocabulary(rules: &TableRules) -> Vocabulary {
+    if rules.pontoon {
+        Vocabulary {
+            hit: "Twist (T)",
+            stand: "Stick (S)",
+            double_down: "Buy (B)",
+        }
+    } else {
+        Vocabulary {
+            hit: "Hit (H)",
+            stand: "Stand (S)",
+            double_down: "Double Down (D)",
+        }
+    }
+}
+
+fn read_command() -> String {
+    let mut command = String::new();
+    io::stdin()
+        .read_line(&mut command)
+        .expect("Failed to read line");
+    command
+}
+
+fn seat_label(context: &Context, seat: usize) -> String {
+    if context.seats.len() > 1 {
+        format!("Seat {}: ", seat + 1)
+    } else {
+        String::new()
+    }
+}
+
+fn hand_label(context: &Context, id: HandId) -> String {
+    format!("{}Hand {}", seat_label(context, id.seat), id.hand + 1)
+}
+
+fn place_bets(
+    state: &GameState,
+    history: &HandHistory,
+    bots: bool,
+) -> Option<(GameState, HandHistory)> {
+    let mut betting = place_bet(state, history, 0)?;
+    for seat in 1..state.context().seats.len() {
+        betting = if bots {
+            println!(
+                "{}bets {} chips",
+                seat_label(state.context(), seat),
+                BOT_WAGER.0
+            );
+            let (state, history) = betting;
+            let command = Command::Bet(seat, BOT_WAGER);
+            let (next_state, actions) = command.apply(&state).ok()?;
+            (next_state, history.record(&state, command, &actions))
+        } else {
+            place_bet(&betting.0, &betting.1, seat)?
+        };
+    }
+    Some(betting)
+}
+
+fn place_bet(
+    state: &GameState,
+    history: &HandHistory,
+    seat: usize,
+) -> Option<(GameState, HandHistory)> {
+    println!(
+        "{}You have {} chips. How much would you like to bet?",
+        seat_label(state.context(), seat),
+        state.context().seats[seat].bankroll.balance().0
+    );
+
+    match read_command().trim().parse::<u32>() {
+        Ok(amount) => {
+            let command = Command::Bet(seat, Chips(amount));
+            match command.apply(state) {
+                Ok((next_state, actions)) => {
+                    place_side_bets(next_state, history.record(state, command, &actions), seat)
+                }
+                Err(error) => {
+                    println!("{}", error);
+                    None
+                }
+            }
+        }
+        Err(_) => {
+            println!("Please enter a number of chips");
+            None
+        }
+    }
+}
+
+fn place_side_bets(
+    state: GameState,
+    history: HandHistory,
+    seat: usize,
+) -> Option<(GameState, HandHistory)> {
+    let side_bets: Vec<SideBet> = state
+        .context()
+        .rules()
+        .side_bets
+        .iter()
+        .map(|(side_bet, _)| *side_bet)
+        .collect();
+    side_bets
+        .into_iter()
+        .try_fold((state, history), |(state, history), side_bet| {
+            println!(
+                "{}Side bet on {}? (0 to skip)",
+                seat_label(state.context(), seat),
+                side_bet
+            );
+            match read_command().trim().parse::<u32>() {
+                Ok(0) => Some((state, history)),
+                Ok(amount) => {
+                    let command = Command::SideBet(seat, side_bet, Chips(amount));
+                    match command.apply(&state) {
+                        Ok((next_state, actions)) => {
+                            Some((next_state, history.record(&state, command, &actions)))
+                        }
+                        Err(error) => {
+                            println!("{}", error);
+                            None
+                        }
+                    }
+                }
+                Err(_) => {
+                    println!("Please enter a number of chips");
+                    None
+                }
+            }
+        })
+}
+
+fn print_player_hands(context: &Context, seat: usize) {
+    let player_hands = &context.seats[seat].player_hands;
+    for (index, player_hand) in player_hands.iter().enumerate() {
+        print!("{}", seat_label(context, seat));
+        if player_hands.len() > 1 {
+            print!("Hand {}: ", index + 1);
+        }
+        print!("You have ");
+        for card in player_hand.hand.cards() {
+            print!("{:?} ", card.rank)
+        }
+        println!();
+        println!("For a total of {:?}", player_hand.score().0);
+    }
+}
+
+fn is_bot_turn(bot: &Option<Box<dyn PlayerPolicy>>, context: &Context) -> bool {
+    bot.is_some() && context.active_seat > 0
+}
+
+fn bot_turn(
+    bot: &mut dyn PlayerPolicy,
+    counter: &CardCounter,
+    state: &GameState,
+) -> Result<Command, Box<dyn Error>> {
+    let context = state.context();
+    let view = TableView::new(state, counter).ok_or(BlackjackError::InvalidStateError)?;
+    print_player_hands(context, context.active_seat);
+    let command = choose(bot, state, &view);
+    if let GameState::WaitingForPlayer(_) = state {
+        println!(
+            "{}plays {:?}",
+            seat_label(context, context.active_seat),
+            command
+        );
+    }
+    Ok(command)
+}
+
+fn recorded(
+    counter: &mut CardCounter,
+    history: &mut HandHistory,
+    state: &GameState,
+    command: Command,
+) -> Result<(GameState, Vector<Action>), Box<dyn Error>> {
+    let (next_state, actions) = command.apply(state)?;
+    *counter = counter.observe(&actions);
+    *history = history.record(state, command, &actions);
+    Ok((next_state, actions))
+}
+
+fn quiz_count(counter: &CardCounter, system: CountingSystem, context: &Context) {
+    let running_count = counter.running_count(system);
+    println!("What is the {} running count?", system);
+    match read_command().trim().parse::<i32>() {
+        Ok(answer) if answer == running_count => println!("Correct!"),
+        _ => println!("Not quite. The running count is {}", running_count),
+    }
+    println!(
+        "With {:.1} decks left the true count is {:.1}",
+        context.decks_remaining(),
+        counter.true_count(system, context.decks_remaining())
+    );
+}
+
+fn print_settlement(context: &Context, actions: &Vector<Action>) {
+    actions.iter().for_each(|action| match action {
+        Action::Reshuffle => println!("Reshuffling the shoe"),
+        Action::InsuranceWon(seat, chips) => println!(
+            "{}Insurance pays {} chips",
+            seat_label(context, *seat),
+            chips.0
+        ),
+        Action::InsuranceLost(seat, chips) => println!(
+            "{}Insurance loses {} chips",
+            seat_label(context, *seat),
+            chips.0
+        ),
+        Action::EvenMoney(seat) => println!("{}You took even money", seat_label(context, *seat)),
+        Action::WagerWon(id, chips) => {
+            println!("{} wins {} chips", hand_label(context, *id), chips.0)
+        }
+        Action::WagerPushed(id, chips) => println!(
+            "{} has its {} chips returned",
+            hand_label(context, *id),
+            chips.0
+        ),
+        Action::WagerLost(id, chips) => {
+            println!("{} loses {} chips", hand_label(context, *id), chips.0)
+        }
+        Action::WagerSurrendered(id, chips) => println!(
+            "{} surrenders, {} chips returned",
+            hand_label(context, *id),
+            chips.0
+        ),
+        Action::PlayerBonus(id, bonus) => {
+            println!("{} earns a {:?} bonus", hand_label(context, *id), bonus)
+        }
+        Action::CardBought(id, chips) => println!(
+            "{} buys a card, raising the stake to {} chips",
+            hand_label(context, *id),
+            chips.0
+        ),
+        Action::Switch(seat) => println!("{}Switched the second cards", seat_label(context, *seat)),
+        Action::DealerPushes22 => println!("Dealer makes 22 and pushes every standing hand"),
+        Action::PlayerCharlie(id) => {
+            println!("{} is a Charlie", hand_label(context, *id))
+        }
+        Action::PlayerFiveCardTrick(id) => {
+            println!("{} is a five-card trick", hand_label(context, *id))
+        }
+        Action::DoubleDownRescued(id) => {
+            println!("{} rescues its double down", hand_label(context, *id))
+        }
+        Action::SideBetWon(seat, side_bet, outcome, chips) => println!(
+            "{}{} hits a {} and wins {} chips",
+            seat_label(context, *seat),
+            side_bet,
+            outcome,
+            chips.0
+        ),
+        Action::SideBetLost(seat, side_bet, chips) => println!(
+            "{}{} loses {} chips",
+            seat_label(context, *seat),
+            side_bet,
+            chips.0
+        ),
+        _ => {}
+    });
+}
+
+const BOT_WAGER: Chips = Chips(10);
 
 fn main() -> Result<(), Box<dyn Error>> {
     println!("Welcome to Blackjack. You play me, the dummy dealer. I will deal.");
+    let seed = seed_from_args();
+    println!("Shuffling with seed {}", seed);
+    let trainer = trainer_from_args();
+    if let Some(system) = trainer {
+        println!("Keep the {} count. I'll quiz you after every hand.", system);
+    }
 
-    let mut state_and_actions = (GameState::new(), Vector::<Action>::new());
+    let mut bot = bot_from_args(seed);
+    let mut advisor = BasicStrategy::new();
+
+    let rules = rules_from_args();
+    let mut counter = CardCounter::new(rules.decks);
+    let mut history = HandHistory::new(rules, seats_from_args(), seed);
+    let mut state_and_actions = match history.initial_state() {
+        Ok(state) => (state, Vector::<Action>::new()),
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
 
     loop {
         match &state_and_actions {
-            (GameState::Ready(_), _) => state_and_actions = deal(&state_and_actions.0)?,
-            (GameState::WaitingForPlayer(context), _) => {
-                println!(
-                    "Dealer shows {:?}",
-                    context.dealer_hand.upcard().unwrap().rank
-                );
-                print!("You have ");
-                for card in context.player_hand.cards() {
-                    print!("{:?} ", card.rank)
-                }
-                println!("");
-                println!("For a total of {:?}", context.player_hand.score().0);
-                println!("Hit (H) or Stand (S)?");
-
-                let mut command = String::new();
-                io::stdin()
-                    .read_line(&mut command)
-                    .expect("Failed to read line");
-
-                match command.trim() {
-                    "H" | "h" => state_and_actions.0 = hit(&state_and_actions.0)?.0,
-                    "S" | "s" => state_and_actions.0 = stand(&state_and_actions.0)?.0,
+            (GameState::Ready(_), _) => {
+                if let Some((state, betting)) =
+                    place_bets(&state_and_actions.0, &history, bot.is_some())
+                {
+                    history = betting;
+                    state_and_actions =
+                        recorded(&mut counter, &mut history, &state, Command::Deal)?;
+                }
+            }
+            (GameState::OfferingInsurance(context), _)
+            | (GameState::WaitingForPlayer(context), _)
+                if is_bot_turn(&bot, context) =>
+            {
+                if let Some(bot) = bot.as_mut() {
+                    let command = bot_turn(bot.as_mut(), &counter, &state_and_actions.0)?;
+                    state_and_actions =
+                        recorded(&mut counter, &mut history, &state_and_actions.0, command)?;
+                }
+            }
+            (GameState::OfferingInsurance(context), _) => {
+                println!("Dealer shows Ace");
+                print_player_hands(context, context.active_seat);
+                if context.offers_even_money() {
+                    println!("Even money? (Y/N)");
+                } else if state_and_actions.0.can_surrender() {
+                    println!("Insurance? (Y/N) Or Surrender (R)?");
+                } else {
+                    println!("Insurance? (Y/N)");
+                }
+
+                match read_command().trim() {
+                    "R" | "r" => match recorded(
+                        &mut counter,
+                        &mut history,
+                        &state_and_actions.0,
+                        Command::Surrender,
+                    ) {
+                        Ok(result) => state_and_actions = result,
+                        Err(error) => println!("{}", error),
+                    },
+                    "Y" | "y" => match recorded(
+                        &mut counter,
+                        &mut history,
+                        &state_and_actions.0,
+                        Command::TakeInsurance,
+                    ) {
+                        Ok(result) => state_and_actions = result,
+                        Err(error) => println!("{}", error),
+                    },
+                    "N" | "n" => {
+                        state_and_actions = recorded(
+                            &mut counter,
+                            &mut history,
+                            &state_and_actions.0,
+                            Command::DeclineInsurance,
+                        )?
+                    }
+                    _ => {
+                        println!("Please try again");
+                    }
+                };
+            }
+            (GameState::WaitingForPlayer(context), actions) => {
+                print_settlement(context, actions);
+                if context.dealer_hand.is_concealed() {
+                    println!("Dealer's cards are face down");
+                } else if context.dealer_hand.is_exposed() {
+                    let dealer_cards: Vec<_> = context
+                        .dealer_hand
+                        .cards()
+                        .iter()
+                        .map(|card| card.rank)
+                        .collect();
+                    println!("Dealer shows {:?}", dealer_cards);
+                } else {
+                    println!(
+                        "Dealer shows {:?}",
+                        context.dealer_hand.upcard().unwrap().rank
+                    );
+                }
+                print_player_hands(context, context.active_seat);
+                if context.seat().player_hands.len() > 1 {
+                    println!(
+                        "{}Playing hand {}",
+                        seat_label(context, context.active_seat),
+                        context.seat().active_hand + 1
+                    );
+                }
+                if context.can_switch() {
+                    println!("Switch the second cards? (W)");
+                }
+                let words = vocabulary(context.rules());
+                if !context.can_hit() {
+                    println!("{} or Rescue (R)?", words.stand);
+                } else if state_and_actions.0.can_surrender() {
+                    println!(
+                        "{}, {}, {}, Split (P) or Surrender (R)?",
+                        words.hit, words.stand, words.double_down
+                    );
+                } else {
+                    println!(
+                        "{}, {}, {} or Split (P)?",
+                        words.hit, words.stand, words.double_down
+                    );
+                }
+                println!("(Type ? for a hint)");
+
+                match read_command().trim() {
+                    "?" => {
+                        if let Some(view) = TableView::new(&state_and_actions.0, &counter) {
+                            println!("Basic strategy says: {:?}", advisor.decide(&view));
+                        }
+                    }
+                    "H" | "h" | "T" | "t" if !context.can_hit() => {
+                        println!("This hand can't take another card")
+                    }
+                    "H" | "h" | "T" | "t" => match recorded(
+                        &mut counter,
+                        &mut history,
+                        &state_and_actions.0,
+                        Command::Hit,
+                    ) {
+                        Ok(result) => state_and_actions = result,
+                        Err(error) => println!("{}", error),
+                    },
+                    "S" | "s" => {
+                        state_and_actions = recorded(
+                            &mut counter,
+                            &mut history,
+                            &state_and_actions.0,
+                            Command::Stand,
+                        )?
+                    }
+                    "B" | "b" if context.rules().pontoon => match recorded(
+                        &mut counter,
+                        &mut history,
+                        &state_and_actions.0,
+                        Command::Buy,
+                    ) {
+                        Ok(result) => state_and_actions = result,
+                        Err(error) => println!("{}", error),
+                    },
+                    "D" | "d" => match recorded(
+                        &mut counter,
+                        &mut history,
+                        &state_and_actions.0,
+                        Command::DoubleDown,
+                    ) {
+                        Ok(result) => state_and_actions = result,
+                        Err(error) => println!("{}", error),
+                    },
+                    "P" | "p" => match recorded(
+                        &mut counter,
+                        &mut history,
+                        &state_and_actions.0,
+                        Command::Split,
+                    ) {
+                        Ok(result) => state_and_actions = result,
+                        Err(error) => println!("{}", error),
+                    },
+                    "R" | "r" => match recorded(
+                        &mut counter,
+                        &mut history,
+                        &state_and_actions.0,
+                        Command::Surrender,
+                    ) {
+                        Ok(result) => state_and_actions = result,
+                        Err(error) => println!("{}", error),
+                    },
+                    "W" | "w" => match recorded(
+                        &mut counter,
+                        &mut history,
+                        &state_and_actions.0,
+                        Command::Switch,
+                    ) {
+                        Ok(result) => state_and_actions = result,
+                        Err(error) => println!("{}", error),
+                    },
                     _ => {
                         println!("Please try again");
                     }
                 };
             }
-            (GameState::DealerWins(context), _)
-            | (GameState::PlayerWins(context), _)
-            | (GameState::Draw(context), _) => {
+            (GameState::DealerWins(context), actions)
+            | (GameState::PlayerWins(context), actions)
+            | (GameState::Draw(context), actions)
+            | (GameState::PlayerSurrenders(context), actions) => {
                 print!("Dealer has ");
                 for card in context.dealer_hand.cards() {
                     print!("{:?} ", card.rank);
                 }
-                println!("");
+                println!();
                 println!("For a total of {:?}", context.dealer_hand.score().0);
-                print!("You have ");
-                for card in context.player_hand.cards() {
-                    print!("{:?} ", card.rank)
+                for seat in 0..context.seats.len() {
+                    print_player_hands(context, seat);
                 }
-                println!("");
-                println!("For a total of {:?}", context.player_hand.score().0);
                 match state_and_actions.0 {
                     GameState::DealerWins(_) => println!("Dealer Wins!"),
                     GameState::PlayerWins(_) => println!("Player Wins!"),
                     GameState::Draw(_) => println!("Tie. Womp womp"),
+                    GameState::PlayerSurrenders(_) => println!("You surrendered"),
                     _ => panic!("Impossible state reached"),
                 }
+                print_settlement(context, actions);
+                if let Some(system) = trainer {
+                    quiz_count(&counter, system, context);
+                }
                 println!("Another hand?");
-                let mut command = String::new();
-                io::stdin()
-                    .read_line(&mut command)
-                    .expect("Failed to read line");
 
-                match command.trim() {
-                    "Y" | "y" => state_and_actions = deal(&state_and_actions.0)?,
+                match read_command().trim() {
+                    "Y" | "y" => {
+                        if let Some((state, betting)) =
+                            place_bets(&state_and_actions.0, &history, bot.is_some())
+                        {
+                            history = betting;
+                            state_and_actions =
+                                recorded(&mut counter, &mut history, &state, Command::Deal)?;
+                        }
+                    }
                     _ => break,
                 }
             }
         };
     }
 
+    if let Some(path) = history_path_from_args() {
+        fs::write(&path, history_export(&history, &path)?)?;
+        println!("Hand history written to {}", path);
+    }
     Ok(())
 }
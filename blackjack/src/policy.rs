@@ -0,0 +1,522 @@
+use crate::counting::{CardCounter, CountingSystem};
+use crate::deck::Card;
+use crate::game::GameState;
+use crate::hand::{Hand, Score};
+use crate::rules::{DealerStoppingRule, TableRules};
+use crate::strategy::{Advisor, Move};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+pub const POLICY_NAMES: [&str; 5] = ["basic", "never-bust", "mimic-dealer", "counter", "random"];
+
+#[derive(Clone, Debug)]
+pub struct TableView<'a> {
+    pub hand: Hand,
+    pub upcard: Option<Card>,
+    pub seen: &'a CardCounter,
+    pub decks_remaining: f64,
+    pub rules: &'a TableRules,
+    pub allowed: Vec<Move>,
+}
+
+impl<'a> TableView<'a> {
+    pub fn new(state: &'a GameState, seen: &'a CardCounter) -> Option<Self> {
+        let context = state.context();
+        let dealer_hand = &context.dealer_hand;
+        let upcard = *dealer_hand.upcard()?;
+        Some(TableView {
+            hand: context.player_hand(),
+            upcard: Some(upcard).filter(|_| !dealer_hand.is_concealed()),
+            seen,
+            decks_remaining: context.decks_remaining(),
+            rules: context.rules(),
+            allowed: allowed_moves(state),
+        })
+    }
+
+    pub fn true_count(&self, system: CountingSystem) -> f64 {
+        self.seen.true_count(system, self.decks_remaining)
+    }
+}
+
+pub trait PlayerPolicy {
+    fn decide(&mut self, view: &TableView) -> Move;
+
+    fn take_insurance(&mut self, _view: &TableView) -> bool {
+        false
+    }
+}
+
+impl<P: PlayerPolicy + ?Sized> PlayerPolicy for Box<P> {
+    fn decide(&mut self, view: &TableView) -> Move {
+        (**self).decide(view)
+    }
+
+    fn take_insurance(&mut self, view: &TableView) -> bool {
+        (**self).take_insurance(view)
+    }
+}
+
+pub fn policy_named(name: &str, seed: u64) -> Option<Box<dyn PlayerPolicy>> {
+    let policy: Box<dyn PlayerPolicy> = match name {
+        "basic" => Box::new(BasicStrategy::new()),
+        "never-bust" => Box::new(NeverBust),
+        "mimic-dealer" => Box::new(MimicDealer),
+        "counter" => Box::new(DeviationCounter::new()),
+        "random" => Box::new(RandomPlayer::new(seed)),
+        _ => return None,
+    };
+    Some(policy)
+}
+
+pub fn allowed_moves(state: &GameState) -> Vec<Move> {
+    let context = state.context();
+    let mut moves = if context.can_hit() {
+        vec![Move::Hit, Move::Stand]
+    } else {
+        vec![Move::Stand]
+    };
+    if context.can_double_down() {
+        moves.push(Move::Double);
+    }
+    if context.can_split() {
+        moves.push(Move::Split);
+    }
+    if state.can_surrender() {
+        moves.push(Move::Surrender);
+    }
+    moves
+}
+
+pub struct BasicStrategy {
+    advisor: Option<Advisor>,
+}
+
+impl BasicStrategy {
+    pub fn new() -> Self {
+        BasicStrategy { advisor: None }
+    }
+}
+
+impl Default for BasicStrategy {
+    fn default() -> Self {
+        BasicStrategy::new()
+    }
+}
+
+impl PlayerPolicy for BasicStrategy {
+    fn decide(&mut self, view: &TableView) -> Move {
+        let rules = view.rules;
+        let advisor = self.advisor.get_or_insert_with(|| Advisor::new(rules));
+        match &view.upcard {
+            Some(upcard) => advisor.advise(&view.hand, upcard, &view.allowed),
+            None => advisor.advise_blind(&view.hand, &view.allowed),
+        }
+    }
+}
+
+// Hits only while no single card can bust the hand, carrying soft hands up to
+// a soft 18.
+pub struct NeverBust;
+
+impl PlayerPolicy for NeverBust {
+    fn decide(&mut self, view: &TableView) -> Move {
+        let total = view.hand.total();
+        let safe = total.score <= Score(11) || (total.soft && total.score < Score(18));
+        if safe && view.allowed.contains(&Move::Hit) {
+            Move::Hit
+        } else {
+            Move::Stand
+        }
+    }
+}
+
+pub struct MimicDealer;
+
+impl PlayerPolicy for MimicDealer {
+    fn decide(&mut self, view: &TableView) -> Move {
+        if view.rules.dealer.hits(view.hand.total()) && view.allowed.contains(&Move::Hit) {
+            Move::Hit
+        } else {
+            Move::Stand
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DeviationHand {
+    Hard(u8),
+    Pair(u8),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Deviation {
+    pub hand: DeviationHand,
+    pub upcard: u8,
+    pub index: f64,
+    pub at_or_above: Move,
+    pub below: Move,
+}
+
+impl Deviation {
+    const fn hard(total: u8, upcard: u8, index: f64, at_or_above: Move, below: Move) -> Self {
+        Deviation {
+            hand: DeviationHand::Hard(total),
+            upcard,
+            index,
+            at_or_above,
+            below,
+        }
+    }
+
+    fn applies_to(&self, hand: &Hand, upcard: &Card) -> bool {
+        let cards = hand.cards();
+        let matches_hand = match self.hand {
+            DeviationHand::Hard(total) => !hand.is_soft() && hand.score() == Score(total),
+            DeviationHand::Pair(value) => {
+                cards.len() == 2 && cards.iter().all(|card| card.rank.to_value().0 == value)
+            }
+        };
+        matches_hand && upcard.rank.to_value().0 == self.upcard
+    }
+
+    fn play(&self, true_count: f64) -> Move {
+        if true_count >= self.index {
+            self.at_or_above
+        } else {
+            self.below
+        }
+    }
+}
+
+pub const INSURANCE_INDEX: f64 = 3.0;
+
+// The Illustrious 18 Hi-Lo index plays, less insurance which is decided by
+// INSURANCE_INDEX.
+pub const ILLUSTRIOUS_18: [Deviation; 17] = [
+    Deviation::hard(16, 10, 0.0, Move::Stand, Move::Hit),
+    Deviation::hard(15, 10, 4.0, Move::Stand, Move::Hit),
+    Deviation {
+        hand: DeviationHand::Pair(10),
+        upcard: 5,
+        index: 5.0,
+        at_or_above: Move::Split,
+        below: Move::Stand,
+    },
+    Deviation {
+        hand: DeviationHand::Pair(10),
+        upcard: 6,
+        index: 4.0,
+        at_or_above: Move::Split,
+        below: Move::Stand,
+    },
+    Deviation::hard(10, 10, 4.0, Move::Double, Move::Hit),
+    Deviation::hard(12, 3, 2.0, Move::Stand, Move::Hit),
+    Deviation::hard(12, 2, 3.0, Move::Stand, Move::Hit),
+    Deviation::hard(11, 11, 1.0, Move::Double, Move::Hit),
+    Deviation::hard(9, 2, 1.0, Move::Double, Move::Hit),
+    Deviation::hard(10, 11, 4.0, Move::Double, Move::Hit),
+    Deviation::hard(9, 7, 3.0, Move::Double, Move::Hit),
+    Deviation::hard(16, 9, 5.0, Move::Stand, Move::Hit),
+    Deviation::hard(13, 2, -1.0, Move::Stand, Move::Hit),
+    Deviation::hard(12, 4, 0.0, Move::Stand, Move::Hit),
+    Deviation::hard(12, 5, -2.0, Move::Stand, Move::Hit),
+    Deviation::hard(12, 6, -1.0, Move::Stand, Move::Hit),
+    Deviation::hard(13, 3, -2.0, Move::Stand, Move::Hit),
+];
+
+// Plays basic strategy, switching to an index play when the Hi-Lo true count
+// crosses it. A deviation only replaces one of the two plays it chooses
+// between, so basic strategy's splits and surrenders are left alone.
+pub struct DeviationCounter {
+    basic: BasicStrategy,
+    deviations: Vec<Deviation>,
+    insurance_index: f64,
+}
+
+impl DeviationCounter {
+    pub fn new() -> Self {
+        DeviationCounter::with_deviations(ILLUSTRIOUS_18.to_vec(), INSURANCE_INDEX)
+    }
+
+    pub fn with_deviations(deviations: Vec<Deviation>, insurance_index: f64) -> Self {
+        DeviationCounter {
+            basic: BasicStrategy::new(),
+            deviations,
+            insurance_index,
+        }
+    }
+}
+
+impl Default for DeviationCounter {
+    fn default() -> Self {
+        DeviationCounter::new()
+    }
+}
+
+impl PlayerPolicy for DeviationCounter {
+    fn decide(&mut self, view: &TableView) -> Move {
+        let basic = self.basic.decide(view);
+        let true_count = view.true_count(CountingSystem::HiLo);
+        self.deviations
+            .iter()
+            .find(|deviation| {
+                view.upcard
+                    .is_some_and(|upcard| deviation.applies_to(&view.hand, &upcard))
+            })
+            .filter(|deviation| basic == deviation.at_or_above || basic == deviation.below)
+            .map(|deviation| deviation.play(true_count))
+            .filter(|play| view.allowed.contains(play))
+            .unwrap_or(basic)
+    }
+
+    fn take_insurance(&mut self, view: &TableView) -> bool {
+        view.true_count(CountingSystem::HiLo) >= self.insurance_index
+    }
+}
+
+pub struct RandomPlayer {
+    rng: StdRng,
+}
+
+impl RandomPlayer {
+    pub fn new(seed: u64) -> Self {
+        RandomPlayer {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl PlayerPolicy for RandomPlayer {
+    fn decide(&mut self, view: &TableView) -> Move {
+        *view.allowed.choose(&mut self.rng).unwrap_or(&Move::Stand)
+    }
+
+    fn take_insurance(&mut self, _view: &TableView) -> bool {
+        self.rng.gen()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bankroll::Chips;
+    use crate::deck::{Rank, Suit};
+    use crate::game::{bet, deal, Action};
+    use crate::rules::DealerRule;
+    use im::{vector, Vector};
+
+    fn card(rank: Rank) -> Card {
+        Card {
+            rank,
+            suit: Suit::Spade,
+        }
+    }
+
+    fn hand(ranks: &[Rank]) -> Hand {
+        ranks
+            .iter()
+            .fold(Hand::new(), |hand, rank| hand.add(card(*rank)))
+    }
+
+    fn view<'a>(
+        hand: Hand,
+        upcard: Rank,
+        seen: &'a CardCounter,
+        rules: &'a TableRules,
+    ) -> TableView<'a> {
+        TableView {
+            hand,
+            upcard: Some(card(upcard)),
+            seen,
+            decks_remaining: 1.0,
+            rules,
+            allowed: vec![Move::Hit, Move::Stand, Move::Double, Move::Split],
+        }
+    }
+
+    fn counter_at(running_count: i32) -> CardCounter {
+        let rank = if running_count >= 0 {
+            Rank::Five
+        } else {
+            Rank::King
+        };
+        let cards: Vector<Card> = (0..running_count.abs()).map(|_| card(rank)).collect();
+        CardCounter::new(1).observe(&vector![Action::NewDealerCards(cards)])
+    }
+
+    #[test]
+    fn basic_strategy_only_picks_moves_the_table_allows() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mut policy = BasicStrategy::new();
+        let seen = CardCounter::new(1);
+
+        for seed in 0..200 {
+            let fresh = GameState::new(TableRules::default(), seed)?;
+            let state = deal(&bet(&fresh, 0, Chips(10))?.0)?.0;
+            if let GameState::WaitingForPlayer(_) = state {
+                let view = TableView::new(&state, &seen).unwrap();
+                assert!(view.allowed.contains(&policy.decide(&view)));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn a_concealed_deal_gives_no_upcard() -> Result<(), Box<dyn std::error::Error>> {
+        let mut policy = BasicStrategy::new();
+        let seen = CardCounter::new(1);
+
+        for seed in 0..20 {
+            let fresh = GameState::new(TableRules::pontoon(), seed)?;
+            let state = deal(&bet(&fresh, 0, Chips(10))?.0)?.0;
+            if let GameState::WaitingForPlayer(_) = state {
+                let view = TableView::new(&state, &seen).unwrap();
+                assert_eq!(view.upcard, None);
+                assert!(view.allowed.contains(&policy.decide(&view)));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn never_bust_stands_once_a_card_could_bust_the_hand() {
+        let rules = TableRules::default();
+        let seen = CardCounter::new(1);
+
+        let decide =
+            |ranks: &[Rank]| NeverBust.decide(&view(hand(ranks), Rank::Ten, &seen, &rules));
+
+        assert_eq!(decide(&[Rank::Five, Rank::Six]), Move::Hit);
+        assert_eq!(decide(&[Rank::Ten, Rank::Two]), Move::Stand);
+        assert_eq!(decide(&[Rank::Ace, Rank::Six]), Move::Hit);
+        assert_eq!(decide(&[Rank::Ace, Rank::Seven]), Move::Stand);
+    }
+
+    #[test]
+    fn the_simple_policies_stand_when_the_hand_cannot_take_a_card() {
+        let rules = TableRules::spanish_21();
+        let seen = CardCounter::new(1);
+        let held_for_rescue = TableView {
+            allowed: vec![Move::Stand, Move::Surrender],
+            ..view(
+                hand(&[Rank::Five, Rank::Four, Rank::Two]),
+                Rank::Ten,
+                &seen,
+                &rules,
+            )
+        };
+
+        assert_eq!(NeverBust.decide(&held_for_rescue), Move::Stand);
+        assert_eq!(MimicDealer.decide(&held_for_rescue), Move::Stand);
+    }
+
+    #[test]
+    fn mimic_dealer_follows_the_tables_soft_17_rule() {
+        let soft_17 = hand(&[Rank::Ace, Rank::Six]);
+        let seen = CardCounter::new(1);
+        let s17 = TableRules::default();
+        let h17 = TableRules {
+            dealer: DealerRule::HitsSoft17,
+            ..TableRules::default()
+        };
+
+        assert_eq!(
+            MimicDealer.decide(&view(soft_17.clone(), Rank::Ten, &seen, &s17)),
+            Move::Stand
+        );
+        assert_eq!(
+            MimicDealer.decide(&view(soft_17, Rank::Ten, &seen, &h17)),
+            Move::Hit
+        );
+        assert_eq!(
+            MimicDealer.decide(&view(hand(&[Rank::Ten, Rank::Six]), Rank::Two, &seen, &s17)),
+            Move::Hit
+        );
+    }
+
+    #[test]
+    fn the_counter_deviates_from_basic_strategy_at_its_index() {
+        let rules = TableRules::default();
+        let mut policy = DeviationCounter::new();
+        let sixteen = hand(&[Rank::Ten, Rank::Six]);
+        let neutral = CardCounter::new(1);
+        let positive = counter_at(2);
+        let negative = counter_at(-1);
+
+        assert_eq!(
+            policy.decide(&view(sixteen.clone(), Rank::Ten, &negative, &rules)),
+            Move::Hit
+        );
+        assert_eq!(
+            policy.decide(&view(sixteen, Rank::Ten, &positive, &rules)),
+            Move::Stand
+        );
+        assert_eq!(
+            policy.decide(&view(
+                hand(&[Rank::Ten, Rank::Two]),
+                Rank::Four,
+                &neutral,
+                &rules
+            )),
+            Move::Stand
+        );
+        assert_eq!(
+            policy.decide(&view(
+                hand(&[Rank::Ten, Rank::Two]),
+                Rank::Four,
+                &negative,
+                &rules
+            )),
+            Move::Hit
+        );
+        assert!(!policy.take_insurance(&view(
+            hand(&[Rank::Ten, Rank::Nine]),
+            Rank::Ace,
+            &positive,
+            &rules
+        )));
+        assert!(policy.take_insurance(&view(
+            hand(&[Rank::Ten, Rank::Nine]),
+            Rank::Ace,
+            &counter_at(3),
+            &rules
+        )));
+    }
+
+    #[test]
+    fn the_counter_keeps_basic_strategy_splits() {
+        let rules = TableRules::default();
+        let mut policy = DeviationCounter::new();
+        let eights = hand(&[Rank::Eight, Rank::Eight]);
+
+        assert_eq!(
+            policy.decide(&view(eights, Rank::Ten, &counter_at(4), &rules)),
+            Move::Split
+        );
+    }
+
+    #[test]
+    fn a_random_player_is_reproducible_and_stays_within_the_allowed_moves() {
+        let rules = TableRules::default();
+        let seen = CardCounter::new(1);
+        let table_view = view(hand(&[Rank::Eight, Rank::Eight]), Rank::Six, &seen, &rules);
+        let mut first = RandomPlayer::new(3);
+        let mut second = RandomPlayer::new(3);
+
+        for _ in 0..50 {
+            let chosen = first.decide(&table_view);
+            assert!(table_view.allowed.contains(&chosen));
+            assert_eq!(chosen, second.decide(&table_view));
+        }
+    }
+
+    #[test]
+    fn every_policy_name_resolves() {
+        for name in POLICY_NAMES.iter() {
+            assert!(policy_named(name, 1).is_some());
+        }
+        assert!(policy_named("psychic", 1).is_none());
+    }
+}
@@ -0,0 +1,495 @@
+use crate::deck::{DeckComposition, Rank, Suit, DEFAULT_DECKS, DEFAULT_PENETRATION};
+use crate::hand::{Hand, HandTotal, Score};
+use crate::side_bet::{Paytable, SideBet};
+use im::Vector;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub const DEFAULT_SPLIT_LIMIT: usize = 4;
+pub const FIVE_CARD_TRICK: usize = 5;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RulesError {
+    #[error("A table needs at least one deck, not {0}")]
+    DeckCountError(usize),
+    #[error("Penetration must be more than 0 and at most 1, not {0}")]
+    PenetrationError(f64),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum DealerRule {
+    StandsOnSoft17,
+    HitsSoft17,
+}
+
+pub trait DealerStoppingRule {
+    fn hits(&self, total: HandTotal) -> bool;
+}
+
+impl DealerStoppingRule for DealerRule {
+    fn hits(&self, total: HandTotal) -> bool {
+        match self {
+            DealerRule::StandsOnSoft17 => total.score < Score(17),
+            DealerRule::HitsSoft17 => {
+                total.score < Score(17) || (total.score == Score(17) && total.soft)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum BlackjackPayout {
+    ThreeToTwo,
+    SixToFive,
+    OneToOne,
+    TwoToOne,
+}
+
+impl BlackjackPayout {
+    pub fn ratio(self) -> (u32, u32) {
+        match self {
+            BlackjackPayout::ThreeToTwo => (3, 2),
+            BlackjackPayout::SixToFive => (6, 5),
+            BlackjackPayout::OneToOne => (1, 1),
+            BlackjackPayout::TwoToOne => (2, 1),
+        }
+    }
+}
+
+// Spanish 21 pays extra on a winning 21 made with five or more cards, or with
+// 6-7-8 or 7-7-7 graded by how many suits it shows.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum TwentyOneBonus {
+    FiveCards,
+    SixCards,
+    SevenCards,
+    MixedSevens,
+    SuitedSevens,
+    SpadedSevens,
+}
+
+impl TwentyOneBonus {
+    pub fn of(hand: &Hand) -> Option<Self> {
+        if hand.score() != Score(21) {
+            return None;
+        }
+        let cards = hand.cards();
+        let mut ranks: Vec<Rank> = cards.iter().map(|card| card.rank).collect();
+        ranks.sort_by_key(|rank| *rank as u8);
+        let sevens = ranks == vec![Rank::Six, Rank::Seven, Rank::Eight]
+            || ranks == vec![Rank::Seven, Rank::Seven, Rank::Seven];
+        match cards.len() {
+            3 if sevens && cards.iter().all(|card| card.suit == Suit::Spade) => {
+                Some(TwentyOneBonus::SpadedSevens)
+            }
+            3 if sevens && cards.iter().all(|card| card.suit == cards[0].suit) => {
+                Some(TwentyOneBonus::SuitedSevens)
+            }
+            3 if sevens => Some(TwentyOneBonus::MixedSevens),
+            5 => Some(TwentyOneBonus::FiveCards),
+            6 => Some(TwentyOneBonus::SixCards),
+            length if length >= 7 => Some(TwentyOneBonus::SevenCards),
+            _ => None,
+        }
+    }
+
+    pub fn ratio(self) -> (u32, u32) {
+        match self {
+            TwentyOneBonus::FiveCards | TwentyOneBonus::MixedSevens => (3, 2),
+            TwentyOneBonus::SixCards | TwentyOneBonus::SuitedSevens => (2, 1),
+            TwentyOneBonus::SevenCards | TwentyOneBonus::SpadedSevens => (3, 1),
+        }
+    }
+}
+
+// Pontoon ranks a two-card 21 above a five-card trick, and a five-card trick
+// above any other total
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum PontoonRank {
+    Bust,
+    Total(Score),
+    FiveCardTrick,
+    Pontoon,
+}
+
+impl PontoonRank {
+    pub fn of(hand: &Hand) -> Self {
+        let cards = hand.cards().len();
+        match hand.score() {
+            score if score > Score(21) => PontoonRank::Bust,
+            Score(21) if cards == 2 => PontoonRank::Pontoon,
+            _ if cards >= FIVE_CARD_TRICK => PontoonRank::FiveCardTrick,
+            score => PontoonRank::Total(score),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum DoubleRule {
+    AnyTwoCards,
+    NineToEleven,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum SurrenderRule {
+    NoSurrender,
+    Late,
+    Early,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum HoleCardRule {
+    #[default]
+    Peek,
+    NoHoleCard,
+    OriginalBetsOnly,
+    DoubleExposure,
+    BothFaceDown,
+}
+
+impl HoleCardRule {
+    pub fn dealer_has_hole_card(self) -> bool {
+        matches!(
+            self,
+            HoleCardRule::Peek | HoleCardRule::DoubleExposure | HoleCardRule::BothFaceDown
+        )
+    }
+
+    pub fn exposes_hole_card(self) -> bool {
+        self == HoleCardRule::DoubleExposure
+    }
+
+    pub fn conceals_upcard(self) -> bool {
+        self == HoleCardRule::BothFaceDown
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum CharlieRule {
+    #[default]
+    NoCharlie,
+    FiveCard,
+    SixCard,
+    SevenCard,
+}
+
+impl CharlieRule {
+    pub fn cards(self) -> Option<usize> {
+        match self {
+            CharlieRule::NoCharlie => None,
+            CharlieRule::FiveCard => Some(5),
+            CharlieRule::SixCard => Some(6),
+            CharlieRule::SevenCard => Some(7),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct TableRules {
+    pub dealer: DealerRule,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub deck: DeckComposition,
+    pub decks: usize,
+    pub penetration: f64,
+    pub blackjack_payout: BlackjackPayout,
+    pub double_after_split: bool,
+    pub double_rule: DoubleRule,
+    pub split_limit: usize,
+    pub hit_split_aces: bool,
+    pub surrender: SurrenderRule,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub hole_card: HoleCardRule,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub side_bets: Vector<(SideBet, Paytable)>,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub twenty_one_bonuses: bool,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub double_down_rescue: bool,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub player_21_always_wins: bool,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub charlie: CharlieRule,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub switch: bool,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub dealer_22_pushes: bool,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub dealer_wins_ties: bool,
+    #[cfg_attr(feature = "serialization", serde(default))]
+    pub pontoon: bool,
+}
+
+impl TableRules {
+    pub fn spanish_21() -> Self {
+        TableRules {
+            dealer: DealerRule::HitsSoft17,
+            deck: DeckComposition::Spanish,
+            decks: 6,
+            hit_split_aces: true,
+            twenty_one_bonuses: true,
+            double_down_rescue: true,
+            player_21_always_wins: true,
+            ..TableRules::default()
+        }
+    }
+
+    pub fn blackjack_switch() -> Self {
+        TableRules {
+            dealer: DealerRule::HitsSoft17,
+            decks: 6,
+            blackjack_payout: BlackjackPayout::OneToOne,
+            surrender: SurrenderRule::NoSurrender,
+            switch: true,
+            dealer_22_pushes: true,
+            ..TableRules::default()
+        }
+    }
+
+    pub fn double_exposure() -> Self {
+        TableRules {
+            dealer: DealerRule::HitsSoft17,
+            decks: 6,
+            blackjack_payout: BlackjackPayout::OneToOne,
+            surrender: SurrenderRule::NoSurrender,
+            hole_card: HoleCardRule::DoubleExposure,
+            dealer_wins_ties: true,
+            ..TableRules::default()
+        }
+    }
+
+    pub fn pontoon() -> Self {
+        TableRules {
+            dealer: DealerRule::HitsSoft17,
+            decks: 6,
+            blackjack_payout: BlackjackPayout::TwoToOne,
+            surrender: SurrenderRule::NoSurrender,
+            hole_card: HoleCardRule::BothFaceDown,
+            dealer_wins_ties: true,
+            pontoon: true,
+            ..TableRules::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), RulesError> {
+        if self.decks == 0 {
+            return Err(RulesError::DeckCountError(self.decks));
+        }
+        if !(self.penetration > 0.0 && self.penetration <= 1.0) {
+            return Err(RulesError::PenetrationError(self.penetration));
+        }
+        Ok(())
+    }
+
+    pub fn hands_per_seat(&self) -> usize {
+        if self.switch {
+            2
+        } else {
+            1
+        }
+    }
+
+    pub fn paytable(&self, side_bet: SideBet) -> Option<&Paytable> {
+        self.side_bets
+            .iter()
+            .find(|(offered, _)| *offered == side_bet)
+            .map(|(_, paytable)| paytable)
+    }
+}
+
+impl Default for TableRules {
+    fn default() -> Self {
+        TableRules {
+            dealer: DealerRule::StandsOnSoft17,
+            deck: DeckComposition::Standard,
+            decks: DEFAULT_DECKS,
+            penetration: DEFAULT_PENETRATION,
+            blackjack_payout: BlackjackPayout::ThreeToTwo,
+            double_after_split: true,
+            double_rule: DoubleRule::AnyTwoCards,
+            split_limit: DEFAULT_SPLIT_LIMIT,
+            hit_split_aces: false,
+            surrender: SurrenderRule::Late,
+            hole_card: HoleCardRule::Peek,
+            side_bets: Vector::new(),
+            twenty_one_bonuses: false,
+            double_down_rescue: false,
+            player_21_always_wins: false,
+            charlie: CharlieRule::NoCharlie,
+            switch: false,
+            dealer_22_pushes: false,
+            dealer_wins_ties: false,
+            pontoon: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck::{Card, Rank, Suit};
+    use crate::hand::Hand;
+
+    fn total_of(ranks: Vec<Rank>) -> HandTotal {
+        ranks
+            .into_iter()
+            .fold(Hand::new(), |hand, rank| {
+                hand.add(Card {
+                    rank,
+                    suit: Suit::Heart,
+                })
+            })
+            .total()
+    }
+
+    #[test]
+    fn s17_dealer_stands_on_every_seventeen() {
+        let rule = DealerRule::StandsOnSoft17;
+
+        assert!(!rule.hits(total_of(vec![Rank::Ace, Rank::Six])));
+        assert!(!rule.hits(total_of(vec![Rank::Ace, Rank::Ace, Rank::Five])));
+        assert!(!rule.hits(total_of(vec![Rank::Ace, Rank::Six, Rank::Ten])));
+    }
+
+    #[test]
+    fn h17_dealer_hits_only_the_soft_seventeens() {
+        let rule = DealerRule::HitsSoft17;
+
+        assert!(rule.hits(total_of(vec![Rank::Ace, Rank::Six])));
+        assert!(rule.hits(total_of(vec![Rank::Ace, Rank::Ace, Rank::Five])));
+        assert!(!rule.hits(total_of(vec![Rank::Ace, Rank::Six, Rank::Ten])));
+    }
+
+    #[test]
+    fn every_dealer_hits_sixteen_and_stands_on_eighteen() {
+        for rule in [DealerRule::StandsOnSoft17, DealerRule::HitsSoft17].iter() {
+            assert!(rule.hits(total_of(vec![Rank::Ten, Rank::Six])));
+            assert!(!rule.hits(total_of(vec![Rank::Ace, Rank::Seven])));
+        }
+    }
+
+    fn hand_of(cards: Vec<(Rank, Suit)>) -> Hand {
+        cards.into_iter().fold(Hand::new(), |hand, (rank, suit)| {
+            hand.add(Card { rank, suit })
+        })
+    }
+
+    fn total_hand(ranks: Vec<Rank>) -> Hand {
+        hand_of(ranks.into_iter().map(|rank| (rank, Suit::Heart)).collect())
+    }
+
+    #[test]
+    fn long_twenty_ones_earn_a_bonus_by_card_count() {
+        let five = hand_of(vec![
+            (Rank::Two, Suit::Heart),
+            (Rank::Three, Suit::Club),
+            (Rank::Four, Suit::Club),
+            (Rank::Five, Suit::Heart),
+            (Rank::Seven, Suit::Spade),
+        ]);
+        let six = five.add(Card {
+            rank: Rank::Ace,
+            suit: Suit::Heart,
+        });
+
+        assert_eq!(TwentyOneBonus::of(&five), Some(TwentyOneBonus::FiveCards));
+        assert_eq!(
+            TwentyOneBonus::of(&hand_of(vec![
+                (Rank::Two, Suit::Heart),
+                (Rank::Three, Suit::Club),
+                (Rank::Four, Suit::Club),
+                (Rank::Five, Suit::Heart),
+                (Rank::Six, Suit::Spade),
+                (Rank::Ace, Suit::Heart),
+            ])),
+            Some(TwentyOneBonus::SixCards)
+        );
+        assert_eq!(TwentyOneBonus::of(&six), None);
+        assert_eq!(
+            TwentyOneBonus::of(&total_hand(vec![Rank::King, Rank::Ace])),
+            None
+        );
+    }
+
+    #[test]
+    fn six_seven_eight_and_triple_sevens_are_graded_by_suit() {
+        assert_eq!(
+            TwentyOneBonus::of(&hand_of(vec![
+                (Rank::Eight, Suit::Heart),
+                (Rank::Six, Suit::Club),
+                (Rank::Seven, Suit::Club),
+            ])),
+            Some(TwentyOneBonus::MixedSevens)
+        );
+        assert_eq!(
+            TwentyOneBonus::of(&total_hand(vec![Rank::Seven, Rank::Seven, Rank::Seven])),
+            Some(TwentyOneBonus::SuitedSevens)
+        );
+        assert_eq!(
+            TwentyOneBonus::of(&hand_of(vec![
+                (Rank::Six, Suit::Spade),
+                (Rank::Seven, Suit::Spade),
+                (Rank::Eight, Suit::Spade),
+            ])),
+            Some(TwentyOneBonus::SpadedSevens)
+        );
+        assert_eq!(
+            TwentyOneBonus::of(&total_hand(vec![Rank::Five, Rank::Seven, Rank::Nine])),
+            None
+        );
+    }
+
+    #[test]
+    fn pontoon_ranks_a_pontoon_over_a_five_card_trick_over_twenty_one() {
+        let pontoon = PontoonRank::of(&total_hand(vec![Rank::Ace, Rank::King]));
+        let five_card_trick = PontoonRank::of(&total_hand(vec![
+            Rank::Two,
+            Rank::Two,
+            Rank::Three,
+            Rank::Three,
+            Rank::Four,
+        ]));
+        let twenty_one = PontoonRank::of(&total_hand(vec![Rank::Seven, Rank::Seven, Rank::Seven]));
+        let twenty = PontoonRank::of(&total_hand(vec![Rank::King, Rank::Queen]));
+        let bust = PontoonRank::of(&total_hand(vec![Rank::King, Rank::Queen, Rank::Two]));
+
+        assert_eq!(five_card_trick, PontoonRank::FiveCardTrick);
+        assert!(pontoon > five_card_trick);
+        assert!(five_card_trick > twenty_one);
+        assert!(twenty_one > twenty);
+        assert!(twenty > bust);
+    }
+    #[test]
+    fn every_preset_is_valid_but_an_empty_shoe_is_not() {
+        for rules in &[
+            TableRules::default(),
+            TableRules::spanish_21(),
+            TableRules::blackjack_switch(),
+            TableRules::double_exposure(),
+            TableRules::pontoon(),
+        ] {
+            assert_eq!(rules.validate(), Ok(()));
+        }
+        let no_decks = TableRules {
+            decks: 0,
+            ..TableRules::default()
+        };
+        assert_eq!(no_decks.validate(), Err(RulesError::DeckCountError(0)));
+        let dealt_out = TableRules {
+            penetration: 1.25,
+            ..TableRules::default()
+        };
+        assert_eq!(
+            dealt_out.validate(),
+            Err(RulesError::PenetrationError(1.25))
+        );
+    }
+}
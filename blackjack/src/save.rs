@@ -0,0 +1,233 @@
+use crate::game::GameState;
+use crate::history::HandHistory;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+pub const SAVE_VERSION: u32 = 9;
+
+const SEAT_FIELDS: [&str; 5] = [
+    "player_hands",
+    "active_hand",
+    "bankroll",
+    "wager",
+    "insurance",
+];
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Saved game version {0} is not supported")]
+    UnsupportedVersion(u32),
+    #[error("Saved game is not valid JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+}
+
+#[derive(Serialize)]
+struct SavingGame<'a> {
+    version: u32,
+    state: &'a GameState,
+}
+
+#[derive(Deserialize)]
+struct SavedGame {
+    state: GameState,
+}
+
+#[derive(Serialize)]
+struct SavingHistory<'a> {
+    version: u32,
+    history: &'a HandHistory,
+}
+
+#[derive(Deserialize)]
+struct SavedHistory {
+    history: HandHistory,
+}
+
+#[derive(Deserialize)]
+struct SavedVersion {
+    version: u32,
+}
+
+pub fn to_json(state: &GameState) -> Result<String, SaveError> {
+    let saving_game = SavingGame {
+        version: SAVE_VERSION,
+        state,
+    };
+    Ok(serde_json::to_string(&saving_game)?)
+}
+
+pub fn from_json(json: &str) -> Result<GameState, SaveError> {
+    let version = check_version(json)?;
+    let saved = migrate(version, serde_json::from_str(json)?);
+    let SavedGame { state } = serde_json::from_value(saved)?;
+    Ok(state)
+}
+
+pub fn history_to_json(history: &HandHistory) -> Result<String, SaveError> {
+    let saving_history = SavingHistory {
+        version: SAVE_VERSION,
+        history,
+    };
+    Ok(serde_json::to_string(&saving_history)?)
+}
+
+pub fn history_from_json(json: &str) -> Result<HandHistory, SaveError> {
+    check_version(json)?;
+    let SavedHistory { history } = serde_json::from_str(json)?;
+    Ok(history)
+}
+
+fn check_version(json: &str) -> Result<u32, SaveError> {
+    let SavedVersion { version } = serde_json::from_str(json)?;
+    if version == 0 || version > SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+    Ok(version)
+}
+
+// Version 1 kept its only player's hands and chips on the context. Every
+// later version just adds fields, which take their defaults when missing.
+fn migrate(version: u32, mut saved: Value) -> Value {
+    if version < 2 {
+        let context = saved["state"]
+            .as_object_mut()
+            .and_then(|state| state.values_mut().next())
+            .and_then(Value::as_object_mut);
+        if let Some(context) = context {
+            let seat: Map<String, Value> = SEAT_FIELDS
+                .iter()
+                .filter_map(|field| Some((field.to_string(), context.remove(*field)?)))
+                .collect();
+            context.insert("seats".to_string(), Value::Array(vec![Value::Object(seat)]));
+            context.insert("active_seat".to_string(), Value::from(0));
+        }
+    }
+    saved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bankroll::Chips;
+    use crate::game::{bet, deal, stand, Action, HandId};
+    use crate::history::{Command, HandHistory};
+    use crate::rules::TableRules;
+
+    fn mid_hand() -> Result<GameState, Box<dyn std::error::Error>> {
+        let (game, _) = bet(&GameState::new(TableRules::default(), 12)?, 0, Chips(25))?;
+        let (game, _) = deal(&game)?;
+        Ok(game)
+    }
+
+    #[test]
+    fn every_state_round_trips() -> Result<(), Box<dyn std::error::Error>> {
+        let context = mid_hand()?.context().clone();
+        let states = vec![
+            GameState::Ready(context.clone()),
+            GameState::OfferingInsurance(context.clone()),
+            GameState::WaitingForPlayer(context.clone()),
+            GameState::DealerWins(context.clone()),
+            GameState::PlayerWins(context.clone()),
+            GameState::Draw(context.clone()),
+            GameState::PlayerSurrenders(context),
+        ];
+
+        for state in states {
+            assert_eq!(from_json(&to_json(&state)?)?, state);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn a_resumed_game_plays_on_like_the_original() -> Result<(), Box<dyn std::error::Error>> {
+        let game = mid_hand()?;
+        let resumed = from_json(&to_json(&game)?)?;
+
+        if let GameState::WaitingForPlayer(_) = game {
+            assert_eq!(stand(&resumed)?, stand(&game)?);
+        }
+        assert_eq!(deal(&stand(&resumed)?.0)?, deal(&stand(&game)?.0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn the_json_carries_its_version() -> Result<(), Box<dyn std::error::Error>> {
+        let json = to_json(&mid_hand()?)?;
+
+        let value: serde_json::Value = serde_json::from_str(&json)?;
+
+        assert_eq!(value["version"], SAVE_VERSION);
+        Ok(())
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let json = to_json(&mid_hand()?)?.replacen(
+            &format!("\"version\":{}", SAVE_VERSION),
+            "\"version\":99",
+            1,
+        );
+
+        let result = from_json(&json);
+
+        assert!(matches!(result, Err(SaveError::UnsupportedVersion(99))));
+        Ok(())
+    }
+
+    #[test]
+    fn a_version_1_save_still_loads() -> Result<(), Box<dyn std::error::Error>> {
+        let saved = from_json(include_str!("../fixtures/save_v1.json"))?;
+
+        assert_eq!(saved, mid_hand()?);
+        Ok(())
+    }
+
+    #[test]
+    fn actions_round_trip() -> Result<(), serde_json::Error> {
+        let action = Action::WagerWon(HandId::new(2, 1), Chips(30));
+
+        let json = serde_json::to_string(&action)?;
+
+        assert_eq!(serde_json::from_str::<Action>(&json)?, action);
+        Ok(())
+    }
+
+    #[test]
+    fn hand_histories_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let history = HandHistory::new(TableRules::default(), 1, 12);
+        let start = history.initial_state()?;
+        let (game, actions) = Command::Bet(0, Chips(25)).apply(&start)?;
+        let history = history.record(&start, Command::Bet(0, Chips(25)), &actions);
+        let (_, actions) = Command::Deal.apply(&game)?;
+        let history = history.record(&game, Command::Deal, &actions);
+
+        let json = serde_json::to_string(&history)?;
+
+        assert_eq!(serde_json::from_str::<HandHistory>(&json)?, history);
+        Ok(())
+    }
+
+    #[test]
+    fn saved_histories_replay_like_the_original() -> Result<(), Box<dyn std::error::Error>> {
+        let history = HandHistory::new(TableRules::default(), 1, 12);
+        let start = history.initial_state()?;
+        let (game, actions) = Command::Bet(0, Chips(25)).apply(&start)?;
+        let history = history.record(&start, Command::Bet(0, Chips(25)), &actions);
+        let (dealt, actions) = Command::Deal.apply(&game)?;
+        let history = history.record(&game, Command::Deal, &actions);
+
+        let loaded = history_from_json(&history_to_json(&history)?)?;
+
+        assert_eq!(loaded.replay(loaded.events().len())?, dealt);
+        assert!(matches!(
+            history_from_json(&history_to_json(&history)?.replacen(
+                &format!("\"version\":{}", SAVE_VERSION),
+                "\"version\":99",
+                1
+            )),
+            Err(SaveError::UnsupportedVersion(99))
+        ));
+        Ok(())
+    }
+}
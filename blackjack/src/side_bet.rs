@@ -0,0 +1,351 @@
+use crate::deck::{Card, Rank, Suit};
+use crate::hand::{DealerHand, Hand, Score};
+use im::{vector, Vector};
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter};
+
+#[derive(Clone, Copy, PartialEq, Debug, EnumIter, Display)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum SideBet {
+    #[strum(serialize = "Perfect Pairs")]
+    PerfectPairs,
+    #[strum(serialize = "21+3")]
+    TwentyOnePlusThree,
+    #[strum(serialize = "Lucky Ladies")]
+    LuckyLadies,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Display)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum SideBetOutcome {
+    #[strum(serialize = "mixed pair")]
+    MixedPair,
+    #[strum(serialize = "colored pair")]
+    ColoredPair,
+    #[strum(serialize = "perfect pair")]
+    PerfectPair,
+    #[strum(serialize = "flush")]
+    Flush,
+    #[strum(serialize = "straight")]
+    Straight,
+    #[strum(serialize = "three of a kind")]
+    ThreeOfAKind,
+    #[strum(serialize = "straight flush")]
+    StraightFlush,
+    #[strum(serialize = "suited trips")]
+    SuitedTrips,
+    #[strum(serialize = "twenty")]
+    Twenty,
+    #[strum(serialize = "suited twenty")]
+    SuitedTwenty,
+    #[strum(serialize = "matched twenty")]
+    MatchedTwenty,
+    #[strum(serialize = "queen of hearts pair")]
+    QueenOfHeartsPair,
+    #[strum(serialize = "queen of hearts pair with dealer blackjack")]
+    QueenOfHeartsWithDealerBlackjack,
+}
+
+/// Pays for each winning outcome, quoted as "to 1".
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Paytable {
+    pays: Vector<(SideBetOutcome, u32)>,
+}
+
+impl Paytable {
+    pub fn new(pays: Vector<(SideBetOutcome, u32)>) -> Self {
+        Paytable { pays }
+    }
+
+    pub fn pays(&self, outcome: SideBetOutcome) -> Option<u32> {
+        self.pays
+            .iter()
+            .find(|(paid, _)| *paid == outcome)
+            .map(|(_, pays)| *pays)
+    }
+}
+
+pub trait SideBetResolver {
+    fn resolve(&self, hand: &Hand, dealer_hand: &DealerHand) -> Option<SideBetOutcome>;
+}
+
+impl SideBet {
+    pub fn paytable(self) -> Paytable {
+        Paytable::new(match self {
+            SideBet::PerfectPairs => vector![
+                (SideBetOutcome::MixedPair, 6),
+                (SideBetOutcome::ColoredPair, 12),
+                (SideBetOutcome::PerfectPair, 25),
+            ],
+            SideBet::TwentyOnePlusThree => vector![
+                (SideBetOutcome::Flush, 5),
+                (SideBetOutcome::Straight, 10),
+                (SideBetOutcome::ThreeOfAKind, 30),
+                (SideBetOutcome::StraightFlush, 40),
+                (SideBetOutcome::SuitedTrips, 100),
+            ],
+            SideBet::LuckyLadies => vector![
+                (SideBetOutcome::Twenty, 4),
+                (SideBetOutcome::SuitedTwenty, 10),
+                (SideBetOutcome::MatchedTwenty, 25),
+                (SideBetOutcome::QueenOfHeartsPair, 200),
+                (SideBetOutcome::QueenOfHeartsWithDealerBlackjack, 1000),
+            ],
+        })
+    }
+}
+
+impl SideBetResolver for SideBet {
+    fn resolve(&self, hand: &Hand, dealer_hand: &DealerHand) -> Option<SideBetOutcome> {
+        let cards = hand.cards();
+        if cards.len() != 2 {
+            return None;
+        }
+        match self {
+            SideBet::PerfectPairs => perfect_pair(cards[0], cards[1]),
+            SideBet::TwentyOnePlusThree => dealer_hand
+                .upcard()
+                .and_then(|upcard| twenty_one_plus_three([cards[0], cards[1], *upcard])),
+            SideBet::LuckyLadies => lucky_ladies(hand, dealer_hand),
+        }
+    }
+}
+
+fn is_red(suit: Suit) -> bool {
+    suit == Suit::Heart || suit == Suit::Diamond
+}
+
+fn perfect_pair(first: Card, second: Card) -> Option<SideBetOutcome> {
+    if first.rank != second.rank {
+        None
+    } else if first.suit == second.suit {
+        Some(SideBetOutcome::PerfectPair)
+    } else if is_red(first.suit) == is_red(second.suit) {
+        Some(SideBetOutcome::ColoredPair)
+    } else {
+        Some(SideBetOutcome::MixedPair)
+    }
+}
+
+fn is_straight(cards: &[Card; 3]) -> bool {
+    let mut ranks: Vec<u8> = cards.iter().map(|card| card.rank as u8).collect();
+    ranks.sort_unstable();
+    let ace_low = ranks == vec![Rank::Two as u8, Rank::Three as u8, Rank::Ace as u8];
+    ace_low || (ranks[1] == ranks[0] + 1 && ranks[2] == ranks[1] + 1)
+}
+
+fn twenty_one_plus_three(cards: [Card; 3]) -> Option<SideBetOutcome> {
+    let flush = cards.iter().all(|card| card.suit == cards[0].suit);
+    let trips = cards.iter().all(|card| card.rank == cards[0].rank);
+    let straight = is_straight(&cards);
+    match (flush, trips, straight) {
+        (true, true, _) => Some(SideBetOutcome::SuitedTrips),
+        (true, _, true) => Some(SideBetOutcome::StraightFlush),
+        (_, true, _) => Some(SideBetOutcome::ThreeOfAKind),
+        (_, _, true) => Some(SideBetOutcome::Straight),
+        (true, _, _) => Some(SideBetOutcome::Flush),
+        _ => None,
+    }
+}
+
+fn lucky_ladies(hand: &Hand, dealer_hand: &DealerHand) -> Option<SideBetOutcome> {
+    if hand.score() != Score(20) {
+        return None;
+    }
+    let cards = hand.cards();
+    let queen_of_hearts = Card {
+        rank: Rank::Queen,
+        suit: Suit::Heart,
+    };
+    if cards.iter().all(|card| *card == queen_of_hearts) {
+        let dealer_blackjack = dealer_hand.cards().len() == 2 && dealer_hand.score() == Score(21);
+        if dealer_blackjack {
+            Some(SideBetOutcome::QueenOfHeartsWithDealerBlackjack)
+        } else {
+            Some(SideBetOutcome::QueenOfHeartsPair)
+        }
+    } else if cards[0] == cards[1] {
+        Some(SideBetOutcome::MatchedTwenty)
+    } else if cards[0].suit == cards[1].suit {
+        Some(SideBetOutcome::SuitedTwenty)
+    } else {
+        Some(SideBetOutcome::Twenty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(rank: Rank, suit: Suit) -> Card {
+        Card { rank, suit }
+    }
+
+    fn hand_of(cards: Vec<Card>) -> Hand {
+        cards
+            .into_iter()
+            .fold(Hand::new(), |hand, card| hand.add(card))
+    }
+
+    fn dealer_of(cards: Vec<Card>) -> DealerHand {
+        cards
+            .into_iter()
+            .fold(DealerHand::new(), |hand, card| hand.add(card))
+    }
+
+    fn dealer_showing(upcard: Card) -> DealerHand {
+        dealer_of(vec![card(Rank::Two, Suit::Club), upcard])
+    }
+
+    #[test]
+    fn perfect_pairs_grades_a_pair_by_its_suits() {
+        let dealer = dealer_showing(card(Rank::Five, Suit::Club));
+        let resolve = |second: Suit| {
+            SideBet::PerfectPairs.resolve(
+                &hand_of(vec![
+                    card(Rank::Eight, Suit::Heart),
+                    card(Rank::Eight, second),
+                ]),
+                &dealer,
+            )
+        };
+
+        assert_eq!(resolve(Suit::Heart), Some(SideBetOutcome::PerfectPair));
+        assert_eq!(resolve(Suit::Diamond), Some(SideBetOutcome::ColoredPair));
+        assert_eq!(resolve(Suit::Spade), Some(SideBetOutcome::MixedPair));
+    }
+
+    #[test]
+    fn perfect_pairs_needs_matching_ranks_not_just_values() {
+        let hand = hand_of(vec![
+            card(Rank::King, Suit::Heart),
+            card(Rank::Queen, Suit::Heart),
+        ]);
+
+        assert_eq!(
+            SideBet::PerfectPairs.resolve(&hand, &dealer_showing(card(Rank::Two, Suit::Club))),
+            None
+        );
+    }
+
+    #[test]
+    fn twenty_one_plus_three_plays_the_dealer_upcard() {
+        let resolve = |first: Card, second: Card, upcard: Card| {
+            SideBet::TwentyOnePlusThree
+                .resolve(&hand_of(vec![first, second]), &dealer_showing(upcard))
+        };
+
+        assert_eq!(
+            resolve(
+                card(Rank::Two, Suit::Club),
+                card(Rank::Nine, Suit::Club),
+                card(Rank::King, Suit::Club)
+            ),
+            Some(SideBetOutcome::Flush)
+        );
+        assert_eq!(
+            resolve(
+                card(Rank::Queen, Suit::Heart),
+                card(Rank::King, Suit::Spade),
+                card(Rank::Ace, Suit::Club)
+            ),
+            Some(SideBetOutcome::Straight)
+        );
+        assert_eq!(
+            resolve(
+                card(Rank::Ace, Suit::Heart),
+                card(Rank::Two, Suit::Heart),
+                card(Rank::Three, Suit::Heart)
+            ),
+            Some(SideBetOutcome::StraightFlush)
+        );
+        assert_eq!(
+            resolve(
+                card(Rank::Seven, Suit::Heart),
+                card(Rank::Seven, Suit::Spade),
+                card(Rank::Seven, Suit::Club)
+            ),
+            Some(SideBetOutcome::ThreeOfAKind)
+        );
+        assert_eq!(
+            resolve(
+                card(Rank::Seven, Suit::Heart),
+                card(Rank::Seven, Suit::Heart),
+                card(Rank::Seven, Suit::Heart)
+            ),
+            Some(SideBetOutcome::SuitedTrips)
+        );
+        assert_eq!(
+            resolve(
+                card(Rank::King, Suit::Heart),
+                card(Rank::Ace, Suit::Spade),
+                card(Rank::Two, Suit::Club)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn lucky_ladies_pays_on_twenty() {
+        let dealer = dealer_showing(card(Rank::Six, Suit::Club));
+        let resolve = |first: Card, second: Card| {
+            SideBet::LuckyLadies.resolve(&hand_of(vec![first, second]), &dealer)
+        };
+
+        assert_eq!(
+            resolve(card(Rank::Ace, Suit::Club), card(Rank::Nine, Suit::Heart)),
+            Some(SideBetOutcome::Twenty)
+        );
+        assert_eq!(
+            resolve(card(Rank::Jack, Suit::Club), card(Rank::King, Suit::Club)),
+            Some(SideBetOutcome::SuitedTwenty)
+        );
+        assert_eq!(
+            resolve(card(Rank::Jack, Suit::Club), card(Rank::Jack, Suit::Club)),
+            Some(SideBetOutcome::MatchedTwenty)
+        );
+        assert_eq!(
+            resolve(card(Rank::Jack, Suit::Club), card(Rank::Nine, Suit::Club)),
+            None
+        );
+    }
+
+    #[test]
+    fn lucky_ladies_top_award_needs_a_dealer_blackjack() {
+        let queens = hand_of(vec![
+            card(Rank::Queen, Suit::Heart),
+            card(Rank::Queen, Suit::Heart),
+        ]);
+
+        assert_eq!(
+            SideBet::LuckyLadies.resolve(&queens, &dealer_showing(card(Rank::Ace, Suit::Club))),
+            Some(SideBetOutcome::QueenOfHeartsPair)
+        );
+        assert_eq!(
+            SideBet::LuckyLadies.resolve(
+                &queens,
+                &dealer_of(vec![
+                    card(Rank::King, Suit::Club),
+                    card(Rank::Ace, Suit::Club)
+                ])
+            ),
+            Some(SideBetOutcome::QueenOfHeartsWithDealerBlackjack)
+        );
+    }
+
+    #[test]
+    fn a_paytable_only_pays_the_outcomes_it_lists() {
+        let paytable = Paytable::new(vector![(SideBetOutcome::PerfectPair, 30)]);
+
+        assert_eq!(paytable.pays(SideBetOutcome::PerfectPair), Some(30));
+        assert_eq!(paytable.pays(SideBetOutcome::MixedPair), None);
+        assert_eq!(
+            SideBet::PerfectPairs
+                .paytable()
+                .pays(SideBetOutcome::MixedPair),
+            Some(6)
+        );
+    }
+}
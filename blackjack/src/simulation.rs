@@ -0,0 +1,347 @@
+use crate::bankroll::{Bankroll, Chips};
+use crate::counting::CardCounter;
+use crate::game::{bet, deal, Action, BlackjackError, GameState};
+use crate::hand::Score;
+use crate::history::Command;
+use crate::policy::{PlayerPolicy, TableView};
+use crate::rules::TableRules;
+use crate::strategy::Move;
+use im::Vector;
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const SIMULATION_WAGER: Chips = Chips(10);
+const SIMULATION_BANKROLL: Chips = Chips(u32::MAX / 2);
+
+type TransitionResult = Result<(GameState, Vector<Action>), Box<dyn std::error::Error>>;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct UpcardStatistics {
+    pub hands: u64,
+    pub busts: u64,
+}
+
+impl UpcardStatistics {
+    pub fn bust_rate(&self) -> f64 {
+        rate(self.busts, self.hands)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Statistics {
+    pub rounds: u64,
+    pub wins: u64,
+    pub losses: u64,
+    pub pushes: u64,
+    pub blackjacks: u64,
+    pub dealer_upcards: BTreeMap<u8, UpcardStatistics>,
+    units: f64,
+    squared_units: f64,
+}
+
+impl Statistics {
+    pub fn mean(&self) -> f64 {
+        if self.rounds == 0 {
+            0.0
+        } else {
+            self.units / self.rounds as f64
+        }
+    }
+
+    pub fn house_edge(&self) -> f64 {
+        -self.mean()
+    }
+
+    pub fn variance(&self) -> f64 {
+        if self.rounds == 0 {
+            0.0
+        } else {
+            self.squared_units / self.rounds as f64 - self.mean().powi(2)
+        }
+    }
+
+    pub fn standard_error(&self) -> f64 {
+        if self.rounds == 0 {
+            0.0
+        } else {
+            (self.variance() / self.rounds as f64).sqrt()
+        }
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        rate(self.wins, self.rounds)
+    }
+
+    pub fn loss_rate(&self) -> f64 {
+        rate(self.losses, self.rounds)
+    }
+
+    pub fn push_rate(&self) -> f64 {
+        rate(self.pushes, self.rounds)
+    }
+
+    pub fn blackjack_rate(&self) -> f64 {
+        rate(self.blackjacks, self.rounds)
+    }
+
+    fn record(&mut self, state: &GameState, actions: &Vector<Action>, units: f64) {
+        self.rounds += 1;
+        self.units += units;
+        self.squared_units += units * units;
+        if units > 0.0 {
+            self.wins += 1;
+        } else if units < 0.0 {
+            self.losses += 1;
+        } else {
+            self.pushes += 1;
+        }
+        if actions
+            .iter()
+            .any(|action| matches!(action, Action::PlayerBlackjack(_) | Action::EvenMoney(_)))
+        {
+            self.blackjacks += 1;
+        }
+
+        let context = state.context();
+        if let (Some(upcard), true) = (context.dealer_hand.upcard(), dealer_played(state)) {
+            let upcard_statistics = self
+                .dealer_upcards
+                .entry(upcard.rank.to_value().0)
+                .or_default();
+            upcard_statistics.hands += 1;
+            if context.dealer_hand.score() > Score(21) {
+                upcard_statistics.busts += 1;
+            }
+        }
+    }
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Rounds played:      {}", self.rounds)?;
+        writeln!(
+            f,
+            "House edge:         {:.3}% (+/- {:.3}%)",
+            self.house_edge() * 100.0,
+            self.standard_error() * 100.0
+        )?;
+        writeln!(f, "Win rate:           {:.2}%", self.win_rate() * 100.0)?;
+        writeln!(f, "Loss rate:          {:.2}%", self.loss_rate() * 100.0)?;
+        writeln!(f, "Push rate:          {:.2}%", self.push_rate() * 100.0)?;
+        writeln!(
+            f,
+            "Blackjacks:         {:.2}%",
+            self.blackjack_rate() * 100.0
+        )?;
+        writeln!(f, "Variance per round: {:.4}", self.variance())?;
+        writeln!(f, "Dealer bust rate by upcard:")?;
+        for (upcard, upcard_statistics) in &self.dealer_upcards {
+            let label = if *upcard == 11 {
+                "A".to_string()
+            } else {
+                upcard.to_string()
+            };
+            writeln!(
+                f,
+                "  {:>2}: {:.2}% of {} hands",
+                label,
+                upcard_statistics.bust_rate() * 100.0,
+                upcard_statistics.hands
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn simulate(
+    policy: &mut impl PlayerPolicy,
+    rules: TableRules,
+    seed: u64,
+    rounds: u64,
+) -> Result<Statistics, Box<dyn std::error::Error>> {
+    let mut counter = CardCounter::new(rules.decks);
+    let mut context = GameState::new(rules, seed)?.context().clone();
+    context.seats[0].bankroll = Bankroll::new(SIMULATION_BANKROLL);
+    let mut state = GameState::Ready(context);
+    let mut statistics = Statistics::default();
+    let staked = SIMULATION_WAGER.0 as f64 * state.context().rules().hands_per_seat() as f64;
+
+    for _ in 0..rounds {
+        let balance = state.context().seat().bankroll.balance();
+        let (round_state, actions) = play_round(policy, &mut counter, &state)?;
+        let units =
+            (round_state.context().seat().bankroll.balance().0 as f64 - balance.0 as f64) / staked;
+        statistics.record(&round_state, &actions, units);
+        state = round_state;
+    }
+    Ok(statistics)
+}
+
+fn play_round(
+    policy: &mut impl PlayerPolicy,
+    counter: &mut CardCounter,
+    state: &GameState,
+) -> TransitionResult {
+    let (state, mut actions) = bet(state, 0, SIMULATION_WAGER)?;
+    let (mut state, dealt) = deal(&state)?;
+    *counter = counter.observe(&dealt);
+    actions.extend(dealt);
+
+    loop {
+        let (next_state, next_actions) = match &state {
+            GameState::OfferingInsurance(_) | GameState::WaitingForPlayer(_) => {
+                let view =
+                    TableView::new(&state, counter).ok_or(BlackjackError::InvalidStateError)?;
+                decide(policy, &state, &view)?
+            }
+            _ => return Ok((state, actions)),
+        };
+        *counter = counter.observe(&next_actions);
+        state = next_state;
+        actions.extend(next_actions);
+    }
+}
+
+pub fn decide(
+    policy: &mut (impl PlayerPolicy + ?Sized),
+    state: &GameState,
+    view: &TableView,
+) -> TransitionResult {
+    choose(policy, state, view).apply(state)
+}
+
+pub fn choose(
+    policy: &mut (impl PlayerPolicy + ?Sized),
+    state: &GameState,
+    view: &TableView,
+) -> Command {
+    match state {
+        GameState::OfferingInsurance(_) if state.can_surrender() => {
+            if policy.decide(view) == Move::Surrender {
+                Command::Surrender
+            } else {
+                insurance(policy, view)
+            }
+        }
+        GameState::OfferingInsurance(_) => insurance(policy, view),
+        _ => match policy.decide(view) {
+            chosen if view.allowed.contains(&chosen) => Command::from(chosen),
+            _ => Command::Stand,
+        },
+    }
+}
+
+fn insurance(policy: &mut (impl PlayerPolicy + ?Sized), view: &TableView) -> Command {
+    if policy.take_insurance(view) {
+        Command::TakeInsurance
+    } else {
+        Command::DeclineInsurance
+    }
+}
+
+pub fn play(state: &GameState, chosen: Move) -> TransitionResult {
+    Command::from(chosen).apply(state)
+}
+
+fn dealer_played(state: &GameState) -> bool {
+    let context = state.context();
+    let natural = |cards: usize, score: Score| cards == 2 && score == Score(21);
+    let player_natural = context.seat().player_hands.len() == 1
+        && natural(
+            context.player_hand().cards().len(),
+            context.player_hand().score(),
+        );
+
+    match state {
+        GameState::PlayerSurrenders(_) => false,
+        _ => {
+            !natural(
+                context.dealer_hand.cards().len(),
+                context.dealer_hand.score(),
+            ) && !player_natural
+                && context
+                    .seat()
+                    .player_hands
+                    .iter()
+                    .any(|player_hand| player_hand.score() <= Score(21))
+        }
+    }
+}
+
+fn rate(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::BasicStrategy;
+    use crate::rules::BlackjackPayout;
+
+    struct AlwaysStand;
+
+    impl PlayerPolicy for AlwaysStand {
+        fn decide(&mut self, _view: &TableView) -> Move {
+            Move::Stand
+        }
+    }
+
+    #[test]
+    fn every_round_is_a_win_loss_or_push() -> Result<(), Box<dyn std::error::Error>> {
+        let statistics = simulate(&mut BasicStrategy::new(), TableRules::default(), 1, 2000)?;
+
+        assert_eq!(statistics.rounds, 2000);
+        assert_eq!(
+            statistics.wins + statistics.losses + statistics.pushes,
+            statistics.rounds
+        );
+        assert!(statistics.blackjacks > 0);
+        assert!(statistics.variance() > 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn simulations_with_the_same_seed_match() -> Result<(), Box<dyn std::error::Error>> {
+        let first = simulate(&mut BasicStrategy::new(), TableRules::default(), 7, 500)?;
+        let second = simulate(&mut BasicStrategy::new(), TableRules::default(), 7, 500)?;
+
+        assert_eq!(first, second);
+        Ok(())
+    }
+
+    #[test]
+    fn standing_on_everything_lets_the_dealer_play_most_hands(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let statistics = simulate(&mut AlwaysStand, TableRules::default(), 3, 2000)?;
+
+        let dealer_hands: u64 = statistics
+            .dealer_upcards
+            .values()
+            .map(|upcard| upcard.hands)
+            .sum();
+        assert!(dealer_hands <= statistics.rounds);
+        assert!(dealer_hands > statistics.rounds * 8 / 10);
+        assert!(
+            statistics.dealer_upcards[&6].bust_rate() > statistics.dealer_upcards[&11].bust_rate()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn a_worse_blackjack_payout_raises_the_house_edge() -> Result<(), Box<dyn std::error::Error>> {
+        let six_to_five = TableRules {
+            blackjack_payout: BlackjackPayout::SixToFive,
+            ..TableRules::default()
+        };
+
+        let standard = simulate(&mut AlwaysStand, TableRules::default(), 5, 2000)?;
+        let worse = simulate(&mut AlwaysStand, six_to_five, 5, 2000)?;
+
+        assert!(worse.house_edge() > standard.house_edge());
+        Ok(())
+    }
+}
@@ -0,0 +1,621 @@
+use crate::deck::{Card, Rank};
+use crate::hand::{Hand, HandTotal, Score};
+use crate::rules::{DealerStoppingRule, DoubleRule, SurrenderRule, TableRules};
+use std::collections::HashMap;
+use std::fmt;
+
+const BUST: usize = 22;
+const CARD_VALUES: [u8; 10] = [2, 3, 4, 5, 6, 7, 8, 9, 10, 1];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Move {
+    Hit,
+    Stand,
+    Double,
+    Split,
+    Surrender,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let abbreviation = match self {
+            Move::Hit => "H",
+            Move::Stand => "S",
+            Move::Double => "D",
+            Move::Split => "P",
+            Move::Surrender => "R",
+        };
+        write!(f, "{}", abbreviation)
+    }
+}
+
+pub const ALL_MOVES: [Move; 5] = [
+    Move::Hit,
+    Move::Stand,
+    Move::Double,
+    Move::Split,
+    Move::Surrender,
+];
+
+pub fn basic_strategy(hand: &Hand, upcard: &Card, rules: &TableRules) -> Move {
+    Advisor::new(rules).advise(hand, upcard, &ALL_MOVES)
+}
+
+pub struct Advisor {
+    rules: TableRules,
+    evaluators: HashMap<u8, Evaluator>,
+}
+
+impl Advisor {
+    pub fn new(rules: &TableRules) -> Self {
+        Advisor {
+            rules: rules.clone(),
+            evaluators: HashMap::new(),
+        }
+    }
+
+    pub fn advise(&mut self, hand: &Hand, upcard: &Card, allowed: &[Move]) -> Move {
+        let (hard, ace, first_decision, pair) = decision(hand);
+        self.evaluator(card_value(upcard.rank))
+            .best_move(hard, ace, first_decision, pair, allowed)
+    }
+
+    // With the dealer's cards face down every upcard is possible, so each is
+    // weighed by its chance of showing once the dealer has no natural.
+    pub fn advise_blind(&mut self, hand: &Hand, allowed: &[Move]) -> Move {
+        let (hard, ace, first_decision, pair) = decision(hand);
+        let mut expected: Vec<(Move, f64)> = Vec::new();
+        for upcard in CARD_VALUES.iter() {
+            let evaluator = self.evaluator(*upcard);
+            let weight = probability(*upcard) * (1.0 - evaluator.dealer_blackjack);
+            for (choice, value) in evaluator.choices(hard, ace, first_decision, pair) {
+                match expected.iter_mut().find(|(seen, _)| *seen == choice) {
+                    Some((_, total)) => *total += weight * value,
+                    None => expected.push((choice, weight * value)),
+                }
+            }
+        }
+        best_of(expected, allowed)
+    }
+
+    fn evaluator(&mut self, upcard: u8) -> &mut Evaluator {
+        let rules = &self.rules;
+        self.evaluators
+            .entry(upcard)
+            .or_insert_with(|| Evaluator::new(upcard, rules))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StrategyTable {
+    pub hard: Vec<(u8, Vec<Move>)>,
+    pub soft: Vec<(u8, Vec<Move>)>,
+    pub pairs: Vec<(u8, Vec<Move>)>,
+}
+
+impl StrategyTable {
+    pub fn generate(rules: &TableRules) -> Self {
+        let mut advisor = Advisor::new(rules);
+        let mut row = |hard: u8, ace: bool, pair: Option<u8>| -> Vec<Move> {
+            CARD_VALUES
+                .iter()
+                .map(|upcard| {
+                    advisor
+                        .evaluator(*upcard)
+                        .best_move(hard, ace, true, pair, &ALL_MOVES)
+                })
+                .collect()
+        };
+
+        StrategyTable {
+            hard: (5..=20)
+                .map(|total| (total, row(total, false, None)))
+                .collect(),
+            soft: (13..=20)
+                .map(|total| (total, row(total - 10, true, None)))
+                .collect(),
+            pairs: CARD_VALUES
+                .iter()
+                .map(|value| (*value, row(value * 2, *value == 1, Some(*value))))
+                .collect(),
+        }
+    }
+
+    pub fn hard(&self, total: u8, upcard: &Card) -> Option<Move> {
+        Self::lookup(&self.hard, total, upcard)
+    }
+
+    pub fn soft(&self, total: u8, upcard: &Card) -> Option<Move> {
+        Self::lookup(&self.soft, total, upcard)
+    }
+
+    pub fn pair(&self, rank: Rank, upcard: &Card) -> Option<Move> {
+        Self::lookup(&self.pairs, card_value(rank), upcard)
+    }
+
+    fn lookup(rows: &[(u8, Vec<Move>)], total: u8, upcard: &Card) -> Option<Move> {
+        let column = CARD_VALUES
+            .iter()
+            .position(|value| *value == card_value(upcard.rank))?;
+        rows.iter()
+            .find(|(row_total, _)| *row_total == total)
+            .map(|(_, moves)| moves[column])
+    }
+}
+
+impl fmt::Display for StrategyTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "       2 3 4 5 6 7 8 9 T A")?;
+        let sections = [
+            ("Hard", &self.hard),
+            ("Soft", &self.soft),
+            ("Pair", &self.pairs),
+        ];
+        for (name, rows) in sections.iter() {
+            for (total, moves) in rows.iter() {
+                match total {
+                    1 => write!(f, "{}  A", name)?,
+                    _ => write!(f, "{} {:>2}", name, total)?,
+                }
+                for chosen in moves {
+                    write!(f, " {}", chosen)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn decision(hand: &Hand) -> (u8, bool, bool, Option<u8>) {
+    let cards = hand.cards();
+    let hard = cards.iter().map(|card| card_value(card.rank)).sum();
+    let ace = cards.iter().any(|card| card.rank == Rank::Ace);
+    let pair = if cards.len() == 2 && cards[0].rank == cards[1].rank {
+        Some(card_value(cards[0].rank))
+    } else {
+        None
+    };
+    (hard, ace, cards.len() == 2, pair)
+}
+
+fn best_of(choices: Vec<(Move, f64)>, allowed: &[Move]) -> Move {
+    choices
+        .into_iter()
+        .filter(|(choice, _)| allowed.contains(choice))
+        .fold((Move::Stand, f64::NEG_INFINITY), |best, choice| {
+            if choice.1 > best.1 {
+                choice
+            } else {
+                best
+            }
+        })
+        .0
+}
+
+fn card_value(rank: Rank) -> u8 {
+    match rank {
+        Rank::Ace => 1,
+        _ => rank.to_value().0,
+    }
+}
+
+fn probability(value: u8) -> f64 {
+    if value == 10 {
+        4.0 / 13.0
+    } else {
+        1.0 / 13.0
+    }
+}
+
+fn total(hard: u8, ace: bool) -> HandTotal {
+    if ace && hard + 10 <= 21 {
+        HandTotal {
+            score: Score(hard + 10),
+            soft: true,
+        }
+    } else {
+        HandTotal {
+            score: Score(hard),
+            soft: false,
+        }
+    }
+}
+
+// Expected values are computed for an infinite deck, after the dealer has
+// checked for blackjack, ignoring resplits.
+struct Evaluator {
+    rules: TableRules,
+    dealer_blackjack: f64,
+    dealer_totals: [f64; BUST + 1],
+    best_play: HashMap<(u8, bool), f64>,
+}
+
+impl Evaluator {
+    fn new(upcard: u8, rules: &TableRules) -> Self {
+        let blackjack_hole_card = match upcard {
+            1 => Some(10),
+            10 => Some(1),
+            _ => None,
+        };
+        let dealer_blackjack = blackjack_hole_card.map_or(0.0, probability);
+
+        let mut dealer_totals = [0.0; BUST + 1];
+        for hole_card in CARD_VALUES.iter() {
+            if Some(*hole_card) == blackjack_hole_card {
+                continue;
+            }
+            let weight = probability(*hole_card) / (1.0 - dealer_blackjack);
+            let hard = upcard + hole_card;
+            let ace = upcard == 1 || *hole_card == 1;
+            Self::dealer_draws(rules, hard, ace, weight, &mut dealer_totals);
+        }
+
+        Evaluator {
+            rules: rules.clone(),
+            dealer_blackjack,
+            dealer_totals,
+            best_play: HashMap::new(),
+        }
+    }
+
+    fn dealer_draws(
+        rules: &TableRules,
+        hard: u8,
+        ace: bool,
+        weight: f64,
+        dealer_totals: &mut [f64; BUST + 1],
+    ) {
+        let current = total(hard, ace);
+        if current.score > Score(21) {
+            dealer_totals[BUST] += weight;
+        } else if !rules.dealer.hits(current) {
+            dealer_totals[current.score.0 as usize] += weight;
+        } else {
+            for card in CARD_VALUES.iter() {
+                Self::dealer_draws(
+                    rules,
+                    hard + card,
+                    ace || *card == 1,
+                    weight * probability(*card),
+                    dealer_totals,
+                );
+            }
+        }
+    }
+
+    fn stand(&self, hard: u8, ace: bool) -> f64 {
+        let Score(player) = total(hard, ace).score;
+        if player > 21 {
+            return -1.0;
+        }
+        self.dealer_totals
+            .iter()
+            .enumerate()
+            .map(|(dealer, chance)| match dealer {
+                BUST => *chance,
+                _ if (player as usize) > dealer => *chance,
+                _ if (player as usize) < dealer => -chance,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    fn after_card<F>(&mut self, hard: u8, ace: bool, mut play: F) -> f64
+    where
+        F: FnMut(&mut Self, u8, bool) -> f64,
+    {
+        CARD_VALUES
+            .iter()
+            .map(|card| {
+                let (hard, ace) = (hard + card, ace || *card == 1);
+                let outcome = if total(hard, ace).score > Score(21) {
+                    -1.0
+                } else {
+                    play(self, hard, ace)
+                };
+                probability(*card) * outcome
+            })
+            .sum()
+    }
+
+    fn hit(&mut self, hard: u8, ace: bool) -> f64 {
+        self.after_card(hard, ace, Self::best_play)
+    }
+
+    fn best_play(&mut self, hard: u8, ace: bool) -> f64 {
+        if let Some(expected) = self.best_play.get(&(hard, ace)) {
+            return *expected;
+        }
+        let expected = self.stand(hard, ace).max(self.hit(hard, ace));
+        self.best_play.insert((hard, ace), expected);
+        expected
+    }
+
+    fn double(&mut self, hard: u8, ace: bool) -> f64 {
+        2.0 * self.after_card(hard, ace, |evaluator, hard, ace| evaluator.stand(hard, ace))
+    }
+
+    fn can_double(&self, hard: u8, ace: bool) -> bool {
+        match self.rules.double_rule {
+            DoubleRule::AnyTwoCards => true,
+            DoubleRule::NineToEleven => {
+                let score = total(hard, ace).score;
+                Score(9) <= score && score <= Score(11)
+            }
+        }
+    }
+
+    fn split(&mut self, value: u8) -> f64 {
+        let ace = value == 1;
+        let split_hand = self.after_card(value, ace, |evaluator, hard, ace| {
+            if value == 1 && !evaluator.rules.hit_split_aces {
+                return evaluator.stand(hard, ace);
+            }
+            let mut expected = evaluator.best_play(hard, ace);
+            if evaluator.rules.double_after_split && evaluator.can_double(hard, ace) {
+                expected = expected.max(evaluator.double(hard, ace));
+            }
+            expected
+        });
+        2.0 * split_hand
+    }
+
+    fn surrender(&self) -> f64 {
+        match self.rules.surrender {
+            SurrenderRule::Early => (self.dealer_blackjack - 0.5) / (1.0 - self.dealer_blackjack),
+            _ => -0.5,
+        }
+    }
+
+    fn best_move(
+        &mut self,
+        hard: u8,
+        ace: bool,
+        first_decision: bool,
+        pair: Option<u8>,
+        allowed: &[Move],
+    ) -> Move {
+        best_of(self.choices(hard, ace, first_decision, pair), allowed)
+    }
+
+    fn choices(
+        &mut self,
+        hard: u8,
+        ace: bool,
+        first_decision: bool,
+        pair: Option<u8>,
+    ) -> Vec<(Move, f64)> {
+        let mut choices = vec![
+            (Move::Stand, self.stand(hard, ace)),
+            (Move::Hit, self.hit(hard, ace)),
+        ];
+        if first_decision && self.can_double(hard, ace) {
+            choices.push((Move::Double, self.double(hard, ace)));
+        }
+        if let Some(value) = pair {
+            choices.push((Move::Split, self.split(value)));
+        }
+        if first_decision && self.rules.surrender != SurrenderRule::NoSurrender {
+            choices.push((Move::Surrender, self.surrender()));
+        }
+        choices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck::Suit;
+    use crate::rules::DealerRule;
+
+    fn card(rank: Rank) -> Card {
+        Card {
+            rank,
+            suit: Suit::Spade,
+        }
+    }
+
+    fn hand(ranks: Vec<Rank>) -> Hand {
+        ranks
+            .into_iter()
+            .fold(Hand::new(), |hand, rank| hand.add(card(rank)))
+    }
+
+    fn no_surrender() -> TableRules {
+        TableRules {
+            surrender: SurrenderRule::NoSurrender,
+            ..TableRules::default()
+        }
+    }
+
+    #[test]
+    fn stand_on_hard_seventeen() {
+        let advice = basic_strategy(
+            &hand(vec![Rank::Ten, Rank::Seven]),
+            &card(Rank::Ten),
+            &TableRules::default(),
+        );
+
+        assert_eq!(advice, Move::Stand);
+    }
+
+    #[test]
+    fn hit_twelve_against_a_seven_and_stand_against_a_four() {
+        let twelve = hand(vec![Rank::Ten, Rank::Two]);
+
+        assert_eq!(
+            basic_strategy(&twelve, &card(Rank::Seven), &TableRules::default()),
+            Move::Hit
+        );
+        assert_eq!(
+            basic_strategy(&twelve, &card(Rank::Four), &TableRules::default()),
+            Move::Stand
+        );
+    }
+
+    #[test]
+    fn double_eleven_against_a_six() {
+        let advice = basic_strategy(
+            &hand(vec![Rank::Six, Rank::Five]),
+            &card(Rank::Six),
+            &TableRules::default(),
+        );
+
+        assert_eq!(advice, Move::Double);
+    }
+
+    #[test]
+    fn only_hit_after_the_first_two_cards() {
+        let advice = basic_strategy(
+            &hand(vec![Rank::Three, Rank::Three, Rank::Five]),
+            &card(Rank::Six),
+            &TableRules::default(),
+        );
+
+        assert_eq!(advice, Move::Hit);
+    }
+
+    #[test]
+    fn always_split_aces_and_eights() {
+        let rules = no_surrender();
+
+        for upcard in [Rank::Two, Rank::Seven, Rank::Ten].iter() {
+            assert_eq!(
+                basic_strategy(&hand(vec![Rank::Ace, Rank::Ace]), &card(*upcard), &rules),
+                Move::Split
+            );
+            assert_eq!(
+                basic_strategy(
+                    &hand(vec![Rank::Eight, Rank::Eight]),
+                    &card(*upcard),
+                    &rules
+                ),
+                Move::Split
+            );
+        }
+    }
+
+    #[test]
+    fn never_split_tens() {
+        let advice = basic_strategy(
+            &hand(vec![Rank::King, Rank::King]),
+            &card(Rank::Six),
+            &TableRules::default(),
+        );
+
+        assert_eq!(advice, Move::Stand);
+    }
+
+    #[test]
+    fn surrender_sixteen_against_a_ten_only_when_the_table_allows_it() {
+        let sixteen = hand(vec![Rank::Ten, Rank::Six]);
+
+        assert_eq!(
+            basic_strategy(&sixteen, &card(Rank::Ten), &TableRules::default()),
+            Move::Surrender
+        );
+        assert_eq!(
+            basic_strategy(&sixteen, &card(Rank::Ten), &no_surrender()),
+            Move::Hit
+        );
+    }
+
+    #[test]
+    fn soft_nineteen_doubles_against_a_six_only_when_the_dealer_hits_soft_seventeen() {
+        let soft_nineteen = hand(vec![Rank::Ace, Rank::Eight]);
+        let h17 = TableRules {
+            dealer: DealerRule::HitsSoft17,
+            ..TableRules::default()
+        };
+
+        assert_eq!(
+            basic_strategy(&soft_nineteen, &card(Rank::Six), &TableRules::default()),
+            Move::Stand
+        );
+        assert_eq!(
+            basic_strategy(&soft_nineteen, &card(Rank::Six), &h17),
+            Move::Double
+        );
+    }
+
+    #[test]
+    fn restricted_doubling_changes_the_advice() {
+        let soft_seventeen = hand(vec![Rank::Ace, Rank::Six]);
+        let nine_to_eleven = TableRules {
+            double_rule: DoubleRule::NineToEleven,
+            ..TableRules::default()
+        };
+
+        assert_eq!(
+            basic_strategy(&soft_seventeen, &card(Rank::Five), &TableRules::default()),
+            Move::Double
+        );
+        assert_eq!(
+            basic_strategy(&soft_seventeen, &card(Rank::Five), &nine_to_eleven),
+            Move::Hit
+        );
+    }
+
+    #[test]
+    fn the_generated_table_matches_the_advisor() {
+        let rules = TableRules::default();
+        let table = StrategyTable::generate(&rules);
+
+        assert_eq!(table.hard(16, &card(Rank::Ten)), Some(Move::Surrender));
+        assert_eq!(table.hard(11, &card(Rank::Six)), Some(Move::Double));
+        assert_eq!(table.soft(18, &card(Rank::Nine)), Some(Move::Hit));
+        assert_eq!(
+            table.pair(Rank::Eight, &card(Rank::Nine)),
+            Some(Move::Split)
+        );
+        assert_eq!(table.hard(22, &card(Rank::Nine)), None);
+    }
+
+    #[test]
+    fn different_rules_generate_different_tables() {
+        let h17 = TableRules {
+            dealer: DealerRule::HitsSoft17,
+            ..TableRules::default()
+        };
+
+        assert_ne!(
+            StrategyTable::generate(&TableRules::default()),
+            StrategyTable::generate(&h17)
+        );
+    }
+
+    #[test]
+    fn the_advisor_only_picks_from_the_allowed_moves() {
+        let mut advisor = Advisor::new(&TableRules::default());
+        let eights = hand(vec![Rank::Eight, Rank::Eight]);
+
+        assert_eq!(
+            advisor.advise(&eights, &card(Rank::Ten), &ALL_MOVES),
+            Move::Split
+        );
+        assert_eq!(
+            advisor.advise(&eights, &card(Rank::Ten), &[Move::Hit, Move::Stand]),
+            Move::Hit
+        );
+    }
+
+    #[test]
+    fn without_an_upcard_the_advisor_plays_the_odds() {
+        let mut advisor = Advisor::new(&TableRules::pontoon());
+        let hit_or_stand = [Move::Hit, Move::Stand];
+
+        assert_eq!(
+            advisor.advise_blind(&hand(vec![Rank::Ten, Rank::Two]), &hit_or_stand),
+            Move::Hit
+        );
+        assert_eq!(
+            advisor.advise_blind(&hand(vec![Rank::Ten, Rank::Nine]), &hit_or_stand),
+            Move::Stand
+        );
+        assert_eq!(
+            advisor.advise_blind(&hand(vec![Rank::Six, Rank::Five]), &ALL_MOVES),
+            Move::Double
+        );
+    }
+}
@@ -1,14 +1,25 @@
+use blackjack::bankroll::Chips;
 use blackjack::deck::{Card, Rank};
 use blackjack::{
-    game::{deal, hit, stand, Action, GameState},
-    hand::DealerHand,
+    game::{Action, Context, GameState, HandId},
+    hand::{DealerHand, Hand},
+    history::{Command, HandHistory},
+    save,
+    strategy::{basic_strategy, Move},
 };
-use gdnative::api::{AtlasTexture, RichTextLabel, ToolButton};
+use gdnative::api::{AtlasTexture, FileDialog, Label, RichTextLabel, ToolButton};
 use gdnative::prelude::*;
 use im::{vector, Vector};
 use std::cmp::Ordering;
+use std::fs;
 use thiserror::Error;
 
+const BET_SIZE: Chips = Chips(10);
+const DEALT_ROTATION: f64 = 360.0;
+const SIDEWAYS_ROTATION: f64 = 450.0;
+const HAND_SPACING: f32 = 250.0;
+const REPLAY_STEP_SECONDS: f64 = 1.5;
+
 #[derive(Debug, Error)]
 enum GodotError {
     #[error("{0}")]
@@ -82,7 +93,69 @@ fn clear_result_text(owner: TRef<Node2D>) {
     });
 }
 
-fn show_dealer_hole_card(owner: TRef<Node2D>, texture: &str) {
+fn set_button_disabled(owner: TRef<Node2D>, name: &str, disabled: bool) {
+    get_typed_node::<ToolButton>(name, owner).map(|node| {
+        node.set_disabled(disabled);
+    });
+}
+
+fn set_button_highlighted(owner: TRef<Node2D>, name: &str, highlighted: bool) {
+    get_typed_node::<ToolButton>(name, owner).map(|node| {
+        let color = if highlighted {
+            Color::rgb(1.0, 0.85, 0.2)
+        } else {
+            Color::rgb(1.0, 1.0, 1.0)
+        };
+        node.set_modulate(color);
+    });
+}
+
+fn highlight_basic_strategy(owner: TRef<Node2D>, advice: Option<Move>) {
+    let buttons = [
+        (Move::Hit, "./Hit"),
+        (Move::Stand, "./Stand"),
+        (Move::Double, "./DoubleDown"),
+        (Move::Split, "./Split"),
+        (Move::Surrender, "./Surrender"),
+    ];
+    for (button_move, name) in buttons.iter() {
+        set_button_highlighted(owner, name, advice == Some(*button_move));
+    }
+}
+
+fn set_button_text(owner: TRef<Node2D>, name: &str, text: &str) {
+    get_typed_node::<ToolButton>(name, owner).map(|node| {
+        node.set_text(text);
+    });
+}
+
+fn show_replay_position(owner: TRef<Node2D>, replay: Option<&Replay>) {
+    get_typed_node::<Label>("./ReplayPosition", owner).map(|node| {
+        let text = match replay {
+            Some(replay) if replay.position > 0 => format!(
+                "Hand {} - event {} of {}",
+                replay.history.events()[replay.position - 1].hand,
+                replay.position,
+                replay.history.events().len()
+            ),
+            Some(replay) => format!("Event 0 of {}", replay.history.events().len()),
+            None => String::new(),
+        };
+        node.set_text(text);
+    });
+}
+
+fn show_bankroll(owner: TRef<Node2D>, balance: Chips) {
+    get_typed_node::<Label>("./Bankroll", owner).map(|node| {
+        node.set_text(format!("Chips: {}", balance.0));
+    });
+}
+
+// Every dealt card is followed by the tween that moved it into the hand
+const DEALER_HOLE_CARD_CHILD: i64 = 0;
+const DEALER_UPCARD_CHILD: i64 = 2;
+
+fn show_dealer_card(owner: TRef<Node2D>, child: i64, texture: &str) {
     get_typed_node::<Node2D>("./DealerHand", owner).map(|dealer_hand_node| {
         let resource_loader = ResourceLoader::godot_singleton();
         let sprite = Sprite::new();
@@ -91,19 +164,114 @@ fn show_dealer_hole_card(owner: TRef<Node2D>, texture: &str) {
             .and_then(|res| res.cast::<AtlasTexture>())
             .expect("Couldn't load atlasTexture texture");
         sprite.set_texture(texture);
-        sprite.set_position(Vector2::new(0.0, 0.0));
+        sprite.set_position(Vector2::new(child as f32 * 35.0, 0.0));
 
-        let hole_texture = dealer_hand_node.get_child(0).unwrap();
-        let hole_texture = unsafe { hole_texture.assume_unique() };
-        hole_texture.replace_by(sprite, false);
-        hole_texture.queue_free();
+        let face_down = dealer_hand_node.get_child(child).unwrap();
+        let face_down = unsafe { face_down.assume_unique() };
+        face_down.replace_by(sprite, false);
+        face_down.queue_free();
     });
 }
 
+fn player_hand_node(owner: TRef<Node2D>, index: usize) -> Result<TRef<Node2D>, GodotError> {
+    let player_hands = get_typed_node::<Node2D>("./PlayerHand", owner)?;
+    while player_hands.get_child_count() <= index as i64 {
+        let hand = Node2D::new();
+        hand.set_position(Vector2::new(
+            player_hands.get_child_count() as f32 * HAND_SPACING,
+            0.0,
+        ));
+        player_hands.add_child(hand, false);
+    }
+    player_hands
+        .get_child(index as i64)
+        .map(|node| unsafe { node.assume_safe() })
+        .and_then(|node| node.cast::<Node2D>())
+        .ok_or(GodotError::FindNodeFailed(format!(
+            "Player hand {} not found",
+            index
+        )))
+}
+
+// Hands are laid out seat by seat, so a hand's node follows every hand of the
+// seats before it
+fn hand_node_index(context: &Context, id: HandId) -> usize {
+    context
+        .seats
+        .iter()
+        .take(id.seat)
+        .map(|seat| seat.player_hands.len())
+        .sum::<usize>()
+        + id.hand
+}
+
+fn show_switched_cards(
+    owner: TRef<Node2D>,
+    context: &Context,
+    seat: usize,
+) -> Result<(), GodotError> {
+    for (hand, player_hand) in context.seats[seat].player_hands.iter().enumerate() {
+        let node = player_hand_node(owner, hand_node_index(context, HandId::new(seat, hand)))?;
+        let second_card = node
+            .get_children()
+            .iter()
+            .filter_map(|var| var.try_to_object::<Sprite>())
+            .nth(1)
+            .map(|card| unsafe { card.assume_safe() })
+            .ok_or(GodotError::FindNodeFailed(
+                "Switched hand has no second card".to_string(),
+            ))?;
+        let dealt = player_hand.hand.cards();
+        let card = dealt.get(1).ok_or(GodotError::FindNodeFailed(
+            "Switched hand has no second card".to_string(),
+        ))?;
+        let texture = ResourceLoader::godot_singleton()
+            .load(texture_path_from_card(card), "AtlasTexture", false)
+            .and_then(|res| res.cast::<AtlasTexture>())
+            .expect("Couldn't load atlasTexture texture");
+        second_card.set_texture(texture);
+    }
+    Ok(())
+}
+
+fn split_player_hand(owner: TRef<Node2D>, index: usize) -> Result<(), GodotError> {
+    let player_hands = get_typed_node::<Node2D>("./PlayerHand", owner)?;
+    let hand = player_hand_node(owner, index)?;
+    let split_card = hand
+        .get_children()
+        .iter()
+        .filter_map(|var| var.try_to_object::<Sprite>())
+        .nth(1)
+        .map(|card| unsafe { card.assume_safe() })
+        .ok_or(GodotError::FindNodeFailed(
+            "Split hand has no second card".to_string(),
+        ))?;
+
+    let new_hand = Node2D::new();
+    let new_hand = unsafe { new_hand.assume_shared() };
+    player_hands.add_child(new_hand, false);
+    player_hands.move_child(new_hand, (index + 1) as i64);
+
+    hand.remove_child(split_card);
+    let new_hand = unsafe { new_hand.assume_safe() };
+    new_hand.add_child(split_card, false);
+    split_card.set_position(Vector2::new(0.0, 0.0));
+
+    for (position, var) in player_hands.get_children().iter().enumerate() {
+        var.try_to_object::<Node2D>().map(|node| {
+            let node = unsafe { node.assume_safe() };
+            node.set_position(Vector2::new(position as f32 * HAND_SPACING, 0.0));
+        });
+    }
+    Ok(())
+}
+
 fn sort_new_card_actions(mut actions: Vector<Action>) -> Vector<Action> {
     actions.sort_by(|a, b| match (a, b) {
-        (Action::NewDealerCards(_), Action::NewPlayerCard(_)) => Ordering::Greater,
-        (Action::NewPlayerCard(_), Action::NewDealerCards(_)) => Ordering::Less,
+        (Action::NewDealerCards(_), Action::NewPlayerCard(_, _)) => Ordering::Greater,
+        (Action::NewPlayerCard(_, _), Action::NewDealerCards(_)) => Ordering::Less,
+        (Action::NewDealerCards(_), Action::DoubleDown(_, _, _)) => Ordering::Greater,
+        (Action::DoubleDown(_, _, _), Action::NewDealerCards(_)) => Ordering::Less,
         _ => Ordering::Equal,
     });
     actions
@@ -113,7 +281,10 @@ fn filter_new_card_actions(actions: Vector<Action>) -> Vector<Action> {
     actions
         .iter()
         .filter(|action| match action {
-            Action::NewHand(_, _) | Action::NewDealerCards(_) | Action::NewPlayerCard(_) => true,
+            Action::NewHand(_, _)
+            | Action::NewDealerCards(_)
+            | Action::NewPlayerCard(_, _)
+            | Action::DoubleDown(_, _, _) => true,
             _ => false,
         })
         .cloned()
@@ -124,109 +295,415 @@ fn filter_new_card_actions(actions: Vector<Action>) -> Vector<Action> {
 struct CardAnimationProperties {
     destination_node: Ref<Node2D>,
     texture_name: String,
+    rotation_degrees: f64,
+}
+
+// The live game is parked in `live_state` while a recorded history is
+// shown, and `position` trails `target` while steps are still animating.
+struct Replay {
+    history: HandHistory,
+    live_state: GameState,
+    position: usize,
+    target: usize,
+    playing: bool,
+    elapsed: f64,
 }
 
 #[derive(NativeClass)]
 #[inherit(Node2D)]
 struct Blackjack {
     state: GameState,
+    history: HandHistory,
     actions: Vector<Action>,
     animations: Vector<CardAnimationProperties>,
+    advice: Option<(Hand, Card, Move)>,
+    replay: Option<Replay>,
 }
 
 #[methods]
 impl Blackjack {
     fn new(_owner: &Node2D) -> Self {
+        let history = HandHistory::default();
         Blackjack {
-            state: GameState::new(),
+            state: history
+                .initial_state()
+                .expect("A single seat table can always be set up"),
+            history,
             actions: vector![],
             animations: vector![],
+            advice: None,
+            replay: None,
         }
     }
 
-    #[export]
-    fn _on_new_game_pressed(&mut self, owner: TRef<Node2D>) {
+    fn play(&mut self, command: Command) -> Result<(), Box<dyn std::error::Error>> {
+        let (state, actions) = command.apply(&self.state)?;
+        self.history = self.history.record(&self.state, command, &actions);
+        self.state = state;
+        self.actions = actions;
+        Ok(())
+    }
+
+    fn clear_table(&mut self, owner: TRef<Node2D>) {
         clear_all_children("./DealerHand", owner);
         clear_all_children("./PlayerHand", owner);
         clear_result_text(owner);
+        self.actions.clear();
+        self.animations.clear();
+    }
 
-        let (state, actions) = deal(&self.state).expect("Dealing has to work, basically");
+    fn load_replay(
+        &mut self,
+        owner: TRef<Node2D>,
+        path: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let history = save::history_from_json(&fs::read_to_string(path)?)?;
+        let start = history.initial_state()?;
+        let live_state = std::mem::replace(&mut self.state, start);
+        let live_state = self
+            .replay
+            .take()
+            .map_or(live_state, |replay| replay.live_state);
+        self.replay = Some(Replay {
+            history,
+            live_state,
+            position: 0,
+            target: 0,
+            playing: false,
+            elapsed: 0.0,
+        });
+        self.clear_table(owner);
+        Ok(())
+    }
+
+    fn save_history(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, save::history_to_json(&self.history)?)?;
+        Ok(())
+    }
+
+    fn rewind_replay(
+        &mut self,
+        owner: TRef<Node2D>,
+        target: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(replay) = &mut self.replay {
+            let start = replay.history.hand_start(target.saturating_sub(1));
+            self.state = replay.history.replay(start)?;
+            replay.position = start;
+            replay.target = target;
+            replay.playing = false;
+        }
+        self.clear_table(owner);
+        Ok(())
+    }
+
+    fn advance_replay(
+        &mut self,
+        owner: TRef<Node2D>,
+        delta: f64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let idle = self.animations.is_empty() && self.actions.is_empty();
+        let replay = match &mut self.replay {
+            Some(replay) if idle => replay,
+            _ => return Ok(()),
+        };
+        let events = replay.history.events().len();
+        if replay.playing && replay.position == replay.target {
+            replay.elapsed += delta;
+            if replay.elapsed >= REPLAY_STEP_SECONDS {
+                replay.elapsed = 0.0;
+                replay.target = (replay.target + 1).min(events);
+                replay.playing = replay.target < events;
+            }
+        }
+        if replay.position >= replay.target {
+            return Ok(());
+        }
+
+        let opens_hand = replay.history.hand_start(replay.position) == replay.position;
+        let (state, actions) = replay.history.step(&self.state, replay.position)?;
+        replay.position += 1;
+        if opens_hand {
+            self.clear_table(owner);
+        }
         self.state = state;
         self.actions = actions;
+        Ok(())
+    }
+
+    fn basic_strategy_advice(&mut self) -> Option<Move> {
+        let context = match &self.state {
+            GameState::WaitingForPlayer(context) if context.dealer_hand.is_concealed() => {
+                return None
+            }
+            GameState::WaitingForPlayer(context) => context,
+            _ => return None,
+        };
+        let hand = context.player_hand();
+        let upcard = *context.dealer_hand.upcard()?;
+        match &self.advice {
+            Some((advised_hand, advised_upcard, advice))
+                if *advised_hand == hand && *advised_upcard == upcard =>
+            {
+                Some(*advice)
+            }
+            _ => {
+                let advice = basic_strategy(&hand, &upcard, context.rules());
+                self.advice = Some((hand, upcard, advice));
+                Some(advice)
+            }
+        }
+    }
+
+    #[export]
+    fn _on_new_game_pressed(&mut self, owner: TRef<Node2D>) {
+        if let Some(replay) = self.replay.take() {
+            self.state = replay.live_state;
+        }
+        self.clear_table(owner);
+
+        if let Err(error) = self.play(Command::Bet(0, BET_SIZE)) {
+            show_result_text(owner, &error.to_string());
+            return;
+        }
+        self.play(Command::Deal)
+            .expect("Dealing has to work, basically");
     }
 
     #[export]
     fn _on_stand_pressed(&mut self, _owner: TRef<Node2D>) {
-        let (state, actions) = stand(&self.state).expect("You could stand at this point");
-        self.state = state;
-        self.actions = actions;
+        self.play(Command::Stand)
+            .expect("You could stand at this point");
+    }
+
+    #[export]
+    fn _on_double_down_pressed(&mut self, owner: TRef<Node2D>) {
+        if let Err(error) = self.play(Command::DoubleDown) {
+            show_result_text(owner, &error.to_string());
+        }
+    }
+
+    #[export]
+    fn _on_split_pressed(&mut self, owner: TRef<Node2D>) {
+        if let Err(error) = self.play(Command::Split) {
+            show_result_text(owner, &error.to_string());
+        }
+    }
+
+    #[export]
+    fn _on_insurance_pressed(&mut self, owner: TRef<Node2D>) {
+        if let Err(error) = self.play(Command::TakeInsurance) {
+            show_result_text(owner, &error.to_string());
+        }
+    }
+
+    #[export]
+    fn _on_decline_insurance_pressed(&mut self, _owner: TRef<Node2D>) {
+        self.play(Command::DeclineInsurance)
+            .expect("You can decline insurance at this point");
+    }
+
+    #[export]
+    fn _on_surrender_pressed(&mut self, owner: TRef<Node2D>) {
+        if let Err(error) = self.play(Command::Surrender) {
+            show_result_text(owner, &error.to_string());
+        }
     }
 
     #[export]
     fn _on_hit_pressed(&mut self, _owner: TRef<Node2D>) {
-        let (state, actions) = hit(&self.state).expect("You can hit at this point");
-        self.state = state;
-        self.actions = actions;
+        self.play(Command::Hit).expect("You can hit at this point");
+    }
+
+    #[export]
+    fn _on_load_history_pressed(&mut self, owner: TRef<Node2D>) {
+        get_typed_node::<FileDialog>("./HistoryDialog", owner).map(|dialog| {
+            dialog.popup_centered(Vector2::new(0.0, 0.0));
+        });
+    }
+
+    #[export]
+    fn _on_history_dialog_file_selected(&mut self, owner: TRef<Node2D>, path: String) {
+        if let Err(error) = self.load_replay(owner, &path) {
+            show_result_text(owner, &error.to_string());
+        }
     }
 
     #[export]
-    fn _process(&mut self, owner: TRef<Node2D>, _delta: f64) {
+    fn _on_save_history_pressed(&mut self, owner: TRef<Node2D>) {
+        get_typed_node::<FileDialog>("./SaveHistoryDialog", owner).map(|dialog| {
+            dialog.popup_centered(Vector2::new(0.0, 0.0));
+        });
+    }
+
+    #[export]
+    fn _on_save_history_dialog_file_selected(&mut self, owner: TRef<Node2D>, path: String) {
+        if let Err(error) = self.save_history(&path) {
+            show_result_text(owner, &error.to_string());
+        }
+    }
+
+    #[export]
+    fn _on_replay_play_pressed(&mut self, _owner: TRef<Node2D>) {
+        if let Some(replay) = &mut self.replay {
+            replay.playing = !replay.playing;
+            replay.elapsed = REPLAY_STEP_SECONDS;
+        }
+    }
+
+    #[export]
+    fn _on_replay_back_pressed(&mut self, owner: TRef<Node2D>) {
+        let target = match &self.replay {
+            Some(replay) => replay.target.saturating_sub(1),
+            None => return,
+        };
+        if let Err(error) = self.rewind_replay(owner, target) {
+            show_result_text(owner, &error.to_string());
+        }
+    }
+
+    #[export]
+    fn _on_replay_forward_pressed(&mut self, _owner: TRef<Node2D>) {
+        if let Some(replay) = &mut self.replay {
+            replay.playing = false;
+            replay.target = (replay.target + 1).min(replay.history.events().len());
+        }
+    }
+
+    #[export]
+    fn _process(&mut self, owner: TRef<Node2D>, delta: f64) {
+        if let Err(error) = self.advance_replay(owner, delta) {
+            show_result_text(owner, &error.to_string());
+            if let Some(replay) = &mut self.replay {
+                replay.target = replay.position;
+                replay.playing = false;
+            }
+        }
         self.process_animations(owner);
         if self.animations.len() <= 0 {
             self.actions.iter().for_each(|action| match action {
                 Action::DealerBlackjack => {
                     show_result_text(owner, "Dealer blackjack!");
                 }
-                Action::DealerWins => {
+                Action::DealerWins(_) => {
                     show_result_text(owner, "Dealer..WINS!");
                 }
-                Action::Draw => {
+                Action::Draw(_) => {
                     show_result_text(owner, "Draws are like kissing your sister");
                 }
-                Action::PlayerWins => {
+                Action::PlayerWins(_) => {
                     show_result_text(owner, "Player..WINS!");
                 }
                 Action::ShowDealerHoleCard(hole_card) => {
-                    show_dealer_hole_card(owner, &texture_path_from_card(&hole_card));
+                    show_dealer_card(
+                        owner,
+                        DEALER_HOLE_CARD_CHILD,
+                        &texture_path_from_card(&hole_card),
+                    );
                 }
-                Action::PlayerBusts => {
+                Action::ShowDealerUpcard(upcard) => {
+                    show_dealer_card(owner, DEALER_UPCARD_CHILD, &texture_path_from_card(&upcard));
+                }
+                Action::PlayerBusts(_) => {
                     show_result_text(owner, "Player busts, Dealer WINS!");
                 }
-                Action::PlayerBlackjack => {
+                Action::PlayerBlackjack(_) => {
                     show_result_text(owner, "Player has blackjack!");
                 }
                 Action::DealerBusts => {
                     show_result_text(owner, "Dealer busts...Player WINS!");
                 }
+                Action::WagerWon(id, chips) => {
+                    show_result_text(
+                        owner,
+                        &format!(" Hand {} wins {} chips", id.hand + 1, chips.0),
+                    );
+                }
+                Action::WagerPushed(id, chips) => {
+                    show_result_text(
+                        owner,
+                        &format!(" Hand {} has {} chips returned", id.hand + 1, chips.0),
+                    );
+                }
+                Action::WagerLost(id, chips) => {
+                    show_result_text(
+                        owner,
+                        &format!(" Hand {} loses {} chips", id.hand + 1, chips.0),
+                    );
+                }
+                Action::Reshuffle => {
+                    show_result_text(owner, "Reshuffling the shoe. ");
+                }
+                Action::InsuranceOffered => {
+                    show_result_text(owner, "Dealer shows an ace. Insurance?");
+                }
+                Action::InsuranceWon(_, chips) => {
+                    show_result_text(owner, &format!("Insurance pays {} chips. ", chips.0));
+                }
+                Action::InsuranceLost(_, chips) => {
+                    show_result_text(owner, &format!("Insurance loses {} chips. ", chips.0));
+                }
+                Action::EvenMoney(_) => {
+                    show_result_text(owner, "Even money!");
+                }
+                Action::DoubleDown(_, wager, _) => {
+                    show_result_text(owner, &format!("Doubled down to {} chips. ", wager.0));
+                }
+                Action::CardBought(_, wager) => {
+                    show_result_text(owner, &format!("Bought a card for {} chips. ", wager.0));
+                }
+                Action::PlayerSurrenders(_) => {
+                    show_result_text(owner, "Player surrenders.");
+                }
+                Action::WagerSurrendered(id, chips) => {
+                    show_result_text(
+                        owner,
+                        &format!(" Hand {} gets {} chips back", id.hand + 1, chips.0),
+                    );
+                }
                 _ => {}
             });
             self.actions.clear();
+            show_bankroll(owner, self.state.context().seat().bankroll.balance());
         }
 
-        match &self.state {
-            GameState::WaitingForPlayer(_) => {
-                get_typed_node::<ToolButton>("./Hit", owner).map(|node| {
-                    node.set_disabled(false);
-                });
-                get_typed_node::<ToolButton>("./Stand", owner).map(|node| {
-                    node.set_disabled(false);
-                });
-                get_typed_node::<ToolButton>("./NewGame", owner).map(|node| {
-                    node.set_disabled(true);
-                });
-            }
-            _ => {
-                get_typed_node::<ToolButton>("./Hit", owner).map(|node| {
-                    node.set_disabled(true);
-                });
-                get_typed_node::<ToolButton>("./Stand", owner).map(|node| {
-                    node.set_disabled(true);
-                });
-                get_typed_node::<ToolButton>("./NewGame", owner).map(|node| {
-                    node.set_disabled(false);
-                });
-            }
-        }
+        let replaying = self.replay.is_some();
+        let (player_turn, offering_insurance) = match &self.state {
+            _ if replaying => (false, false),
+            GameState::WaitingForPlayer(_) => (true, false),
+            GameState::OfferingInsurance(_) => (false, true),
+            _ => (false, false),
+        };
+        let context = self.state.context();
+        set_button_disabled(owner, "./Hit", !(player_turn && context.can_hit()));
+        set_button_disabled(owner, "./Stand", !player_turn);
+        set_button_disabled(
+            owner,
+            "./DoubleDown",
+            !(player_turn && context.can_double_down()),
+        );
+        set_button_disabled(owner, "./Split", !(player_turn && context.can_split()));
+        set_button_disabled(owner, "./Insurance", !offering_insurance);
+        set_button_disabled(owner, "./DeclineInsurance", !offering_insurance);
+        set_button_disabled(
+            owner,
+            "./Surrender",
+            replaying || !self.state.can_surrender(),
+        );
+        set_button_disabled(owner, "./NewGame", player_turn || offering_insurance);
+        set_button_disabled(owner, "./LoadHistory", player_turn || offering_insurance);
+        set_button_disabled(owner, "./ReplayPlay", !replaying);
+        set_button_disabled(owner, "./ReplayBack", !replaying);
+        set_button_disabled(owner, "./ReplayForward", !replaying);
+        let playing = self.replay.as_ref().map_or(false, |replay| replay.playing);
+        set_button_text(
+            owner,
+            "./ReplayPlay",
+            if playing { "Pause" } else { "Play" },
+        );
+        show_replay_position(owner, self.replay.as_ref());
+        let advice = self.basic_strategy_advice();
+        highlight_basic_strategy(owner, advice);
     }
 
     #[export]
@@ -238,29 +715,67 @@ impl Blackjack {
     }
 
     fn process_animations(&mut self, owner: TRef<Node2D>) {
+        let context = self.state.context();
+        self.actions.iter().for_each(|action| match action {
+            Action::Split(id) => {
+                split_player_hand(owner, hand_node_index(context, *id))
+                    .expect("Error splitting hand");
+            }
+            Action::Switch(seat) => {
+                show_switched_cards(owner, context, *seat).expect("Error switching cards");
+            }
+            _ => {}
+        });
+
         let deal_actions = filter_new_card_actions(self.actions.clone());
         let deal_actions = sort_new_card_actions(deal_actions);
 
         let mut animations = deal_actions
             .iter()
             .filter_map(|action| match action {
-                Action::NewHand(player_hand, dealer_hand) => {
-                    let mut player_animations = self
-                        .get_animations_for_player_cards(owner, &player_hand.cards())
-                        .expect("Error getting animations");
+                Action::NewHand(player_hands, dealer_hand) => {
                     let dealer_animations = self
                         .get_animations_for_initial_dealer_hand(owner, &dealer_hand)
                         .expect("Error getting animations");
-                    player_animations.extend(dealer_animations);
-                    Some(player_animations)
+                    // Deal round by round: a card to every hand, then the dealer
+                    let mut animations = Vector::new();
+                    for round in 0..2 {
+                        for (index, hand) in player_hands.iter().enumerate() {
+                            if let Some(card) = hand.cards().get(round) {
+                                animations.push_back(
+                                    self.get_animation_for_player_card(owner, index, *card)
+                                        .expect("Error getting animations"),
+                                );
+                            }
+                        }
+                        animations.extend(dealer_animations.get(round).cloned());
+                    }
+                    Some(animations)
                 }
                 Action::NewDealerCards(cards) => {
                     self.get_animations_for_dealer_cards(owner, cards).ok()
                 }
-                Action::NewPlayerCard(player_card) => self
-                    .get_animation_for_player_card(owner, *player_card)
+                Action::NewPlayerCard(id, player_card) => self
+                    .get_animation_for_player_card(
+                        owner,
+                        hand_node_index(context, *id),
+                        *player_card,
+                    )
                     .map(|card| vector![card])
                     .ok(),
+                Action::DoubleDown(id, _, player_card) => self
+                    .get_animation_for_player_card(
+                        owner,
+                        hand_node_index(context, *id),
+                        *player_card,
+                    )
+                    .map(|card| {
+                        vector![CardAnimationProperties {
+                            rotation_degrees: SIDEWAYS_ROTATION,
+                            ..card
+                        }]
+                    })
+                    .ok(),
                 _ => None,
             })
             .flatten()
@@ -278,9 +793,12 @@ impl Blackjack {
             .actions
             .iter()
             .filter(|action| match action {
-                Action::NewDealerCards(_) | Action::NewPlayerCard(_) | Action::NewHand(_, _) => {
-                    false
-                }
+                Action::NewDealerCards(_)
+                | Action::NewPlayerCard(_, _)
+                | Action::NewHand(_, _)
+                | Action::DoubleDown(_, _, _)
+                | Action::Split(_)
+                | Action::Switch(_) => false,
                 _ => true,
             })
             .cloned()
@@ -290,9 +808,10 @@ impl Blackjack {
     fn get_animation_for_player_card(
         &self,
         owner: TRef<Node2D>,
+        index: usize,
         player_card: Card,
     ) -> Result<CardAnimationProperties, GodotError> {
-        self.get_animations_for_player_cards(owner, &vector![player_card])
+        self.get_animations_for_player_cards(owner, index, &vector![player_card])
             .and_then(|mut animations| {
                 let animation = animations.pop_front();
                 animation.ok_or(GodotError::FindNodeFailed("No animations".to_string()))
@@ -302,14 +821,16 @@ impl Blackjack {
     fn get_animations_for_player_cards(
         &self,
         owner: TRef<Node2D>,
+        index: usize,
         player_cards: &Vector<Card>,
     ) -> Result<Vector<CardAnimationProperties>, GodotError> {
-        get_typed_node::<Node2D>("./PlayerHand", owner).map(|player_hand| {
+        player_hand_node(owner, index).map(|player_hand| {
             player_cards
                 .iter()
                 .map(|card| CardAnimationProperties {
                     destination_node: unsafe { player_hand.assume_shared() },
                     texture_name: texture_path_from_card(card),
+                    rotation_degrees: DEALT_ROTATION,
                 })
                 .collect()
         })
@@ -326,6 +847,7 @@ impl Blackjack {
                 .map(|card| CardAnimationProperties {
                     destination_node: unsafe { dealer_node.assume_shared() },
                     texture_name: texture_path_from_card(card),
+                    rotation_degrees: DEALT_ROTATION,
                 })
                 .collect()
         })
@@ -338,18 +860,30 @@ impl Blackjack {
     ) -> Result<Vector<CardAnimationProperties>, GodotError> {
         get_typed_node::<Node2D>("./DealerHand", owner).map(|dealer_node| {
             let dealer_node = unsafe { dealer_node.assume_shared() };
-            vector![
-                CardAnimationProperties {
-                    destination_node: dealer_node,
-                    texture_name: String::from(
-                        "res://images/playingCardBacks.cardBack_blue1.atlastex"
-                    )
-                },
-                CardAnimationProperties {
-                    destination_node: dealer_node,
-                    texture_name: texture_path_from_card(&dealer_hand.upcard().unwrap())
-                }
-            ]
+            let card_back = String::from("res://images/playingCardBacks.cardBack_blue1.atlastex");
+            // Without a hole card the dealer starts with the upcard alone
+            dealer_hand
+                .cards()
+                .iter()
+                .enumerate()
+                .map(|(position, card)| {
+                    let is_hole_card = dealer_hand.has_hole_card() && position == 0;
+                    let face_down = if is_hole_card {
+                        !dealer_hand.is_exposed()
+                    } else {
+                        dealer_hand.is_concealed()
+                    };
+                    CardAnimationProperties {
+                        destination_node: dealer_node,
+                        texture_name: if face_down {
+                            card_back.clone()
+                        } else {
+                            texture_path_from_card(card)
+                        },
+                        rotation_degrees: DEALT_ROTATION,
+                    }
+                })
+                .collect()
         })
     }
 
@@ -363,7 +897,8 @@ impl Blackjack {
             .expect("Couldn't load atlasTexture texture");
 
         sprite.set_texture(texture);
-        sprite.set_position(Vector2::new(-hand.position().x, -hand.position().y));
+        let origin = Vector2::new(-hand.global_position().x, -hand.global_position().y);
+        sprite.set_position(origin);
 
         let sprite = unsafe { sprite.assume_shared() };
 
@@ -374,7 +909,7 @@ impl Blackjack {
         tween.interpolate_property(
             sprite,
             "position",
-            Vector2::new(-hand.position().x, -hand.position().y),
+            origin,
             Vector2::new(child_count * 35.0, 0.0),
             0.25,
             Tween::TRANS_LINEAR,
@@ -386,7 +921,7 @@ impl Blackjack {
             sprite,
             "rotation_degrees",
             0.0,
-            360.0,
+            props.rotation_degrees,
             0.25,
             Tween::TRANS_LINEAR,
             Tween::EASE_IN,
@@ -418,6 +953,7 @@ godot_init!(init);
 mod godot_lib {
     use super::*;
     use blackjack::deck::Suit;
+    use blackjack::game::HandId;
     use blackjack::hand::{DealerHand, Hand};
 
     #[test]
@@ -511,7 +1047,7 @@ mod godot_lib {
         };
         let actions = vector![
             Action::NewDealerCards(vector![]),
-            Action::NewPlayerCard(irrelevant_card)
+            Action::NewPlayerCard(HandId::new(0, 0), irrelevant_card)
         ];
 
         let new_actions = sort_new_card_actions(actions);
@@ -519,7 +1055,7 @@ mod godot_lib {
         assert_eq!(
             new_actions,
             vector![
-                Action::NewPlayerCard(irrelevant_card),
+                Action::NewPlayerCard(HandId::new(0, 0), irrelevant_card),
                 Action::NewDealerCards(vector![])
             ]
         )
@@ -534,9 +1070,9 @@ mod godot_lib {
         let hand = Hand::new();
         let dealer_hand = DealerHand::new();
         let actions = vector![
-            Action::NewHand(hand, dealer_hand),
+            Action::NewHand(vector![hand], dealer_hand),
             Action::NewDealerCards(vector![]),
-            Action::NewPlayerCard(irrelevant_card)
+            Action::NewPlayerCard(HandId::new(0, 0), irrelevant_card)
         ];
 
         assert_eq!(actions, filter_new_card_actions(actions.clone()))
@@ -544,8 +1080,82 @@ mod godot_lib {
 
     #[test]
     fn filter_new_card_actions_removes_anything_else() {
-        let actions = vector![Action::DealerWins, Action::Draw, Action::PlayerWins];
+        let actions = vector![
+            Action::DealerWins(HandId::new(0, 0)),
+            Action::Draw(HandId::new(0, 0)),
+            Action::PlayerWins(HandId::new(0, 0))
+        ];
 
         assert_eq!(vector![], filter_new_card_actions(actions));
     }
+
+    #[test]
+    fn filter_new_card_actions_keeps_double_down() {
+        let irrelevant_card = Card {
+            rank: Rank::Jack,
+            suit: Suit::Club,
+        };
+        let actions = vector![
+            Action::DoubleDown(HandId::new(0, 0), Chips(20), irrelevant_card),
+            Action::WagerWon(HandId::new(0, 0), Chips(20))
+        ];
+
+        assert_eq!(
+            vector![Action::DoubleDown(
+                HandId::new(0, 0),
+                Chips(20),
+                irrelevant_card
+            )],
+            filter_new_card_actions(actions)
+        );
+    }
+
+    #[test]
+    fn sorting_actions_puts_double_down_card_before_dealer_cards() {
+        let irrelevant_card = Card {
+            rank: Rank::Jack,
+            suit: Suit::Club,
+        };
+        let actions = vector![
+            Action::NewDealerCards(vector![]),
+            Action::DoubleDown(HandId::new(0, 0), Chips(20), irrelevant_card)
+        ];
+
+        let new_actions = sort_new_card_actions(actions);
+
+        assert_eq!(
+            new_actions,
+            vector![
+                Action::DoubleDown(HandId::new(0, 0), Chips(20), irrelevant_card),
+                Action::NewDealerCards(vector![])
+            ]
+        )
+    }
+
+    #[test]
+    fn filter_new_card_actions_removes_wager_actions() {
+        let actions = vector![
+            Action::WagerPlaced(0, Chips(10)),
+            Action::WagerLost(HandId::new(0, 0), Chips(10))
+        ];
+
+        assert_eq!(vector![], filter_new_card_actions(actions));
+    }
+
+    #[test]
+    fn each_hand_at_the_table_has_its_own_node() -> Result<(), Box<dyn std::error::Error>> {
+        use blackjack::game::{bet, deal};
+        use blackjack::rules::TableRules;
+
+        let game = GameState::new_table(TableRules::blackjack_switch(), 2, 1)?;
+        let (game, _) = bet(&game, 0, Chips(10))?;
+        let (game, _) = bet(&game, 1, Chips(10))?;
+        let (game, _) = deal(&game)?;
+        let context = game.context();
+
+        assert_eq!(hand_node_index(context, HandId::new(0, 1)), 1);
+        assert_eq!(hand_node_index(context, HandId::new(1, 0)), 2);
+        assert_eq!(hand_node_index(context, HandId::new(1, 1)), 3);
+        Ok(())
+    }
 }